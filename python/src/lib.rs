@@ -0,0 +1,173 @@
+//! Python bindings for `rfc2289-otp`, built with PyO3.
+//!
+//! OTP values are passed to and from Python as 8-byte `bytes` objects. Invalid
+//! input raises `ValueError`.
+//!
+//! To build and install the extension into the current virtual environment:
+//!
+//! ```text
+//! cd python && maturin develop
+//! ```
+use std::borrow::Cow;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use rfc2289_otp::{
+    calculate_otp_with_options,
+    decode_word_str,
+    format_hex,
+    otp_to_word_string,
+    parse_otp_challenge,
+    parse_otp_response,
+    CalculateOptions,
+    HexStyle,
+    OTPResponse,
+    WordDecodeError,
+};
+
+/// A parsed OTP challenge, such as `otp-md5 499 ke1234`.
+#[pyclass(frozen, get_all, eq, module = "rfc2289_otp")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Challenge {
+    /// The name of the hash algorithm, such as `md5`.
+    pub alg: String,
+    /// The hash count.
+    pub count: usize,
+    /// The seed.
+    pub seed: String,
+}
+
+#[pymethods]
+impl Challenge {
+
+    fn __repr__ (&self) -> String {
+        format!("Challenge(alg={:?}, count={}, seed={:?})", self.alg, self.count, self.seed)
+    }
+
+}
+
+/// A parsed OTP response, with its OTP values decoded. The `new_*` attributes
+/// are only set for init responses.
+#[pyclass(frozen, get_all, eq, module = "rfc2289_otp")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Response {
+    /// The current OTP value.
+    pub otp: Cow<'static, [u8]>,
+    /// The new OTP value, if this is an init response.
+    pub new_otp: Option<Cow<'static, [u8]>>,
+    /// The new hash algorithm, if this is an init response.
+    pub new_alg: Option<String>,
+    /// The new hash count, if this is an init response.
+    pub new_count: Option<usize>,
+    /// The new seed, if this is an init response.
+    pub new_seed: Option<String>,
+}
+
+fn to_otp (otp: &[u8]) -> PyResult<&[u8; 8]> {
+    otp.try_into().map_err(|_| PyValueError::new_err("OTP values must be 8 bytes"))
+}
+
+/// Calculate an OTP value.
+#[pyfunction]
+fn calculate_otp (alg: &str, passphrase: &str, seed: &str, count: usize) -> PyResult<Cow<'static, [u8]>> {
+    calculate_otp_with_options(alg, passphrase, seed, count, &CalculateOptions::default())
+        .map(|otp| Cow::Owned(otp.to_vec()))
+        .map_err(|e| PyValueError::new_err(e.to_string()))
+}
+
+/// Parse an OTP challenge string.
+#[pyfunction]
+fn parse_challenge (challenge: &str) -> PyResult<Challenge> {
+    let c = parse_otp_challenge(challenge)
+        .ok_or_else(|| PyValueError::new_err("invalid OTP challenge"))?;
+    Ok(Challenge {
+        alg: c.hash_alg.to_owned(),
+        count: c.hash_count,
+        seed: c.seed.to_owned(),
+    })
+}
+
+/// Parse an OTP response string, such as `hex:5Bf0 75d9 959d 036f`, and decode
+/// its OTP values.
+#[pyfunction]
+fn parse_response (response: &str) -> PyResult<Response> {
+    let r = parse_otp_response(response)
+        .ok_or_else(|| PyValueError::new_err("invalid OTP response"))?;
+    let decode_err = |e: WordDecodeError| PyValueError::new_err(e.to_string());
+    match r {
+        OTPResponse::Current(otp) => Ok(Response {
+            otp: Cow::Owned(otp.decode().map_err(decode_err)?.to_vec()),
+            new_otp: None,
+            new_alg: None,
+            new_count: None,
+            new_seed: None,
+        }),
+        OTPResponse::Init(init) => Ok(Response {
+            otp: Cow::Owned(init.current_otp.decode().map_err(decode_err)?.to_vec()),
+            new_otp: Some(Cow::Owned(init.new_otp.decode().map_err(decode_err)?.to_vec())),
+            new_alg: Some(init.new_alg.to_owned()),
+            new_count: Some(init.new_seq_num),
+            new_seed: Some(init.new_seed.to_owned()),
+        }),
+    }
+}
+
+/// Convert an OTP value to six words from the standard dictionary.
+#[pyfunction]
+#[pyo3(signature = (otp, with_prefix = false))]
+fn otp_to_words (otp: &[u8], with_prefix: bool) -> PyResult<String> {
+    Ok(otp_to_word_string(to_otp(otp)?, with_prefix))
+}
+
+/// Convert an OTP value to four groups of four uppercase hexadecimal digits.
+#[pyfunction]
+#[pyo3(signature = (otp, with_prefix = false))]
+fn otp_to_hex (otp: &[u8], with_prefix: bool) -> PyResult<String> {
+    let style = if with_prefix { HexStyle::CANONICAL.with_prefix() } else { HexStyle::CANONICAL };
+    Ok(format_hex(to_otp(otp)?, style))
+}
+
+/// Convert six words from the standard dictionary to an OTP value.
+#[pyfunction]
+fn words_to_otp (words: &str) -> PyResult<Cow<'static, [u8]>> {
+    decode_word_str(words)
+        .map(|otp| Cow::Owned(otp.to_vec()))
+        .map_err(|e| PyValueError::new_err(e.to_string()))
+}
+
+/// One Time Passwords (OTP) per IETF RFC 2289.
+#[pymodule]
+#[pyo3(name = "rfc2289_otp")]
+fn rfc2289_otp_python (m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<Challenge>()?;
+    m.add_class::<Response>()?;
+    m.add_function(wrap_pyfunction!(calculate_otp, m)?)?;
+    m.add_function(wrap_pyfunction!(parse_challenge, m)?)?;
+    m.add_function(wrap_pyfunction!(parse_response, m)?)?;
+    m.add_function(wrap_pyfunction!(otp_to_words, m)?)?;
+    m.add_function(wrap_pyfunction!(otp_to_hex, m)?)?;
+    m.add_function(wrap_pyfunction!(words_to_otp, m)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_values () {
+        let otp = calculate_otp("md5", "OTP's are good", "correct", 99).unwrap();
+        assert_eq!(otp.as_ref(), [ 0xB2, 0x03, 0xE2, 0x8F, 0xA5, 0x25, 0xBE, 0x47 ]);
+        assert_eq!(otp_to_words(&otp, false).unwrap(), "LONG IVY JULY AJAR BOND LEE");
+        assert_eq!(otp_to_hex(&otp, true).unwrap(), "hex:B203 E28F A525 BE47");
+        assert_eq!(words_to_otp("LONG IVY JULY AJAR BOND LEE").unwrap(), otp);
+        assert_eq!(parse_challenge("otp-md5 99 correct").unwrap(), Challenge {
+            alg: "md5".to_owned(),
+            count: 99,
+            seed: "correct".to_owned(),
+        });
+        let r = parse_response("word:LONG IVY JULY AJAR BOND LEE").unwrap();
+        assert_eq!(r.otp, otp);
+        assert_eq!(r.new_otp, None);
+    }
+
+}
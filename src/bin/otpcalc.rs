@@ -0,0 +1,173 @@
+//! `otpcalc`: calculate OTP responses, like `opiekey` and `skey`.
+//!
+//! ```text
+//! otpcalc [-4 | -5 | -a ALG] [-n N] <count> <seed>
+//! echo "otp-md5 499 ke1234" | otpcalc
+//! ```
+//!
+//! The passphrase is read from the terminal without echo. With `-n N`, the
+//! responses for the `N` counts up to and including `count` are printed.
+use std::io::{self, BufRead};
+use std::process::ExitCode;
+use rfc2289_otp::{
+    advance_otp,
+    calculate_otp_with_options,
+    format_hex,
+    otp_to_word_string,
+    parse_otp_challenge,
+    CalculateError,
+    CalculateOptions,
+    HashAlg,
+    HexStyle,
+    DEFAULT_MAX_COUNT,
+};
+
+const USAGE: &str = "usage: otpcalc [-4 | -5 | -a ALG] [-n N] [<count> <seed>]";
+
+/// What to calculate, as given on the command line or on stdin.
+#[derive(Debug, PartialEq, Eq)]
+struct Request {
+    alg: String,
+    count: usize,
+    seed: String,
+    n: usize,
+}
+
+/// Parse the command line arguments, excluding the program name. Returns
+/// `Ok(None)` if the challenge should be read from stdin instead.
+fn parse_args <I: IntoIterator<Item = String>> (args: I) -> Result<Option<Request>, String> {
+    let mut alg = "md5".to_owned();
+    let mut n = 1;
+    let mut positional = Vec::new();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-4" => alg = "md4".to_owned(),
+            "-5" => alg = "md5".to_owned(),
+            "-a" | "--alg" => alg = args.next().ok_or("-a requires an algorithm")?,
+            "-n" => {
+                n = args.next()
+                    .and_then(|s| s.parse::<usize>().ok())
+                    .filter(|n| *n > 0)
+                    .ok_or("-n requires a positive number")?;
+            },
+            "-h" | "--help" => return Err(USAGE.to_owned()),
+            s if s.starts_with('-') && s.len() > 1 => return Err(format!("unrecognized option {s}\n{USAGE}")),
+            _ => positional.push(arg),
+        }
+    }
+    match positional.as_slice() {
+        [] => Ok(None),
+        [count, seed] => {
+            let count = count.parse::<usize>().map_err(|_| format!("invalid count {count}"))?;
+            Ok(Some(Request { alg, count, seed: seed.to_owned(), n }))
+        },
+        _ => Err(USAGE.to_owned()),
+    }
+}
+
+/// Calculate the OTP values for the `n` counts up to and including `count`,
+/// lowest count first, by calculating the lowest count and then hashing once
+/// more for each count after it.
+fn calculate_responses (req: &Request, passphrase: &str) -> Result<Vec<(usize, [u8; 8])>, CalculateError> {
+    let alg = HashAlg::from_name(&req.alg).ok_or(CalculateError::UnsupportedAlgorithm)?;
+    if req.count > DEFAULT_MAX_COUNT {
+        return Err(CalculateError::CountTooLarge(DEFAULT_MAX_COUNT));
+    }
+    let first = req.count.saturating_sub(req.n - 1);
+    let mut otp = calculate_otp_with_options(&req.alg, passphrase, &req.seed, first, &CalculateOptions::default())?;
+    let mut otps = Vec::with_capacity(req.count - first + 1);
+    otps.push((first, otp));
+    for count in first + 1..=req.count {
+        otp = advance_otp(alg, &otp, 1).ok_or(CalculateError::UnsupportedAlgorithm)?;
+        otps.push((count, otp));
+    }
+    Ok(otps)
+}
+
+fn run () -> Result<(), String> {
+    let req = match parse_args(std::env::args().skip(1))? {
+        Some(req) => req,
+        None => {
+            let mut line = String::new();
+            io::stdin().lock().read_line(&mut line).map_err(|e| e.to_string())?;
+            let c = parse_otp_challenge(line.trim()).ok_or("invalid OTP challenge")?;
+            Request {
+                alg: c.hash_alg.to_owned(),
+                count: c.hash_count,
+                seed: c.seed.to_owned(),
+                n: 1,
+            }
+        },
+    };
+    let passphrase = rpassword::prompt_password("Enter secret pass phrase: ")
+        .map_err(|e| e.to_string())?;
+    for (count, otp) in calculate_responses(&req, &passphrase).map_err(|e| e.to_string())? {
+        if req.n > 1 {
+            print!("{count}: ");
+        }
+        println!("{}", otp_to_word_string(&otp, false));
+        if req.n > 1 {
+            print!("{}  ", " ".repeat(count.to_string().len()));
+        }
+        println!("{}", format_hex(&otp, HexStyle::CANONICAL));
+    }
+    Ok(())
+}
+
+fn main () -> ExitCode {
+    match run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("otpcalc: {e}");
+            ExitCode::FAILURE
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args (s: &str) -> Vec<String> {
+        s.split_whitespace().map(str::to_owned).collect()
+    }
+
+    #[test]
+    fn parses_arguments () {
+        assert_eq!(parse_args(args("499 ke1234")), Ok(Some(Request {
+            alg: "md5".to_owned(),
+            count: 499,
+            seed: "ke1234".to_owned(),
+            n: 1,
+        })));
+        assert_eq!(parse_args(args("-4 -n 5 499 ke1234")), Ok(Some(Request {
+            alg: "md4".to_owned(),
+            count: 499,
+            seed: "ke1234".to_owned(),
+            n: 5,
+        })));
+        assert_eq!(parse_args(args("-a sha1 499 ke1234")).unwrap().unwrap().alg, "sha1");
+        assert_eq!(parse_args(args("")), Ok(None));
+        assert!(parse_args(args("499")).is_err());
+        assert!(parse_args(args("-n 0 499 ke1234")).is_err());
+        assert!(parse_args(args("-x 499 ke1234")).is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "md5")]
+    fn calculates_consecutive_responses () {
+        let req = Request { alg: "md5".to_owned(), count: 2, seed: "TeSt".to_owned(), n: 5 };
+        let otps = calculate_responses(&req, "This is a test.").unwrap();
+        assert_eq!(otps.iter().map(|(count, _)| *count).collect::<Vec<_>>(), [ 0, 1, 2 ]);
+        for (count, otp) in otps {
+            let expected = calculate_otp_with_options("md5", "This is a test.", "TeSt", count, &CalculateOptions::default());
+            assert_eq!(Ok(otp), expected);
+        }
+        let req = Request { alg: "md5".to_owned(), count: DEFAULT_MAX_COUNT + 1, seed: "TeSt".to_owned(), n: 2 };
+        assert_eq!(calculate_responses(&req, "This is a test."), Err(CalculateError::CountTooLarge(DEFAULT_MAX_COUNT)));
+        let req = Request { alg: "x-foo".to_owned(), count: 2, seed: "TeSt".to_owned(), n: 1 };
+        assert_eq!(calculate_responses(&req, "This is a test."), Err(CalculateError::UnsupportedAlgorithm));
+    }
+
+}
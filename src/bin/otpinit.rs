@@ -0,0 +1,301 @@
+//! `otpinit`: create or re-key a user's entry in an OPIE key file, like
+//! `opiepasswd`.
+//!
+//! ```text
+//! otpinit [-n COUNT] [-s SEED] [-f FILE] <user>
+//! ```
+//!
+//! The passphrase is read from the terminal without echo, twice. A random seed
+//! is generated unless one is given. The entry stores the OTP value for
+//! `COUNT`, which defaults to 499, so the first challenge will be for
+//! `COUNT - 1`. Since OPIE key files do not record the hash algorithm, MD5 is
+//! always used. As when a server accepts an init response, the user's new
+//! seed may not be the seed of their current sequence.
+use std::fs;
+use std::io::{self, Write};
+use std::process::ExitCode;
+use std::time::{SystemTime, UNIX_EPOCH};
+use rand_core::OsRng;
+use rfc2289_otp::{
+    calculate_otp_with_alg,
+    check_passphrase,
+    generate_seed,
+    opiekeys::{parse_opiekeys_line, OpieKeyEntry, DEFAULT_OPIEKEYS_PATH},
+    server::{import_state, OtpState},
+    HashAlg,
+    Seed,
+    DEFAULT_INITIAL_COUNT,
+};
+
+const USAGE: &str = "usage: otpinit [-n COUNT] [-s SEED] [-f FILE] <user>";
+
+/// The permissions of a new key file, which only its owner may read or write,
+/// since anyone who can read it can mount a dictionary attack on the
+/// passphrases of its users. An existing key file keeps its permissions.
+#[cfg(unix)]
+const NEW_KEY_FILE_MODE: u32 = 0o600;
+
+/// The options given on the command line.
+#[derive(Debug, PartialEq, Eq)]
+struct Options {
+    user: String,
+    count: usize,
+    seed: Option<Seed>,
+    file: String,
+}
+
+/// Parse the command line arguments, excluding the program name.
+fn parse_args <I: IntoIterator<Item = String>> (args: I) -> Result<Options, String> {
+    let mut count = DEFAULT_INITIAL_COUNT;
+    let mut seed = None;
+    let mut file = DEFAULT_OPIEKEYS_PATH.to_owned();
+    let mut user = None;
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-n" => {
+                count = args.next()
+                    .and_then(|s| s.parse::<usize>().ok())
+                    .filter(|n| (2..=9999).contains(n))
+                    .ok_or("-n requires a number from 2 to 9999")?;
+            },
+            "-s" => {
+                seed = Some(args.next()
+                    .as_deref()
+                    .and_then(Seed::new)
+                    .ok_or("-s requires one to sixteen alphanumeric characters")?);
+            },
+            "-f" => file = args.next().ok_or("-f requires a file name")?,
+            "-h" | "--help" => return Err(USAGE.to_owned()),
+            s if s.starts_with('-') => return Err(format!("unrecognized option {s}\n{USAGE}")),
+            _ if user.is_none() => user = Some(arg),
+            _ => return Err(USAGE.to_owned()),
+        }
+    }
+    let user = user.ok_or(USAGE)?;
+    if user.contains(char::is_whitespace) {
+        return Err("user names may not contain whitespace".to_owned());
+    }
+    Ok(Options { user, count, seed, file })
+}
+
+/// Format a time as OPIE does, such as `Apr 01,2024 12:00:00`, in UTC.
+fn format_date (secs: u64) -> String {
+    const MONTHS: [&str; 12] = [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun",
+        "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ];
+    let days = (secs / 86400) as i64;
+    let rem = secs % 86400;
+    // Convert days since the epoch to a civil date, per Howard Hinnant's
+    // `civil_from_days` algorithm.
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!(
+        "{} {:02},{} {:02}:{:02}:{:02}",
+        MONTHS[(month - 1) as usize], day, year, rem / 3600, rem / 60 % 60, rem % 60,
+    )
+}
+
+/// The state of `user`'s current sequence in `contents`, if they have one.
+fn current_state (contents: &str, user: &str) -> Option<OtpState> {
+    contents.lines()
+        .filter_map(import_state)
+        .find(|imported| imported.user == user)
+        .map(|imported| imported.state)
+}
+
+/// Replace `path` with `contents` by writing them to a new, uniquely named
+/// file beside it and renaming that over it, so that the key file is never
+/// left partially written.
+fn write_key_file (path: &str, contents: &str) -> io::Result<()> {
+    let existing = match fs::metadata(path) {
+        Ok(metadata) => Some(metadata.permissions()),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+        Err(e) => return Err(e),
+    };
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_nanos()).unwrap_or(0);
+    let tmp = format!("{}.{}.{}.tmp", path, std::process::id(), nanos);
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, NEW_KEY_FILE_MODE);
+    let mut f = options.open(&tmp)?;
+    let result = f.write_all(contents.as_bytes())
+        .and_then(|_| match existing {
+            Some(permissions) => fs::set_permissions(&tmp, permissions),
+            None => Ok(()),
+        })
+        .and_then(|_| f.sync_all())
+        .and_then(|_| fs::rename(&tmp, path));
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp);
+    }
+    result
+}
+
+/// Replace the line for `user` in `contents` with `line`, or append it.
+fn replace_entry (contents: &str, user: &str, line: &str) -> String {
+    let mut out = String::with_capacity(contents.len() + line.len() + 1);
+    let mut replaced = false;
+    for l in contents.lines() {
+        if parse_opiekeys_line(l).is_some_and(|e| e.user == user) {
+            if !replaced {
+                out.push_str(line);
+                out.push('\n');
+                replaced = true;
+            }
+            continue;
+        }
+        out.push_str(l);
+        out.push('\n');
+    }
+    if !replaced {
+        out.push_str(line);
+        out.push('\n');
+    }
+    out
+}
+
+fn run () -> Result<(), String> {
+    let opts = parse_args(std::env::args().skip(1))?;
+    let contents = match fs::read_to_string(&opts.file) {
+        Ok(c) => c,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(format!("{}: {e}", opts.file)),
+    };
+    let current = current_state(&contents, &opts.user);
+    let seed_used = |seed: &Seed| current.is_some_and(|state| state.has_used_seed(seed.as_str()));
+    let seed = match opts.seed {
+        Some(seed) if seed_used(&seed) => return Err(format!("the seed {seed} is already in use")),
+        Some(seed) => seed,
+        None => loop {
+            let seed = generate_seed(&mut OsRng).map_err(|e| e.to_string())?;
+            if !seed_used(&seed) {
+                break seed;
+            }
+        },
+    };
+    let passphrase = rpassword::prompt_password("Enter new secret pass phrase: ")
+        .map_err(|e| e.to_string())?;
+    let check = check_passphrase(&passphrase, seed.as_str());
+    if check.too_short || check.too_long {
+        return Err("the pass phrase must be 10 to 63 characters".to_owned());
+    }
+    if !check.is_acceptable() {
+        return Err("the pass phrase is too weak".to_owned());
+    }
+    let again = rpassword::prompt_password("Again new secret pass phrase: ")
+        .map_err(|e| e.to_string())?;
+    if again != passphrase {
+        return Err("the pass phrases do not match".to_owned());
+    }
+    let otp = calculate_otp_with_alg(HashAlg::Md5, &passphrase, seed.as_str(), opts.count)
+        .ok_or("could not calculate OTP")?;
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+    let date = format_date(now);
+    let entry = OpieKeyEntry {
+        user: &opts.user,
+        count: opts.count,
+        seed: seed.as_str(),
+        otp,
+        date: &date,
+    };
+    let contents = replace_entry(&contents, &opts.user, &entry.to_string());
+    write_key_file(&opts.file, &contents).map_err(|e| format!("{}: {e}", opts.file))?;
+    println!("ID {} OTP key is {} {}", opts.user, opts.count - 1, seed);
+    println!("otp-md5 {} {}", opts.count - 1, seed);
+    Ok(())
+}
+
+fn main () -> ExitCode {
+    match run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("otpinit: {e}");
+            ExitCode::FAILURE
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args (s: &str) -> Vec<String> {
+        s.split_whitespace().map(str::to_owned).collect()
+    }
+
+    #[test]
+    fn parses_arguments () {
+        assert_eq!(parse_args(args("jdoe")), Ok(Options {
+            user: "jdoe".to_owned(),
+            count: DEFAULT_INITIAL_COUNT,
+            seed: None,
+            file: DEFAULT_OPIEKEYS_PATH.to_owned(),
+        }));
+        let opts = parse_args(args("-n 100 -s ke1234 -f keys jdoe")).unwrap();
+        assert_eq!(opts.count, 100);
+        assert_eq!(opts.seed, Seed::new("ke1234"));
+        assert_eq!(opts.file, "keys");
+        assert!(parse_args(args("")).is_err());
+        assert!(parse_args(args("-n 1 jdoe")).is_err());
+        assert!(parse_args(args("-s ke-1234 jdoe")).is_err());
+        assert!(parse_args(args("jdoe jane")).is_err());
+    }
+
+    #[test]
+    fn formats_dates () {
+        assert_eq!(format_date(0), "Jan 01,1970 00:00:00");
+        assert_eq!(format_date(1711972800), "Apr 01,2024 12:00:00");
+        assert_eq!(format_date(951782400), "Feb 29,2000 00:00:00");
+    }
+
+    #[test]
+    fn replaces_entries () {
+        let contents = "jane 0010 ab12 0000000000000000  x\njdoe 0010 ab12 0000000000000000  x\n";
+        assert_eq!(
+            replace_entry(contents, "jdoe", "jdoe new"),
+            "jane 0010 ab12 0000000000000000  x\njdoe new\n",
+        );
+        assert_eq!(replace_entry("", "jdoe", "jdoe new"), "jdoe new\n");
+    }
+
+    #[test]
+    fn finds_used_seeds () {
+        let contents = "jane 0010 ab12 0000000000000000  x\njdoe 0010 cd34 0000000000000000  x\n";
+        let state = current_state(contents, "jdoe").unwrap();
+        assert!(state.has_used_seed("CD34"));
+        assert!(!state.has_used_seed("ab12"));
+        assert_eq!(current_state(contents, "john"), None);
+    }
+
+    #[test]
+    fn writes_key_files () {
+        let dir = std::env::temp_dir().join(format!("otpinit-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("opiekeys");
+        let path = path.to_str().unwrap();
+        write_key_file(path, "jdoe old\n").unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(fs::metadata(path).unwrap().permissions().mode() & 0o777, NEW_KEY_FILE_MODE);
+            fs::set_permissions(path, fs::Permissions::from_mode(0o640)).unwrap();
+            write_key_file(path, "jdoe new\n").unwrap();
+            assert_eq!(fs::metadata(path).unwrap().permissions().mode() & 0o777, 0o640);
+        }
+        write_key_file(path, "jdoe new\n").unwrap();
+        assert_eq!(fs::read_to_string(path).unwrap(), "jdoe new\n");
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+}
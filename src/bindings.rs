@@ -0,0 +1,138 @@
+//! The string conversions shared by the `wasm` and `mobile` bindings, which
+//! only wrap these to suit `wasm-bindgen` and UniFFI respectively. Everything
+//! goes in and out as strings, and OTP values are passed around as contiguous
+//! hexadecimal.
+use alloc::string::{String, ToString};
+use core::fmt;
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+use crate::{
+    calculate_otp_with_options,
+    decode_hex_otp,
+    decode_word_str,
+    format_hex,
+    otp_to_word_string,
+    parse_otp_challenge,
+    CalculateError,
+    CalculateOptions,
+    HexStyle,
+    ResponseFormat,
+};
+
+/// A parsed OTP challenge, such as `otp-md5 499 ke1234`.
+#[cfg_attr(feature = "wasm", wasm_bindgen(getter_with_clone))]
+#[cfg_attr(feature = "uniffi", derive(uniffi::Record))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Challenge {
+    /// The name of the hash algorithm, such as `md5`.
+    pub alg: String,
+    /// The hash count.
+    pub count: u32,
+    /// The seed.
+    pub seed: String,
+}
+
+/// An error returned by the bindings.
+#[cfg_attr(feature = "uniffi", derive(uniffi::Error))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BindingError {
+    /// The OTP challenge could not be parsed.
+    InvalidChallenge,
+    /// The hash algorithm is not supported.
+    UnsupportedAlgorithm,
+    /// The hash count is too large.
+    CountTooLarge,
+    /// The seed is longer than sixteen characters.
+    SeedTooLong,
+    /// The hexadecimal OTP could not be parsed.
+    InvalidHex,
+    /// The six-word OTP could not be decoded.
+    InvalidWords,
+}
+
+impl fmt::Display for BindingError {
+
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            BindingError::InvalidChallenge => "invalid OTP challenge",
+            BindingError::UnsupportedAlgorithm => "unsupported hash algorithm",
+            BindingError::CountTooLarge => "hash count too large",
+            BindingError::SeedTooLong => "seed too long",
+            BindingError::InvalidHex => "invalid hexadecimal OTP",
+            BindingError::InvalidWords => "invalid six-word OTP",
+        })
+    }
+
+}
+
+#[cfg(any(feature = "std", feature = "uniffi"))]
+impl std::error::Error for BindingError {}
+
+impl From<CalculateError> for BindingError {
+
+    fn from(e: CalculateError) -> Self {
+        match e {
+            CalculateError::UnsupportedAlgorithm => BindingError::UnsupportedAlgorithm,
+            CalculateError::CountTooLarge(_) => BindingError::CountTooLarge,
+            CalculateError::SeedTooLong => BindingError::SeedTooLong,
+            // Digests cannot be supplied through these bindings, so this only
+            // means that the algorithm is not one of those built in.
+            CalculateError::UnsupportedDigestSize(_) => BindingError::UnsupportedAlgorithm,
+        }
+    }
+
+}
+
+pub(crate) fn parse_challenge (challenge: &str) -> Result<Challenge, BindingError> {
+    let c = parse_otp_challenge(challenge).ok_or(BindingError::InvalidChallenge)?;
+    Ok(Challenge {
+        alg: c.hash_alg.to_string(),
+        count: u32::try_from(c.hash_count).map_err(|_| BindingError::CountTooLarge)?,
+        seed: c.seed.to_string(),
+    })
+}
+
+pub(crate) fn calculate_otp (alg: &str, passphrase: &str, seed: &str, count: u32) -> Result<String, BindingError> {
+    let otp = calculate_otp_with_options(alg, passphrase, seed, count as usize, &CalculateOptions::default())?;
+    Ok(format_hex(&otp, HexStyle::CONTIGUOUS_LOWERCASE))
+}
+
+pub(crate) fn respond_to_challenge (challenge: &str, passphrase: &str, words: bool) -> Result<String, BindingError> {
+    let c = parse_otp_challenge(challenge).ok_or(BindingError::InvalidChallenge)?;
+    let format = if words { ResponseFormat::Words } else { ResponseFormat::Hex };
+    Ok(c.respond(passphrase, format)?)
+}
+
+pub(crate) fn hex_to_words (hex: &str) -> Result<String, BindingError> {
+    let otp = decode_hex_otp(hex).ok_or(BindingError::InvalidHex)?;
+    Ok(otp_to_word_string(&otp, false))
+}
+
+pub(crate) fn words_to_hex (words: &str) -> Result<String, BindingError> {
+    let otp = decode_word_str(words).map_err(|_| BindingError::InvalidWords)?;
+    Ok(format_hex(&otp, HexStyle::CONTIGUOUS_LOWERCASE))
+}
+
+#[cfg(all(test, feature = "md5"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_strings () {
+        let c = parse_challenge("otp-md5 99 correct").unwrap();
+        assert_eq!(c, Challenge { alg: "md5".to_string(), count: 99, seed: "correct".to_string() });
+        assert_eq!(calculate_otp("md5", "OTP's are good", "correct", 99).unwrap(), "b203e28fa525be47");
+        assert_eq!(
+            respond_to_challenge("otp-md5 99 correct", "OTP's are good", true).unwrap(),
+            "word:LONG IVY JULY AJAR BOND LEE",
+        );
+        assert_eq!(hex_to_words("B203 E28F A525 BE47").unwrap(), "LONG IVY JULY AJAR BOND LEE");
+        assert_eq!(words_to_hex("LONG IVY JULY AJAR BOND LEE").unwrap(), "b203e28fa525be47");
+        assert_eq!(parse_challenge("otp-md5"), Err(BindingError::InvalidChallenge));
+        assert_eq!(hex_to_words("B203 E28F A525 BE"), Err(BindingError::InvalidHex));
+        assert_eq!(words_to_hex("LONG IVY"), Err(BindingError::InvalidWords));
+        assert_eq!(calculate_otp("otp-foo", "OTP's are good", "correct", 99), Err(BindingError::UnsupportedAlgorithm));
+        assert_eq!(calculate_otp("md5", "OTP's are good", "abcdefghijklmnopq", 99), Err(BindingError::SeedTooLong));
+    }
+
+}
@@ -0,0 +1,353 @@
+//! A flat C ABI, for use by PAM modules, SASL plugins, and other C programs.
+//!
+//! Strings are passed in as NUL-terminated UTF-8, and written out into
+//! caller-supplied buffers, NUL-terminated. OTP values are passed as pointers
+//! to 8 bytes. Every function returns an [Rfc2289Status].
+//!
+//! To produce a static or dynamic library, build this crate with the `capi`
+//! feature and the appropriate crate type, for instance:
+//!
+//! ```text
+//! cargo rustc --release --features capi --crate-type staticlib
+//! ```
+//!
+//! A C header can be generated using `cbindgen`, which is configured by
+//! `cbindgen.toml` in the root of this repository.
+use core::ffi::{c_char, CStr};
+use core::fmt::Write;
+use crate::{
+    calculate_otp_with_options,
+    decode_word_str,
+    parse_otp_challenge,
+    parse_otp_response,
+    verify_response,
+    write_hex,
+    write_words,
+    CalculateError,
+    CalculateOptions,
+    HexStyle,
+    VerifyResult,
+};
+
+/// The result of a call to a function of the C ABI.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rfc2289Status {
+    /// The call succeeded.
+    Ok = 0,
+    /// The response did not match the expected OTP value.
+    Mismatch = 1,
+    /// The response matched, but was an init response, which the caller must
+    /// handle by re-initializing the sequence.
+    Reinit = 2,
+    /// A required pointer was null.
+    NullPointer = -1,
+    /// A string was not valid UTF-8.
+    InvalidUtf8 = -2,
+    /// A string could not be parsed.
+    ParseError = -3,
+    /// The hash algorithm is not supported.
+    UnsupportedAlgorithm = -4,
+    /// The hash count is too large.
+    CountTooLarge = -5,
+    /// The output buffer is too small.
+    BufferTooSmall = -6,
+    /// Dictionary words could not be decoded.
+    DecodeError = -7,
+    /// The seed is too long to be lowercased without allocating.
+    SeedTooLong = -8,
+}
+
+/// A [core::fmt::Write] sink that writes into a C buffer, always leaving room
+/// for a terminating NUL.
+struct CBuffer <'a> {
+    buf: &'a mut [u8],
+    len: usize,
+}
+
+impl Write for CBuffer<'_> {
+
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        let end = self.len + s.len();
+        if end >= self.buf.len() {
+            return Err(core::fmt::Error);
+        }
+        self.buf[self.len..end].copy_from_slice(s.as_bytes());
+        self.len = end;
+        Ok(())
+    }
+
+}
+
+/// Convert a NUL-terminated C string to a `&str`.
+///
+/// # Safety
+///
+/// `s` must be null or point to a NUL-terminated string that outlives `'a`.
+unsafe fn str_from_c <'a> (s: *const c_char) -> Result<&'a str, Rfc2289Status> {
+    if s.is_null() {
+        return Err(Rfc2289Status::NullPointer);
+    }
+    CStr::from_ptr(s).to_str().map_err(|_| Rfc2289Status::InvalidUtf8)
+}
+
+/// Write `s` into a C buffer, NUL-terminated.
+///
+/// # Safety
+///
+/// `out` must be null or point to at least `out_len` writable bytes.
+unsafe fn write_c_str (
+    out: *mut c_char,
+    out_len: usize,
+    f: impl FnOnce(&mut CBuffer<'_>) -> core::fmt::Result,
+) -> Rfc2289Status {
+    if out.is_null() {
+        return Rfc2289Status::NullPointer;
+    }
+    let buf = core::slice::from_raw_parts_mut(out as *mut u8, out_len);
+    let mut w = CBuffer { buf, len: 0 };
+    if f(&mut w).is_err() || w.len >= w.buf.len() {
+        return Rfc2289Status::BufferTooSmall;
+    }
+    w.buf[w.len] = 0;
+    Rfc2289Status::Ok
+}
+
+/// Calculate an OTP value, writing the 8-byte result to `otp_out`.
+///
+/// # Safety
+///
+/// `alg`, `passphrase`, and `seed` must be NUL-terminated strings, and
+/// `otp_out` must point to 8 writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn rfc2289_calculate_otp (
+    alg: *const c_char,
+    passphrase: *const c_char,
+    seed: *const c_char,
+    count: usize,
+    otp_out: *mut u8,
+) -> Rfc2289Status {
+    let (alg, passphrase, seed) = match (str_from_c(alg), str_from_c(passphrase), str_from_c(seed)) {
+        (Ok(a), Ok(p), Ok(s)) => (a, p, s),
+        (Err(e), _, _) | (_, Err(e), _) | (_, _, Err(e)) => return e,
+    };
+    if otp_out.is_null() {
+        return Rfc2289Status::NullPointer;
+    }
+    match calculate_otp_with_options(alg, passphrase, seed, count, &CalculateOptions::default()) {
+        Ok(otp) => {
+            core::ptr::copy_nonoverlapping(otp.as_ptr(), otp_out, otp.len());
+            Rfc2289Status::Ok
+        },
+        Err(CalculateError::CountTooLarge(_)) => Rfc2289Status::CountTooLarge,
+        Err(CalculateError::SeedTooLong) => Rfc2289Status::SeedTooLong,
+        Err(_) => Rfc2289Status::UnsupportedAlgorithm,
+    }
+}
+
+/// Parse an OTP challenge, such as `otp-md5 499 ke1234`, writing the
+/// algorithm and seed into the supplied buffers, NUL-terminated, and the hash
+/// count into `count_out`.
+///
+/// # Safety
+///
+/// `challenge` must be a NUL-terminated string, `alg_out` and `seed_out` must
+/// point to `alg_len` and `seed_len` writable bytes, respectively, and
+/// `count_out` must point to a writable `size_t`.
+#[no_mangle]
+pub unsafe extern "C" fn rfc2289_parse_challenge (
+    challenge: *const c_char,
+    alg_out: *mut c_char,
+    alg_len: usize,
+    seed_out: *mut c_char,
+    seed_len: usize,
+    count_out: *mut usize,
+) -> Rfc2289Status {
+    let challenge = match str_from_c(challenge) {
+        Ok(c) => c,
+        Err(e) => return e,
+    };
+    if count_out.is_null() {
+        return Rfc2289Status::NullPointer;
+    }
+    let Some(c) = parse_otp_challenge(challenge) else {
+        return Rfc2289Status::ParseError;
+    };
+    let status = write_c_str(alg_out, alg_len, |w| w.write_str(c.hash_alg));
+    if status != Rfc2289Status::Ok {
+        return status;
+    }
+    let status = write_c_str(seed_out, seed_len, |w| w.write_str(c.seed));
+    if status != Rfc2289Status::Ok {
+        return status;
+    }
+    *count_out = c.hash_count;
+    Rfc2289Status::Ok
+}
+
+/// Write an OTP value as six space-separated words from the standard
+/// dictionary, NUL-terminated. A buffer of 30 bytes always suffices.
+///
+/// # Safety
+///
+/// `otp` must point to 8 readable bytes, and `out` to `out_len` writable
+/// bytes.
+#[no_mangle]
+pub unsafe extern "C" fn rfc2289_otp_to_words (
+    otp: *const u8,
+    out: *mut c_char,
+    out_len: usize,
+) -> Rfc2289Status {
+    if otp.is_null() {
+        return Rfc2289Status::NullPointer;
+    }
+    let otp = &*(otp as *const [u8; 8]);
+    write_c_str(out, out_len, |w| write_words(w, otp, false))
+}
+
+/// Write an OTP value as four groups of four uppercase hexadecimal digits,
+/// NUL-terminated. A buffer of 20 bytes always suffices.
+///
+/// # Safety
+///
+/// `otp` must point to 8 readable bytes, and `out` to `out_len` writable
+/// bytes.
+#[no_mangle]
+pub unsafe extern "C" fn rfc2289_otp_to_hex (
+    otp: *const u8,
+    out: *mut c_char,
+    out_len: usize,
+) -> Rfc2289Status {
+    if otp.is_null() {
+        return Rfc2289Status::NullPointer;
+    }
+    let otp = &*(otp as *const [u8; 8]);
+    write_c_str(out, out_len, |w| write_hex(w, otp, HexStyle::CANONICAL))
+}
+
+/// Decode six space-separated words from the standard dictionary, writing the
+/// 8-byte OTP value to `otp_out`.
+///
+/// # Safety
+///
+/// `words` must be a NUL-terminated string, and `otp_out` must point to 8
+/// writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn rfc2289_words_to_otp (
+    words: *const c_char,
+    otp_out: *mut u8,
+) -> Rfc2289Status {
+    let words = match str_from_c(words) {
+        Ok(w) => w,
+        Err(e) => return e,
+    };
+    if otp_out.is_null() {
+        return Rfc2289Status::NullPointer;
+    }
+    match decode_word_str(words) {
+        Ok(otp) => {
+            core::ptr::copy_nonoverlapping(otp.as_ptr(), otp_out, otp.len());
+            Rfc2289Status::Ok
+        },
+        Err(_) => Rfc2289Status::DecodeError,
+    }
+}
+
+/// Verify an OTP response, such as `hex:5Bf0 75d9 959d 036f`, against the
+/// expected 8-byte OTP value, comparing in constant time.
+///
+/// Returns [Rfc2289Status::Ok] if the response matches,
+/// [Rfc2289Status::Mismatch] if it does not, and [Rfc2289Status::Reinit] if it
+/// is an init response whose current OTP matches.
+///
+/// # Safety
+///
+/// `expected` must point to 8 readable bytes, and `response` must be a
+/// NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn rfc2289_verify_response (
+    expected: *const u8,
+    response: *const c_char,
+) -> Rfc2289Status {
+    let response = match str_from_c(response) {
+        Ok(r) => r,
+        Err(e) => return e,
+    };
+    if expected.is_null() {
+        return Rfc2289Status::NullPointer;
+    }
+    let expected = &*(expected as *const [u8; 8]);
+    let Some(response) = parse_otp_response(response) else {
+        return Rfc2289Status::ParseError;
+    };
+    match verify_response(expected, &response) {
+        Ok(VerifyResult::Match) => Rfc2289Status::Ok,
+        Ok(VerifyResult::Mismatch) => Rfc2289Status::Mismatch,
+        Ok(VerifyResult::Reinit { .. }) => Rfc2289Status::Reinit,
+        Err(_) => Rfc2289Status::DecodeError,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(feature = "md5")]
+    fn calls_through_the_c_abi () {
+        let mut otp = [0u8; 8];
+        let status = unsafe {
+            rfc2289_calculate_otp(c"md5".as_ptr(), c"OTP's are good".as_ptr(), c"correct".as_ptr(), 99, otp.as_mut_ptr())
+        };
+        assert_eq!(status, Rfc2289Status::Ok);
+        assert_eq!(otp, [ 0xB2, 0x03, 0xE2, 0x8F, 0xA5, 0x25, 0xBE, 0x47 ]);
+
+        let mut words = [0 as c_char; 30];
+        let status = unsafe { rfc2289_otp_to_words(otp.as_ptr(), words.as_mut_ptr(), words.len()) };
+        assert_eq!(status, Rfc2289Status::Ok);
+        assert_eq!(unsafe { CStr::from_ptr(words.as_ptr()) }, c"LONG IVY JULY AJAR BOND LEE");
+
+        let mut hex = [0 as c_char; 20];
+        let status = unsafe { rfc2289_otp_to_hex(otp.as_ptr(), hex.as_mut_ptr(), hex.len()) };
+        assert_eq!(status, Rfc2289Status::Ok);
+        assert_eq!(unsafe { CStr::from_ptr(hex.as_ptr()) }, c"B203 E28F A525 BE47");
+        let status = unsafe { rfc2289_otp_to_hex(otp.as_ptr(), hex.as_mut_ptr(), 19) };
+        assert_eq!(status, Rfc2289Status::BufferTooSmall);
+
+        let mut decoded = [0u8; 8];
+        let status = unsafe { rfc2289_words_to_otp(words.as_ptr(), decoded.as_mut_ptr()) };
+        assert_eq!(status, Rfc2289Status::Ok);
+        assert_eq!(decoded, otp);
+
+        let status = unsafe { rfc2289_verify_response(otp.as_ptr(), c"word:LONG IVY JULY AJAR BOND LEE".as_ptr()) };
+        assert_eq!(status, Rfc2289Status::Ok);
+        let status = unsafe { rfc2289_verify_response(otp.as_ptr(), c"hex:0000 0000 0000 0000".as_ptr()) };
+        assert_eq!(status, Rfc2289Status::Mismatch);
+    }
+
+    #[test]
+    fn parses_challenges_through_the_c_abi () {
+        let mut alg = [0 as c_char; 16];
+        let mut seed = [0 as c_char; 17];
+        let mut count = 0usize;
+        let status = unsafe {
+            rfc2289_parse_challenge(
+                c"otp-md5 499 ke1234 ext".as_ptr(),
+                alg.as_mut_ptr(),
+                alg.len(),
+                seed.as_mut_ptr(),
+                seed.len(),
+                &mut count,
+            )
+        };
+        assert_eq!(status, Rfc2289Status::Ok);
+        assert_eq!(unsafe { CStr::from_ptr(alg.as_ptr()) }, c"md5");
+        assert_eq!(unsafe { CStr::from_ptr(seed.as_ptr()) }, c"ke1234");
+        assert_eq!(count, 499);
+        let status = unsafe {
+            rfc2289_parse_challenge(core::ptr::null(), alg.as_mut_ptr(), alg.len(), seed.as_mut_ptr(), seed.len(), &mut count)
+        };
+        assert_eq!(status, Rfc2289Status::NullPointer);
+    }
+
+}
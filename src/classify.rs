@@ -0,0 +1,103 @@
+//! Classifying and parsing any OTP-related string with a single call, for fuzz
+//! targets, protocol sniffers, and gateways that do not know in advance what
+//! kind of message they have received.
+use crate::{HexOrWords, OTPChallenge, OTPInit, OTPResponse, parse_otp_challenge, parse_otp_response};
+
+/// Any OTP-related message, as parsed by [parse_any].
+#[derive(Debug, PartialEq, Eq)]
+pub enum OtpMessage <'a> {
+    /// A challenge, such as `otp-md5 499 ke1234`.
+    Challenge(OTPChallenge<'a>),
+    /// A response containing only the current OTP, such as `word:...`.
+    Response(HexOrWords<'a>),
+    /// An init response, such as `init-hex:...`.
+    Init(OTPInit<'a>),
+    /// The client's initial message in the `OTP` SASL mechanism, per Section 5
+    /// of [IETF RFC 2444](https://www.rfc-editor.org/rfc/rfc2444.html): an
+    /// authorization identity, which may be empty, a NUL, and an
+    /// authentication identity.
+    SaslInitial {
+        /// The authorization identity: the user to act as, or empty to act
+        /// as the authentication identity.
+        authzid: &'a str,
+        /// The authentication identity: the user whose OTP sequence is used.
+        authcid: &'a str,
+    },
+}
+
+/// Parse the client's initial message in the `OTP` SASL mechanism. The
+/// authentication identity must not be empty, and neither identity may
+/// contain a NUL.
+fn parse_sasl_initial <'a> (s: &'a str) -> Option<OtpMessage<'a>> {
+    let (authzid, authcid) = s.split_once('\0')?;
+    if authcid.is_empty() || authcid.contains('\0') {
+        return None;
+    }
+    Some(OtpMessage::SaslInitial { authzid, authcid })
+}
+
+/// Classify and parse a challenge, response, init response, or SASL initial
+/// message, returning `None` if `s` is none of these.
+///
+/// Responses are recognized by their `hex:`, `word:`, `init-hex:`, or
+/// `init-word:` prefix, challenges by their `otp-` prefix, and SASL initial
+/// messages by the NUL that separates their identities. Each is then parsed as
+/// [parse_otp_response] or [parse_otp_challenge] would, so nothing is decoded
+/// or verified.
+///
+/// ```rust
+/// use rfc2289_otp::{parse_any, OtpMessage};
+/// assert!(matches!(parse_any("otp-md5 499 ke1234 ext"), Some(OtpMessage::Challenge(_))));
+/// assert!(matches!(parse_any("word:INCH SEA ANNE LONG AHEM TOUR"), Some(OtpMessage::Response(_))));
+/// assert_eq!(parse_any("\0alice"), Some(OtpMessage::SaslInitial { authzid: "", authcid: "alice" }));
+/// assert_eq!(parse_any("hello"), None);
+/// ```
+pub fn parse_any <'a> (s: &'a str) -> Option<OtpMessage<'a>> {
+    if s.contains('\0') {
+        parse_sasl_initial(s)
+    }
+    else if s.starts_with("otp-") {
+        parse_otp_challenge(s).map(OtpMessage::Challenge)
+    }
+    else {
+        match parse_otp_response(s)? {
+            OTPResponse::Current(otp) => Some(OtpMessage::Response(otp)),
+            OTPResponse::Init(init) => Some(OtpMessage::Init(init)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn classifies_messages () {
+        let challenge = parse_any("otp-md5 499 ke1234 ext").unwrap();
+        assert_eq!(challenge, OtpMessage::Challenge(parse_otp_challenge("otp-md5 499 ke1234 ext").unwrap()));
+        assert_eq!(
+            parse_any("hex:5Bf0 75d9 959d 036f"),
+            Some(OtpMessage::Response(HexOrWords::Hex([ 0x5B, 0xf0, 0x75, 0xd9, 0x95, 0x9d, 0x03, 0x6f ]))),
+        );
+        let init = "init-word:INCH SEA ANNE LONG AHEM TOUR:md5 499 ke1235:GLOB SOFT CALL ARCH FLEW FREY";
+        match parse_any(init) {
+            Some(OtpMessage::Init(i)) => assert_eq!(i.new_seed, "ke1235"),
+            other => panic!("expected an init response, got {:?}", other),
+        }
+        assert_eq!(
+            parse_any("admin\0alice"),
+            Some(OtpMessage::SaslInitial { authzid: "admin", authcid: "alice" }),
+        );
+    }
+
+    #[test]
+    fn rejects_unrecognized_messages () {
+        assert_eq!(parse_any(""), None);
+        assert_eq!(parse_any("otp-md5 ke1234"), None);
+        assert_eq!(parse_any("hex:not hex at all, really"), None);
+        assert_eq!(parse_any("alice\0"), None);
+        assert_eq!(parse_any("a\0b\0c"), None);
+    }
+
+}
@@ -0,0 +1,120 @@
+//! A source of time for time-based policies, such as lockouts and challenge
+//! expiry, that works without `std`.
+//!
+//! Time is measured in whole seconds from an arbitrary starting point, and
+//! must never go backwards. Embedded users can supply their own tick source as
+//! a closure:
+//!
+//! ```rust
+//! use rfc2289_otp::clock::Clock;
+//!
+//! fn rtos_uptime_secs () -> u64 { 42 }
+//!
+//! let clock = || rtos_uptime_secs();
+//! assert_eq!(clock.now_secs(), 42);
+//! ```
+use core::cell::Cell;
+
+/// A monotonic clock with a resolution of one second.
+pub trait Clock {
+
+    /// The number of seconds since an arbitrary, fixed starting point. This
+    /// must never decrease.
+    fn now_secs (&self) -> u64;
+
+}
+
+impl <F: Fn() -> u64> Clock for F {
+
+    fn now_secs (&self) -> u64 {
+        self()
+    }
+
+}
+
+/// A clock that only moves when told to, for tests and simulations.
+#[derive(Debug, Clone, Default)]
+pub struct ManualClock {
+    now: Cell<u64>,
+}
+
+impl ManualClock {
+
+    /// Create a clock that reads `now` seconds.
+    pub const fn new (now: u64) -> Self {
+        ManualClock { now: Cell::new(now) }
+    }
+
+    /// Move the clock forward by `secs` seconds.
+    pub fn advance (&self, secs: u64) {
+        self.now.set(self.now.get().saturating_add(secs));
+    }
+
+}
+
+impl Clock for ManualClock {
+
+    fn now_secs (&self) -> u64 {
+        self.now.get()
+    }
+
+}
+
+/// A clock backed by `std::time::Instant`, which counts seconds from when it
+/// was created.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Copy)]
+pub struct StdClock {
+    start: std::time::Instant,
+}
+
+#[cfg(feature = "std")]
+impl StdClock {
+
+    /// Create a clock that reads zero now.
+    pub fn new () -> Self {
+        StdClock { start: std::time::Instant::now() }
+    }
+
+}
+
+#[cfg(feature = "std")]
+impl Default for StdClock {
+
+    fn default() -> Self {
+        StdClock::new()
+    }
+
+}
+
+#[cfg(feature = "std")]
+impl Clock for StdClock {
+
+    fn now_secs (&self) -> u64 {
+        self.start.elapsed().as_secs()
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn manual_clocks_advance () {
+        let clock = ManualClock::new(10);
+        assert_eq!(clock.now_secs(), 10);
+        clock.advance(5);
+        assert_eq!(clock.now_secs(), 15);
+        clock.advance(u64::MAX);
+        assert_eq!(clock.now_secs(), u64::MAX);
+        assert_eq!((|| 7).now_secs(), 7);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn std_clocks_start_at_zero () {
+        assert_eq!(StdClock::new().now_secs(), 0);
+    }
+
+}
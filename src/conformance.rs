@@ -0,0 +1,201 @@
+//! A conformance checker for code that wraps this library, such as FFI layers
+//! and services, so that they can verify that their glue has not broken
+//! correctness. Implement [OtpImplementation] by calling through the wrapper,
+//! then run [run_conformance] in your own tests:
+//!
+//! ```rust
+//! use rfc2289_otp::conformance::{run_conformance, Reference};
+//! # #[cfg(all(feature = "md4", feature = "md5", feature = "sha1"))]
+//! run_conformance(&Reference).unwrap();
+//! ```
+//!
+//! It checks the test vectors from Appendix C of
+//! [IETF RFC 2289](https://www.rfc-editor.org/rfc/rfc2289.html), followed by
+//! the [GRAMMAR_CASES] for hexadecimal and words.
+use core::fmt;
+use crate::{
+    calculate_otp_with_options,
+    decode_hex_otp,
+    decode_word_str,
+    test_vectors::{self, TestVector},
+    CalculateOptions,
+};
+
+/// An implementation of OTP calculation and decoding under test.
+pub trait OtpImplementation {
+
+    /// Calculate the OTP value for the algorithm named `alg`, such as `md5`,
+    /// or return `None` if it cannot be calculated.
+    fn calculate (&self, alg: &str, passphrase: &str, seed: &str, count: usize) -> Option<[u8; 8]>;
+
+    /// Decode an OTP value written in hexadecimal, without a `hex:` prefix, or
+    /// return `None` if it is invalid.
+    fn decode_hex (&self, s: &str) -> Option<[u8; 8]>;
+
+    /// Decode an OTP value written as six words from the standard dictionary,
+    /// without a `word:` prefix, or return `None` if it is invalid.
+    fn decode_words (&self, s: &str) -> Option<[u8; 8]>;
+
+}
+
+/// This library itself, against which wrappers can be compared.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Reference;
+
+impl OtpImplementation for Reference {
+
+    fn calculate (&self, alg: &str, passphrase: &str, seed: &str, count: usize) -> Option<[u8; 8]> {
+        calculate_otp_with_options(alg, passphrase, seed, count, &CalculateOptions::default()).ok()
+    }
+
+    fn decode_hex (&self, s: &str) -> Option<[u8; 8]> {
+        decode_hex_otp(s)
+    }
+
+    fn decode_words (&self, s: &str) -> Option<[u8; 8]> {
+        decode_word_str(s).ok()
+    }
+
+}
+
+/// Whether a [GrammarCase] is hexadecimal or words.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    /// Sixteen hexadecimal digits, as in a `hex:` response.
+    Hex,
+    /// Six dictionary words, as in a `word:` response.
+    Words,
+}
+
+/// An input that must be decoded to a particular value, or rejected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GrammarCase {
+    /// Whether the input is hexadecimal or words.
+    pub encoding: Encoding,
+    /// The input, without any response prefix.
+    pub input: &'static str,
+    /// The value that the input decodes to, or `None` if it must be rejected.
+    pub expected: Option<[u8; 8]>,
+}
+
+const INCH_SEA: Option<[u8; 8]> = Some([ 0x9E, 0x87, 0x61, 0x34, 0xD9, 0x04, 0x99, 0xDD ]);
+
+const fn case (encoding: Encoding, input: &'static str, expected: Option<[u8; 8]>) -> GrammarCase {
+    GrammarCase { encoding, input, expected }
+}
+
+/// Hexadecimal and words that conforming implementations must accept or
+/// reject. Hexadecimal is case-insensitive and may contain whitespace, and
+/// words are case-insensitive, but there must be exactly sixteen hex digits or
+/// six words with a valid checksum.
+pub const GRAMMAR_CASES: [GrammarCase; 12] = [
+    case(Encoding::Hex, "9E87 6134 D904 99DD", INCH_SEA),
+    case(Encoding::Hex, "9e876134d90499dd", INCH_SEA),
+    case(Encoding::Hex, " 9E87  6134 D904 99dd ", INCH_SEA),
+    case(Encoding::Hex, "9E87 6134 D904 99D", None),
+    case(Encoding::Hex, "9E87 6134 D904 99DD 00", None),
+    case(Encoding::Hex, "9E87 6134 D904 99DG", None),
+    case(Encoding::Words, "INCH SEA ANNE LONG AHEM TOUR", INCH_SEA),
+    case(Encoding::Words, "inch sea anne long ahem tour", INCH_SEA),
+    case(Encoding::Words, "Inch  Sea Anne Long Ahem Tour", INCH_SEA),
+    case(Encoding::Words, "INCH SEA ANNE LONG AHEM", None),
+    case(Encoding::Words, "INCH SEA ANNE LONG AHEM TOUR TOUR", None),
+    case(Encoding::Words, "INCH SEA ANNE LONG AHEM XYZZY", None),
+];
+
+/// The first check that an [OtpImplementation] failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ConformanceFailure {
+    /// The OTP value calculated for this test vector was wrong or missing.
+    Calculation(&'static TestVector),
+    /// The hexadecimal of this test vector did not decode to its value.
+    HexDecoding(&'static TestVector),
+    /// The words of this test vector did not decode to its value.
+    WordDecoding(&'static TestVector),
+    /// This input was decoded to the wrong value, or wrongly accepted or
+    /// rejected.
+    Grammar(&'static GrammarCase),
+}
+
+impl fmt::Display for ConformanceFailure {
+
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConformanceFailure::Calculation(v) => write!(
+                f,
+                "wrong OTP value for {} with seed {:?} and count {}",
+                v.alg.name(), v.seed, v.count,
+            ),
+            ConformanceFailure::HexDecoding(v) => write!(f, "wrong value decoded from hex {:?}", v.hex),
+            ConformanceFailure::WordDecoding(v) => write!(f, "wrong value decoded from words {:?}", v.words),
+            ConformanceFailure::Grammar(c) => write!(f, "wrong result decoding {:?}", c.input),
+        }
+    }
+
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ConformanceFailure {}
+
+/// Check `implementation` against every test vector and [GRAMMAR_CASES],
+/// returning the first failure, if any. The test vectors include MD4, MD5,
+/// and SHA1, so all three must be supported.
+pub fn run_conformance <I: OtpImplementation + ?Sized> (implementation: &I) -> Result<(), ConformanceFailure> {
+    for v in test_vectors::all() {
+        if implementation.calculate(v.alg.name(), v.passphrase, v.seed, v.count) != Some(v.otp) {
+            return Err(ConformanceFailure::Calculation(v));
+        }
+        if implementation.decode_hex(v.hex) != Some(v.otp) {
+            return Err(ConformanceFailure::HexDecoding(v));
+        }
+        if implementation.decode_words(v.words) != Some(v.otp) {
+            return Err(ConformanceFailure::WordDecoding(v));
+        }
+    }
+    for c in GRAMMAR_CASES.iter() {
+        let decoded = match c.encoding {
+            Encoding::Hex => implementation.decode_hex(c.input),
+            Encoding::Words => implementation.decode_words(c.input),
+        };
+        if decoded != c.expected {
+            return Err(ConformanceFailure::Grammar(c));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(all(test, feature = "md4", feature = "md5", feature = "sha1"))]
+mod tests {
+
+    use super::*;
+
+    /// A wrapper that mishandles lowercase words, as a broken binding might.
+    struct CaseSensitive;
+
+    impl OtpImplementation for CaseSensitive {
+
+        fn calculate (&self, alg: &str, passphrase: &str, seed: &str, count: usize) -> Option<[u8; 8]> {
+            Reference.calculate(alg, passphrase, seed, count)
+        }
+
+        fn decode_hex (&self, s: &str) -> Option<[u8; 8]> {
+            Reference.decode_hex(s)
+        }
+
+        fn decode_words (&self, s: &str) -> Option<[u8; 8]> {
+            if s.bytes().any(|b| b.is_ascii_lowercase()) {
+                return None;
+            }
+            Reference.decode_words(s)
+        }
+
+    }
+
+    #[test]
+    fn checks_conformance () {
+        assert_eq!(run_conformance(&Reference), Ok(()));
+        assert_eq!(run_conformance(&CaseSensitive), Err(ConformanceFailure::Grammar(&GRAMMAR_CASES[7])));
+    }
+
+}
@@ -0,0 +1,360 @@
+//! Dictionaries of 2048 words, which OTP values are encoded into six of. The
+//! standard dictionary is defined in
+//! [IETF RFC 1760](https://www.rfc-editor.org/rfc/rfc1760), but sites may use
+//! their own, per Section 6.1 of
+//! [IETF RFC 2289](https://www.rfc-editor.org/rfc/rfc2289.html), in which
+//! case clients should fall back to hexadecimal when talking to servers that
+//! may not know it.
+use core::fmt;
+#[cfg(feature = "alloc")]
+use alloc::{string::{String, ToString}, vec::Vec};
+use crate::{
+    index_to_word,
+    otp_to_word_indices,
+    word_index,
+    word_indices_to_otp,
+};
+
+/// The number of words in a dictionary: one for every 11-bit index.
+pub const DICTIONARY_LEN: usize = 2048;
+
+/// A dictionary mapping 11-bit indices to words.
+pub trait Dictionary {
+
+    /// The word at `index`, or `None` if there is none.
+    fn word (&self, index: u16) -> Option<&str>;
+
+    /// The index of `word`, ignoring case, or `None` if it does not appear.
+    fn index_of (&self, word: &str) -> Option<u16>;
+
+    /// Whether this is the standard dictionary, which every server
+    /// understands.
+    fn is_standard (&self) -> bool {
+        false
+    }
+
+    /// Check this dictionary against the constraints of
+    /// [IETF RFC 2289](https://www.rfc-editor.org/rfc/rfc2289.html), before
+    /// deploying it. See [DictionaryReport].
+    ///
+    /// ```rust
+    /// use rfc2289_otp::{Dictionary, StandardDictionary};
+    /// assert!(StandardDictionary.validate().is_valid());
+    /// let short: &[&str] = &[ "A", "B", "A", "LONGER" ];
+    /// let report = short.validate();
+    /// assert!(!report.is_valid());
+    /// assert_eq!(report.missing.count, 2044);
+    /// assert_eq!(report.duplicates.first, Some(2));
+    /// assert_eq!(report.bad_length.first, Some(3));
+    /// ```
+    fn validate (&self) -> DictionaryReport {
+        let mut report = DictionaryReport {
+            too_many: self.word(DICTIONARY_LEN as u16).is_some(),
+            ..DictionaryReport::default()
+        };
+        let is_standard = self.is_standard();
+        for i in 0..DICTIONARY_LEN as u16 {
+            let Some(word) = self.word(i) else {
+                report.missing.record(i);
+                continue;
+            };
+            if !(1..=4).contains(&word.len()) {
+                report.bad_length.record(i);
+            }
+            if !word.is_ascii() {
+                report.non_ascii.record(i);
+            }
+            if self.index_of(word) != Some(i) {
+                report.duplicates.record(i);
+            }
+            if !is_standard && word_index(word).is_some() {
+                report.standard_words.record(i);
+            }
+        }
+        report
+    }
+
+}
+
+/// How many items checked by a validation have a particular problem, and the
+/// position of the first of them, such as in a [DictionaryReport] or an
+/// `opiekeys::MigrationReport`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ReportIssue <P> {
+    /// The number of items that have the problem.
+    pub count: usize,
+    /// The position of the first item that has the problem, or `None` if none
+    /// do.
+    pub first: Option<P>,
+}
+
+impl <P> ReportIssue<P> {
+
+    pub(crate) fn record (&mut self, position: P) {
+        self.count += 1;
+        self.first.get_or_insert(position);
+    }
+
+    /// Whether no items have this problem.
+    pub fn is_empty (&self) -> bool {
+        self.count == 0
+    }
+
+}
+
+/// How many words of a dictionary have a particular problem, and the index of
+/// the first of them.
+pub type DictionaryIssue = ReportIssue<u16>;
+
+/// The result of [Dictionary::validate].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DictionaryReport {
+    /// Indices below [DICTIONARY_LEN] that have no word.
+    pub missing: DictionaryIssue,
+    /// Whether there is a word at index [DICTIONARY_LEN], so that there are
+    /// more words than can be encoded.
+    pub too_many: bool,
+    /// Words that are empty or longer than four characters.
+    pub bad_length: DictionaryIssue,
+    /// Words that contain characters other than ASCII.
+    pub non_ascii: DictionaryIssue,
+    /// Words that match an earlier word, ignoring case, or that
+    /// [Dictionary::index_of] does not find at their own index.
+    pub duplicates: DictionaryIssue,
+    /// Words that also appear in the standard dictionary, ignoring case. This
+    /// is always empty for the standard dictionary itself.
+    pub standard_words: DictionaryIssue,
+}
+
+impl DictionaryReport {
+
+    /// Whether the dictionary has exactly [DICTIONARY_LEN] unique ASCII words
+    /// of one to four characters each. Words from the standard dictionary are
+    /// allowed: see [DictionaryReport::is_valid_alternate].
+    pub fn is_valid (&self) -> bool {
+        self.missing.is_empty()
+            && !self.too_many
+            && self.bad_length.is_empty()
+            && self.non_ascii.is_empty()
+            && self.duplicates.is_empty()
+    }
+
+    /// Whether the dictionary [is valid](DictionaryReport::is_valid), and
+    /// also shares no words with the standard dictionary, so that a server
+    /// can tell which dictionary a response's words came from.
+    pub fn is_valid_alternate (&self) -> bool {
+        self.is_valid() && self.standard_words.is_empty()
+    }
+
+}
+
+/// The standard dictionary defined in
+/// [IETF RFC 1760](https://www.rfc-editor.org/rfc/rfc1760).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct StandardDictionary;
+
+impl Dictionary for StandardDictionary {
+
+    fn word (&self, index: u16) -> Option<&str> {
+        index_to_word(index)
+    }
+
+    fn index_of (&self, word: &str) -> Option<u16> {
+        word_index(word)
+    }
+
+    fn is_standard (&self) -> bool {
+        true
+    }
+
+}
+
+impl Dictionary for [&str] {
+
+    fn word (&self, index: u16) -> Option<&str> {
+        self.get(index as usize).copied()
+    }
+
+    fn index_of (&self, word: &str) -> Option<u16> {
+        self.iter().position(|w| w.eq_ignore_ascii_case(word)).map(|i| i as u16)
+    }
+
+}
+
+#[cfg(feature = "alloc")]
+impl Dictionary for Vec<String> {
+
+    fn word (&self, index: u16) -> Option<&str> {
+        self.get(index as usize).map(String::as_str)
+    }
+
+    fn index_of (&self, word: &str) -> Option<u16> {
+        self.iter().position(|w| w.eq_ignore_ascii_case(word)).map(|i| i as u16)
+    }
+
+}
+
+/// Encode a 64-bit value as six words from `dictionary`, including the
+/// checksum, exactly as [crate::convert_to_word_format] does for the standard
+/// dictionary.
+///
+/// Returns `None` if `dictionary` is missing a word that is needed.
+pub fn convert_to_word_format_with_dictionary <'d, D: Dictionary + ?Sized> (
+    otp: &[u8; 8],
+    dictionary: &'d D,
+) -> Option<[&'d str; 6]> {
+    let mut output: [&'d str; 6] = [""; 6];
+    for (word, index) in output.iter_mut().zip(otp_to_word_indices(otp)) {
+        *word = dictionary.word(index)?;
+    }
+    Some(output)
+}
+
+/// Decode six words from `dictionary` into a 64-bit value, ignoring case,
+/// exactly as [crate::decode_word_format_with_std_dict] does for the standard
+/// dictionary.
+///
+/// Returns `None` if a word does not appear among the first
+/// [DICTIONARY_LEN] words of `dictionary`. Otherwise, returns the decoded
+/// bytes and a `bool` indicating whether the checksum was valid, respectively.
+///
+/// The inverse of [convert_to_word_format_with_dictionary].
+pub fn decode_word_format_with_dictionary <D: Dictionary + ?Sized> (
+    words: [&str; 6],
+    dictionary: &D,
+) -> Option<([u8; 8], bool)> {
+    let mut indices = [0u16; 6];
+    for (index, word) in indices.iter_mut().zip(words) {
+        *index = dictionary.index_of(word).filter(|i| (*i as usize) < DICTIONARY_LEN)?;
+    }
+    Some(word_indices_to_otp(&indices))
+}
+
+/// Encode a 64-bit value as six owned words from `dictionary`, so that the
+/// result can outlive a dictionary loaded at runtime.
+///
+/// Returns `None` if `dictionary` is missing a word that is needed.
+#[cfg(feature = "alloc")]
+pub fn convert_to_owned_words_with_dictionary <D: Dictionary + ?Sized> (
+    otp: &[u8; 8],
+    dictionary: &D,
+) -> Option<[String; 6]> {
+    let words = convert_to_word_format_with_dictionary(otp, dictionary)?;
+    Some(words.map(ToString::to_string))
+}
+
+/// Write a 64-bit value as six space-separated words from `dictionary` to any
+/// [core::fmt::Write] sink. If `with_prefix` is `true`, the words are prefixed
+/// with `word:`. This does not allocate.
+///
+/// Returns an error, having written nothing, if `dictionary` is missing a word
+/// that is needed.
+///
+/// See [crate::write_words].
+pub fn write_words_with_dictionary <W: fmt::Write, D: Dictionary + ?Sized> (
+    w: &mut W,
+    otp: &[u8; 8],
+    dictionary: &D,
+    with_prefix: bool,
+) -> fmt::Result {
+    let words = convert_to_word_format_with_dictionary(otp, dictionary).ok_or(fmt::Error)?;
+    if with_prefix {
+        w.write_str("word:")?;
+    }
+    for (i, word) in words.iter().enumerate() {
+        if i > 0 {
+            w.write_char(' ')?;
+        }
+        w.write_str(word)?;
+    }
+    Ok(())
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+
+    use super::*;
+    use crate::{convert_to_word_format, otp_to_word_string};
+
+    #[test]
+    fn encodes_with_custom_dictionaries () {
+        let otp = [ 0x9E, 0x87, 0x61, 0x34, 0xD9, 0x04, 0x99, 0xDD ];
+        assert_eq!(
+            convert_to_word_format_with_dictionary(&otp, &StandardDictionary),
+            Some(convert_to_word_format(&otp)),
+        );
+        let numbers: Vec<String> = (0..DICTIONARY_LEN).map(|i| i.to_string()).collect();
+        let words = convert_to_word_format_with_dictionary(&otp, &numbers).unwrap();
+        for (word, standard) in words.iter().zip(convert_to_word_format(&otp)) {
+            assert_eq!(word.parse::<u16>().ok(), word_index(standard));
+        }
+        assert_eq!(numbers.index_of("2047"), Some(2047));
+        assert!(!numbers.is_standard());
+        let short: &[&str] = &[ "A", "B" ];
+        assert_eq!(convert_to_word_format_with_dictionary(&otp, short), None);
+        assert_eq!(short.index_of("b"), Some(1));
+    }
+
+    #[test]
+    fn decodes_with_custom_dictionaries () {
+        let otp = [ 0x9E, 0x87, 0x61, 0x34, 0xD9, 0x04, 0x99, 0xDD ];
+        let numbers: Vec<String> = (0..DICTIONARY_LEN).map(|i| i.to_string()).collect();
+        let words = convert_to_word_format_with_dictionary(&otp, &numbers).unwrap();
+        assert_eq!(decode_word_format_with_dictionary(words, &numbers), Some((otp, true)));
+        let mut bad = words;
+        bad[5] = &numbers[(words[5].parse::<usize>().unwrap()) ^ 1];
+        assert_eq!(decode_word_format_with_dictionary(bad, &numbers), Some((otp, false)));
+        bad[0] = "2048";
+        assert_eq!(decode_word_format_with_dictionary(bad, &numbers), None);
+    }
+
+    #[test]
+    fn formats_words_from_custom_dictionaries () {
+        let otp = [ 0x9E, 0x87, 0x61, 0x34, 0xD9, 0x04, 0x99, 0xDD ];
+        let loaded: Vec<String> = (0..DICTIONARY_LEN)
+            .map(|i| index_to_word(i as u16).unwrap().to_lowercase())
+            .collect();
+        let owned = convert_to_owned_words_with_dictionary(&otp, &loaded).unwrap();
+        drop(loaded);
+        assert_eq!(owned, [ "inch", "sea", "anne", "long", "ahem", "tour" ]);
+
+        let mut s = String::new();
+        write_words_with_dictionary(&mut s, &otp, &StandardDictionary, true).unwrap();
+        assert_eq!(s, otp_to_word_string(&otp, true));
+        let short: &[&str] = &[ "A" ];
+        let mut s = String::new();
+        assert!(write_words_with_dictionary(&mut s, &otp, short, true).is_err());
+        assert!(s.is_empty());
+    }
+
+    #[test]
+    fn validates_dictionaries () {
+        assert_eq!(StandardDictionary.validate(), DictionaryReport::default());
+        let numbers: Vec<String> = (0..DICTIONARY_LEN).map(|i| i.to_string()).collect();
+        assert!(numbers.validate().is_valid_alternate());
+
+        let mut bad = numbers.clone();
+        bad[7] = String::from("ABLE");
+        bad[9] = String::from("12345");
+        bad[10] = String::from("\u{e9}t\u{e9}");
+        bad[11] = String::from("2000");
+        bad.push(String::from("MORE"));
+        let report = bad.validate();
+        assert!(!report.is_valid());
+        assert!(report.too_many);
+        assert!(report.missing.is_empty());
+        assert_eq!(report.bad_length, DictionaryIssue { count: 2, first: Some(9) });
+        assert_eq!(report.non_ascii, DictionaryIssue { count: 1, first: Some(10) });
+        assert_eq!(report.duplicates, DictionaryIssue { count: 1, first: Some(2000) });
+        assert_eq!(report.standard_words, DictionaryIssue { count: 1, first: Some(7) });
+
+        let mut standard: Vec<String> = (0..DICTIONARY_LEN)
+            .map(|i| index_to_word(i as u16).unwrap().to_lowercase())
+            .collect();
+        assert!(standard.validate().is_valid());
+        assert!(!standard.validate().is_valid_alternate());
+        standard.truncate(100);
+        assert_eq!(standard.validate().missing, DictionaryIssue { count: DICTIONARY_LEN - 100, first: Some(100) });
+    }
+
+}
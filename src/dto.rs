@@ -0,0 +1,296 @@
+//! Challenges and responses as plain structures for JSON APIs, so that web
+//! frontends can send and receive their fields, rather than formatting and
+//! parsing the protocol strings themselves.
+//!
+//! With `serde_json`, a challenge looks like this:
+//!
+//! ```json
+//! { "alg": "md5", "count": 499, "seed": "ke1234", "ext": true }
+//! ```
+//!
+//! and a response like this, with a `type` that is its prefix in the protocol:
+//!
+//! ```json
+//! { "type": "word", "otp": "INCH SEA ANNE LONG AHEM TOUR" }
+//! { "type": "init-hex", "current_otp": "9E87 6134 D904 99DD", "new_alg": "sha1",
+//!   "new_count": 499, "new_seed": "ke1235", "new_otp": "..." }
+//! ```
+use alloc::string::String;
+use core::fmt;
+use serde::{Deserialize, Serialize};
+use crate::{
+    decode_hex_otp,
+    format_hex,
+    parse_otp_challenge,
+    parse_otp_response,
+    write_otp_response,
+    Capabilities,
+    HexOrWords,
+    HexStyle,
+    OTPChallenge,
+    OTPInit,
+    OTPResponse,
+};
+
+/// The fields of a challenge. Its `Display` output is the challenge string,
+/// such as `otp-md5 499 ke1234 ext`.
+///
+/// ```rust
+/// use rfc2289_otp::dto::ChallengeDto;
+/// let dto = ChallengeDto::parse("otp-md5 499 ke1234 ext").unwrap();
+/// assert_eq!((dto.alg.as_str(), dto.count, dto.seed.as_str(), dto.ext), ("md5", 499, "ke1234", true));
+/// assert_eq!(dto.to_string(), "otp-md5 499 ke1234 ext");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChallengeDto {
+    /// The name of the hash algorithm, such as `md5`.
+    pub alg: String,
+    /// The count of the OTP being requested.
+    pub count: usize,
+    /// The seed.
+    pub seed: String,
+    /// Whether the server accepts the extended responses of
+    /// [IETF RFC 2243](https://www.rfc-editor.org/rfc/rfc2243). See
+    /// [OTPChallenge::is_extended].
+    #[serde(default)]
+    pub ext: bool,
+}
+
+impl ChallengeDto {
+
+    /// Parse a challenge string, as [parse_otp_challenge] does.
+    pub fn parse (s: &str) -> Option<ChallengeDto> {
+        parse_otp_challenge(s).as_ref().map(ChallengeDto::from)
+    }
+
+    /// The challenge that this describes, borrowing its fields.
+    pub fn to_challenge (&self) -> OTPChallenge<'_> {
+        let tokens = if self.ext { " ext" } else { "" };
+        OTPChallenge {
+            hash_alg: &self.alg,
+            hash_count: self.count,
+            seed: &self.seed,
+            capabilities: Capabilities { tokens, seed_index: None },
+        }
+    }
+
+}
+
+impl From<&OTPChallenge<'_>> for ChallengeDto {
+
+    fn from (challenge: &OTPChallenge<'_>) -> Self {
+        ChallengeDto {
+            alg: String::from(challenge.hash_alg),
+            count: challenge.hash_count,
+            seed: String::from(challenge.seed),
+            ext: challenge.is_extended(),
+        }
+    }
+
+}
+
+#[cfg(feature = "words")]
+impl From<&crate::server::IssuedChallenge> for ChallengeDto {
+
+    /// Describe a challenge issued by an [crate::server::OtpServer], which
+    /// accepts init responses, so `ext` is set.
+    fn from (challenge: &crate::server::IssuedChallenge) -> Self {
+        ChallengeDto {
+            alg: String::from(challenge.alg.name()),
+            count: challenge.count,
+            seed: String::from(challenge.seed.as_str()),
+            ext: true,
+        }
+    }
+
+}
+
+impl fmt::Display for ChallengeDto {
+
+    fn fmt (&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "otp-{} {} {}", self.alg, self.count, self.seed)?;
+        if self.ext {
+            f.write_str(" ext")?;
+        }
+        Ok(())
+    }
+
+}
+
+/// The fields of a response, tagged with its `type`, which is the prefix of
+/// the response string, such as `hex` or `init-word`. Hexadecimal values are
+/// sixteen hexadecimal digits, optionally with spaces or tabs between them,
+/// and words are six words separated by spaces.
+///
+/// ```rust
+/// use rfc2289_otp::dto::ResponseDto;
+/// let dto = ResponseDto::parse("hex:9e876134d90499dd").unwrap();
+/// assert_eq!(dto, ResponseDto::Hex { otp: "9E87 6134 D904 99DD".into() });
+/// assert_eq!(dto.to_wire().unwrap(), "hex:9E87 6134 D904 99DD");
+/// ```
+///
+/// Like the parsed responses, its `Debug` output does not include the OTP
+/// values, unless it is wrapped using `reveal()`, or the `unredacted-debug`
+/// feature is enabled.
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum ResponseDto {
+    /// A `hex:` response.
+    Hex {
+        /// The OTP value.
+        otp: String,
+    },
+    /// A `word:` response.
+    Word {
+        /// The OTP value.
+        otp: String,
+    },
+    /// An `init-hex:` response.
+    InitHex(InitDto),
+    /// An `init-word:` response.
+    InitWord(InitDto),
+}
+
+/// The fields of an init response, as in [OTPInit]. Its `Debug` output does not
+/// include the OTP values, as with [ResponseDto].
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InitDto {
+    /// The OTP value for the current sequence.
+    pub current_otp: String,
+    /// The name of the hash algorithm of the new sequence.
+    pub new_alg: String,
+    /// The count of the new sequence.
+    pub new_count: usize,
+    /// The seed of the new sequence.
+    pub new_seed: String,
+    /// The first OTP value of the new sequence.
+    pub new_otp: String,
+    /// Any fields after the new OTP, as in [OTPInit::extra].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extra: Option<String>,
+}
+
+impl InitDto {
+
+    /// The init response that this describes, with the given OTP values.
+    fn to_init <'a> (&'a self, current_otp: HexOrWords<'a>, new_otp: HexOrWords<'a>) -> OTPInit<'a> {
+        OTPInit {
+            current_otp,
+            new_otp,
+            new_alg: &self.new_alg,
+            new_seq_num: self.new_count,
+            new_seed: &self.new_seed,
+            extra: self.extra.as_deref(),
+        }
+    }
+
+}
+
+impl ResponseDto {
+
+    /// Parse a response string, as [parse_otp_response] does. Returns `None`
+    /// if it cannot be parsed, or is an init response that mixes hexadecimal
+    /// and words.
+    pub fn parse (s: &str) -> Option<ResponseDto> {
+        ResponseDto::from_response(&parse_otp_response(s)?)
+    }
+
+    /// Describe a parsed response. Returns `None` if it is an init response
+    /// that mixes hexadecimal and words.
+    pub fn from_response (response: &OTPResponse<'_>) -> Option<ResponseDto> {
+        let text = |value: &HexOrWords<'_>| match value {
+            HexOrWords::Hex(h) => format_hex(h, HexStyle::CANONICAL),
+            HexOrWords::Words(w) => String::from(*w),
+        };
+        match response {
+            OTPResponse::Current(value @ HexOrWords::Hex(_)) => Some(ResponseDto::Hex { otp: text(value) }),
+            OTPResponse::Current(value @ HexOrWords::Words(_)) => Some(ResponseDto::Word { otp: text(value) }),
+            OTPResponse::Init(init) => {
+                let dto = InitDto {
+                    current_otp: text(&init.current_otp),
+                    new_alg: String::from(init.new_alg),
+                    new_count: init.new_seq_num,
+                    new_seed: String::from(init.new_seed),
+                    new_otp: text(&init.new_otp),
+                    extra: init.extra.map(String::from),
+                };
+                match (&init.current_otp, &init.new_otp) {
+                    (HexOrWords::Hex(_), HexOrWords::Hex(_)) => Some(ResponseDto::InitHex(dto)),
+                    (HexOrWords::Words(_), HexOrWords::Words(_)) => Some(ResponseDto::InitWord(dto)),
+                    _ => None,
+                }
+            },
+        }
+    }
+
+    /// The response that this describes, borrowing its fields. Returns `None`
+    /// if a hexadecimal value is not sixteen hexadecimal digits.
+    pub fn to_response (&self) -> Option<OTPResponse<'_>> {
+        Some(match self {
+            ResponseDto::Hex { otp } => OTPResponse::Current(HexOrWords::Hex(decode_hex_otp(otp)?)),
+            ResponseDto::Word { otp } => OTPResponse::Current(HexOrWords::Words(otp)),
+            ResponseDto::InitHex(dto) => OTPResponse::Init(dto.to_init(
+                HexOrWords::Hex(decode_hex_otp(&dto.current_otp)?),
+                HexOrWords::Hex(decode_hex_otp(&dto.new_otp)?),
+            )),
+            ResponseDto::InitWord(dto) => OTPResponse::Init(dto.to_init(
+                HexOrWords::Words(&dto.current_otp),
+                HexOrWords::Words(&dto.new_otp),
+            )),
+        })
+    }
+
+    /// The response string, as written by [write_otp_response]. Returns `None`
+    /// if a hexadecimal value is not sixteen hexadecimal digits.
+    pub fn to_wire (&self) -> Option<String> {
+        let mut s = String::new();
+        write_otp_response(&mut s, &self.to_response()?).ok()?;
+        Some(s)
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+
+    #[test]
+    fn converts_challenges () {
+        let dto = ChallengeDto::parse("otp-sha1 99 ext TeSt").unwrap();
+        assert_eq!(dto, ChallengeDto { alg: "sha1".into(), count: 99, seed: "TeSt".into(), ext: true });
+        assert_eq!(dto.to_string(), "otp-sha1 99 TeSt ext");
+        let challenge = dto.to_challenge();
+        assert_eq!((challenge.hash_alg, challenge.hash_count, challenge.seed), ("sha1", 99, "TeSt"));
+        assert!(challenge.is_extended());
+        let dto = ChallengeDto { ext: false, ..dto };
+        assert_eq!(dto.to_string(), "otp-sha1 99 TeSt");
+        assert!(!dto.to_challenge().is_extended());
+        assert_eq!(ChallengeDto::parse(&dto.to_string()), Some(dto));
+    }
+
+    #[test]
+    fn converts_responses () {
+        let responses = [
+            "word:INCH SEA ANNE LONG AHEM TOUR",
+            "hex:9E87 6134 D904 99DD",
+            "init-hex:9E87 6134 D904 99DD:sha1 499 ke1235:1111 2222 3333 4444",
+            "init-word:INCH SEA ANNE LONG AHEM TOUR:md5 499 ke1235:GLOB SOFT CALL ARCH FLEW FREY:ext1",
+        ];
+        for response in responses {
+            let dto = ResponseDto::parse(response).unwrap();
+            assert_eq!(dto.to_wire().as_deref(), Some(response));
+        }
+        let ResponseDto::InitWord(init) = ResponseDto::parse(responses[3]).unwrap() else {
+            unreachable!();
+        };
+        assert_eq!(init.extra.as_deref(), Some("ext1"));
+        assert_eq!(init.new_count, 499);
+        assert_eq!(ResponseDto::Hex { otp: "9E87 6134".into() }.to_wire(), None);
+        assert_eq!(
+            ResponseDto::parse("init-hex:9E87 6134 D904 99DD:md5 499 ke1235:INCH SEA ANNE LONG AHEM TOUR"),
+            None,
+        );
+    }
+
+}
@@ -0,0 +1,205 @@
+//! A single error type for the whole crate, so that applications can bubble
+//! errors from parsing, calculating, decoding, and verifying OTPs through
+//! their authentication stack with `?`:
+//!
+//! ```rust
+//! use rfc2289_otp::{calculate_otp_with_options, decode_word_str, CalculateOptions, OtpError};
+//!
+//! fn check (passphrase: &str, response: &str) -> Result<bool, OtpError> {
+//!     let expected = calculate_otp_with_options("md5", passphrase, "TeSt", 0, &CalculateOptions::default())?;
+//!     Ok(decode_word_str(response)? == expected)
+//! }
+//!
+//! # #[cfg(feature = "md5")]
+//! assert_eq!(check("This is a test.", "INCH SEA ANNE LONG AHEM TOUR"), Ok(true));
+//! assert!(check("This is a test.", "INCH SEA ANNE LONG AHEM").is_err());
+//! ```
+use core::fmt;
+use crate::{BufferTooSmall, CalculateError, ProvisioningError};
+#[cfg(feature = "parsing-challenge")]
+use crate::ChallengeParseError;
+#[cfg(feature = "words")]
+use crate::WordDecodeError;
+#[cfg(feature = "parsing-response")]
+use crate::StreamError;
+#[cfg(all(feature = "parsing-response", feature = "words"))]
+use crate::{server::ServerError, CanonicalizeError, InitValidationError};
+#[cfg(all(feature = "alloc", feature = "words"))]
+use crate::sheet::SheetError;
+
+/// Any error returned by this crate. Each of the more specific error types
+/// converts into this one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum OtpError {
+    /// A challenge could not be parsed.
+    #[cfg(feature = "parsing-challenge")]
+    Challenge(ChallengeParseError),
+    /// An OTP value could not be calculated.
+    Calculate(CalculateError),
+    /// Dictionary words could not be decoded.
+    #[cfg(feature = "words")]
+    WordDecode(WordDecodeError),
+    /// An init response was invalid.
+    #[cfg(all(feature = "parsing-response", feature = "words"))]
+    Init(InitValidationError),
+    /// A response could not be canonicalized.
+    #[cfg(all(feature = "parsing-response", feature = "words"))]
+    Canonicalize(CanonicalizeError),
+    /// A response could not be parsed incrementally.
+    #[cfg(feature = "parsing-response")]
+    Stream(StreamError),
+    /// A server could not issue a challenge or verify a response.
+    #[cfg(all(feature = "parsing-response", feature = "words"))]
+    Server(ServerError),
+    /// An OTP sheet could not be generated.
+    #[cfg(all(feature = "alloc", feature = "words"))]
+    Sheet(SheetError),
+    /// A new OTP sequence could not be provisioned.
+    Provisioning(ProvisioningError),
+    /// A buffer did not have enough space for the output.
+    BufferTooSmall,
+}
+
+impl fmt::Display for OtpError {
+
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            #[cfg(feature = "parsing-challenge")]
+            OtpError::Challenge(e) => e.fmt(f),
+            OtpError::Calculate(e) => e.fmt(f),
+            #[cfg(feature = "words")]
+            OtpError::WordDecode(e) => e.fmt(f),
+            #[cfg(all(feature = "parsing-response", feature = "words"))]
+            OtpError::Init(e) => e.fmt(f),
+            #[cfg(all(feature = "parsing-response", feature = "words"))]
+            OtpError::Canonicalize(e) => e.fmt(f),
+            #[cfg(feature = "parsing-response")]
+            OtpError::Stream(e) => e.fmt(f),
+            #[cfg(all(feature = "parsing-response", feature = "words"))]
+            OtpError::Server(e) => e.fmt(f),
+            #[cfg(all(feature = "alloc", feature = "words"))]
+            OtpError::Sheet(e) => e.fmt(f),
+            OtpError::Provisioning(e) => e.fmt(f),
+            OtpError::BufferTooSmall => BufferTooSmall.fmt(f),
+        }
+    }
+
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for OtpError {}
+
+#[cfg(feature = "parsing-challenge")]
+impl From<ChallengeParseError> for OtpError {
+
+    fn from(e: ChallengeParseError) -> Self {
+        OtpError::Challenge(e)
+    }
+
+}
+
+impl From<CalculateError> for OtpError {
+
+    fn from(e: CalculateError) -> Self {
+        OtpError::Calculate(e)
+    }
+
+}
+
+impl From<ProvisioningError> for OtpError {
+
+    fn from(e: ProvisioningError) -> Self {
+        OtpError::Provisioning(e)
+    }
+
+}
+
+#[cfg(feature = "words")]
+impl From<WordDecodeError> for OtpError {
+
+    fn from(e: WordDecodeError) -> Self {
+        OtpError::WordDecode(e)
+    }
+
+}
+
+#[cfg(all(feature = "parsing-response", feature = "words"))]
+impl From<InitValidationError> for OtpError {
+
+    fn from(e: InitValidationError) -> Self {
+        OtpError::Init(e)
+    }
+
+}
+
+#[cfg(all(feature = "parsing-response", feature = "words"))]
+impl From<CanonicalizeError> for OtpError {
+
+    fn from(e: CanonicalizeError) -> Self {
+        OtpError::Canonicalize(e)
+    }
+
+}
+
+#[cfg(feature = "parsing-response")]
+impl From<StreamError> for OtpError {
+
+    fn from(e: StreamError) -> Self {
+        OtpError::Stream(e)
+    }
+
+}
+
+#[cfg(all(feature = "parsing-response", feature = "words"))]
+impl From<ServerError> for OtpError {
+
+    fn from(e: ServerError) -> Self {
+        OtpError::Server(e)
+    }
+
+}
+
+#[cfg(all(feature = "alloc", feature = "words"))]
+impl From<SheetError> for OtpError {
+
+    fn from(e: SheetError) -> Self {
+        OtpError::Sheet(e)
+    }
+
+}
+
+impl From<BufferTooSmall> for OtpError {
+
+    fn from(_: BufferTooSmall) -> Self {
+        OtpError::BufferTooSmall
+    }
+
+}
+
+#[cfg(all(test, feature = "alloc", feature = "words", feature = "parsing"))]
+mod tests {
+
+    use super::*;
+    use alloc::string::ToString;
+    use crate::parse_otp_challenge_strict;
+
+    fn parse_and_decode (challenge: &str, words: &str) -> Result<[u8; 8], OtpError> {
+        parse_otp_challenge_strict(challenge)?;
+        Ok(crate::decode_word_str(words)?)
+    }
+
+    #[test]
+    fn converts_specific_errors () {
+        assert_eq!(
+            parse_and_decode("otp-md5 499 ke:1234", "INCH SEA ANNE LONG AHEM TOUR"),
+            Err(OtpError::Challenge(ChallengeParseError::BadSeed)),
+        );
+        let e = parse_and_decode("otp-md5 499 ke1234", "INCH SEA").unwrap_err();
+        assert_eq!(e, OtpError::WordDecode(WordDecodeError::WrongWordCount(2)));
+        assert_eq!(e.to_string(), "expected six words, but found 2");
+        assert_eq!(OtpError::from(ServerError::SeedReused).to_string(), "seed reused");
+        assert_eq!(OtpError::from(BufferTooSmall), OtpError::BufferTooSmall);
+    }
+
+}
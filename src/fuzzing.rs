@@ -0,0 +1,133 @@
+//! Implementations of `arbitrary::Arbitrary` for the parsed OTP types, which
+//! generate structurally valid instances: recognized algorithms, alphanumeric
+//! seeds of one to sixteen characters, and words that decode correctly.
+use arbitrary::{Arbitrary, Result, Unstructured};
+use crate::{HashAlg, HexOrWords, OTPChallenge, OTPInit, OTPResponse};
+
+/// Seeds used when the fuzzer's input does not start with a valid seed.
+const FALLBACK_SEEDS: [&str; 4] = [ "ke1234", "alpha1", "correct", "TeSt" ];
+
+/// Valid six-word OTPs, from Appendix C of
+/// [IETF RFC 2289](https://www.rfc-editor.org/rfc/rfc2289.html). Words have to
+/// be chosen from a fixed list, since `HexOrWords::Words` borrows its string.
+const SIX_WORDS: [&str; 6] = [
+    "ROME MUG FRED SCAN LIVE LACE",
+    "INCH SEA ANNE LONG AHEM TOUR",
+    "MILT VARY MAST OK SEES WENT",
+    "AURA ALOE HURL WING BERG WAIT",
+    "RUST WELT KICK FELL TAIL FRAU",
+    "FLIT DOSE ALSO MEW DRUM DEFY",
+];
+
+/// Generate a seed that is valid per Section 6.0 of
+/// [IETF RFC 2289](https://www.rfc-editor.org/rfc/rfc2289.html): one to
+/// sixteen alphanumeric characters.
+fn arbitrary_seed <'a> (u: &mut Unstructured<'a>) -> Result<&'a str> {
+    let s: &'a str = u.arbitrary()?;
+    let len = s
+        .bytes()
+        .take(16)
+        .take_while(u8::is_ascii_alphanumeric)
+        .count();
+    if len > 0 {
+        Ok(&s[..len])
+    } else {
+        u.choose(&FALLBACK_SEEDS).copied()
+    }
+}
+
+impl<'a> Arbitrary<'a> for HashAlg {
+
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        u.choose(&HashAlg::ALL).copied()
+    }
+
+}
+
+impl<'a> Arbitrary<'a> for OTPChallenge<'a> {
+
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(OTPChallenge {
+            hash_alg: u.arbitrary::<HashAlg>()?.name(),
+            hash_count: u.arbitrary::<u16>()? as usize,
+            seed: arbitrary_seed(u)?,
+            capabilities: Default::default(),
+        })
+    }
+
+}
+
+impl<'a> Arbitrary<'a> for HexOrWords<'a> {
+
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        if u.arbitrary()? {
+            Ok(HexOrWords::Hex(u.arbitrary()?))
+        } else {
+            Ok(HexOrWords::Words(u.choose(&SIX_WORDS)?))
+        }
+    }
+
+}
+
+impl<'a> Arbitrary<'a> for OTPInit<'a> {
+
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        // Both OTPs must be hex, or both must be words.
+        let (current_otp, new_otp) = if u.arbitrary()? {
+            (HexOrWords::Hex(u.arbitrary()?), HexOrWords::Hex(u.arbitrary()?))
+        } else {
+            (HexOrWords::Words(u.choose(&SIX_WORDS)?), HexOrWords::Words(u.choose(&SIX_WORDS)?))
+        };
+        Ok(OTPInit {
+            current_otp,
+            new_otp,
+            new_alg: u.arbitrary::<HashAlg>()?.name(),
+            new_seq_num: u.arbitrary::<u16>()? as usize,
+            new_seed: arbitrary_seed(u)?,
+            extra: None,
+        })
+    }
+
+}
+
+impl<'a> Arbitrary<'a> for OTPResponse<'a> {
+
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        if u.arbitrary()? {
+            Ok(OTPResponse::Init(u.arbitrary()?))
+        } else {
+            Ok(OTPResponse::Current(u.arbitrary()?))
+        }
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generates_structurally_valid_values () {
+        let data: [u8; 256] = core::array::from_fn(|i| (i * 37 % 251) as u8);
+        let mut u = Unstructured::new(&data);
+        for _ in 0..16 {
+            let challenge: OTPChallenge = u.arbitrary().unwrap();
+            assert!(HashAlg::from_name(challenge.hash_alg).is_some());
+            assert!((1..=16).contains(&challenge.seed.len()));
+            assert!(challenge.seed.bytes().all(|b| b.is_ascii_alphanumeric()));
+            let response: OTPResponse = u.arbitrary().unwrap();
+            match response {
+                OTPResponse::Current(otp) => assert!(otp.decode().is_ok()),
+                OTPResponse::Init(init) => {
+                    assert!(init.current_otp.decode().is_ok());
+                    assert!(init.new_otp.decode().is_ok());
+                    assert_eq!(
+                        matches!(init.current_otp, HexOrWords::Hex(_)),
+                        matches!(init.new_otp, HexOrWords::Hex(_)),
+                    );
+                },
+            }
+        }
+    }
+
+}
@@ -0,0 +1,175 @@
+//! Carrying challenges and responses in HTTP authentication headers, for web
+//! services such as admin panels that log users in with OTPs.
+//!
+//! The server sends the challenge in a `WWW-Authenticate` header using the
+//! `OTP` scheme, and the client sends its response back in an `Authorization`
+//! header, each as a quoted auth-param, per
+//! [IETF RFC 9110](https://www.rfc-editor.org/rfc/rfc9110.html#section-11):
+//!
+//! ```text
+//! WWW-Authenticate: OTP realm="admin", challenge="otp-md5 499 ke1234 ext"
+//! Authorization: OTP response="word:INCH SEA ANNE LONG AHEM TOUR"
+//! ```
+//!
+//! These functions only deal with header values, so they work with any HTTP
+//! library:
+//!
+//! ```rust
+//! use rfc2289_otp::http::{format_www_authenticate, parse_www_authenticate};
+//! use rfc2289_otp::parse_otp_challenge;
+//! let challenge = parse_otp_challenge("otp-md5 499 ke1234 ext").unwrap();
+//! let value = format_www_authenticate(&challenge, Some("admin")).unwrap();
+//! assert_eq!(value, r#"OTP realm="admin", challenge="otp-md5 499 ke1234 ext""#);
+//! assert_eq!(parse_www_authenticate(&value), Some(challenge));
+//! ```
+use alloc::string::String;
+use core::fmt::Write;
+use crate::uri::is_valid_alg;
+use crate::{is_valid_seed, parse_otp_challenge, parse_otp_response, write_otp_response, OTPChallenge, OTPResponse};
+
+/// The name of the HTTP authentication scheme.
+pub const AUTH_SCHEME: &str = "OTP";
+
+/// Format the value of a `WWW-Authenticate` header carrying `challenge`, and
+/// `realm`, if given. Any `"` or `\` in the realm is escaped.
+///
+/// Returns `None` if the realm contains control characters, such as CR or LF,
+/// which cannot appear in a header value, or if the algorithm name, seed, or a
+/// capability word is not valid per the same rules as [crate::write_otp_uri],
+/// since these are written into a quoted string without escaping.
+pub fn format_www_authenticate (challenge: &OTPChallenge<'_>, realm: Option<&str>) -> Option<String> {
+    if !is_valid_alg(challenge.hash_alg)
+        || !is_valid_seed(challenge.seed)
+        || !challenge.capabilities.iter().all(is_valid_alg)
+        || realm.is_some_and(|realm| realm.chars().any(char::is_control)) {
+        return None;
+    }
+    let mut s = String::from(AUTH_SCHEME);
+    if let Some(realm) = realm {
+        s.push_str(" realm=\"");
+        for c in realm.chars() {
+            if c == '"' || c == '\\' {
+                s.push('\\');
+            }
+            s.push(c);
+        }
+        s.push_str("\",");
+    }
+    // Writing to a String cannot fail.
+    let _ = write!(s, " challenge=\"otp-{} {} {}", challenge.hash_alg, challenge.hash_count, challenge.seed);
+    for capability in challenge.capabilities.iter() {
+        s.push(' ');
+        s.push_str(capability);
+    }
+    s.push('"');
+    Some(s)
+}
+
+/// Parse the challenge from the value of a `WWW-Authenticate` header, as
+/// written by [format_www_authenticate]. Returns `None` if the scheme is not
+/// [AUTH_SCHEME], or there is no valid `challenge` parameter.
+pub fn parse_www_authenticate (value: &str) -> Option<OTPChallenge<'_>> {
+    parse_otp_challenge(auth_param(value, "challenge")?)
+}
+
+/// Format the value of an `Authorization` header carrying `response`. Returns
+/// `None` if it is an init response that mixes hexadecimal and words, which
+/// cannot be written.
+pub fn format_authorization (response: &OTPResponse<'_>) -> Option<String> {
+    let mut s = String::from(AUTH_SCHEME);
+    s.push_str(" response=\"");
+    write_otp_response(&mut s, response).ok()?;
+    s.push('"');
+    Some(s)
+}
+
+/// Parse the response from the value of an `Authorization` header, as written
+/// by [format_authorization]. Returns `None` if the scheme is not
+/// [AUTH_SCHEME], or there is no valid `response` parameter.
+pub fn parse_authorization (value: &str) -> Option<OTPResponse<'_>> {
+    parse_otp_response(auth_param(value, "response")?)
+}
+
+/// The value of the auth-param `name`, compared case-insensitively, in a
+/// header value using the [AUTH_SCHEME]. If its value is a quoted string
+/// containing escapes, it is refused, since it cannot be returned without
+/// unescaping it, and OTP challenges and responses never contain one.
+fn auth_param <'a> (value: &'a str, name: &str) -> Option<&'a str> {
+    let value = value.trim_start_matches([' ', '\t']);
+    let (scheme, mut rest) = value.split_at(value.find([' ', '\t']).unwrap_or(value.len()));
+    if !scheme.eq_ignore_ascii_case(AUTH_SCHEME) {
+        return None;
+    }
+    loop {
+        rest = rest.trim_start_matches([' ', '\t', ',']);
+        if rest.is_empty() {
+            return None;
+        }
+        let (param, after) = rest.split_once('=')?;
+        let after = after.trim_start_matches([' ', '\t']);
+        let (param_value, after) = match after.strip_prefix('"') {
+            Some(quoted) => {
+                let mut escaped = false;
+                let end = quoted.bytes().position(|b| {
+                    let end = !escaped && b == b'"';
+                    escaped = !escaped && b == b'\\';
+                    end
+                })?;
+                (&quoted[..end], &quoted[end + 1..])
+            },
+            None => after.split_at(after.find(',').unwrap_or(after.len())),
+        };
+        if param.trim_matches([' ', '\t']).eq_ignore_ascii_case(name) {
+            return Some(param_value.trim_matches([' ', '\t'])).filter(|v| !v.contains('\\'));
+        }
+        rest = after;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::HexOrWords;
+
+    #[test]
+    fn round_trips_www_authenticate () {
+        let challenge = parse_otp_challenge("otp-sha1 99 TeSt").unwrap();
+        let value = format_www_authenticate(&challenge, Some(r#"the "admin" \ panel"#)).unwrap();
+        assert_eq!(value, r#"OTP realm="the \"admin\" \\ panel", challenge="otp-sha1 99 TeSt""#);
+        assert_eq!(parse_www_authenticate(&value), Some(parse_otp_challenge("otp-sha1 99 TeSt").unwrap()));
+        let value = format_www_authenticate(&challenge, None).unwrap();
+        assert_eq!(value, r#"OTP challenge="otp-sha1 99 TeSt""#);
+        assert_eq!(parse_www_authenticate(&value), Some(challenge));
+
+        let challenge = parse_www_authenticate(r#"otp Realm=admin,CHALLENGE = "otp-md5 499 ke1234 ext" , x=y"#).unwrap();
+        assert_eq!((challenge.hash_alg, challenge.hash_count, challenge.seed), ("md5", 499, "ke1234"));
+        assert!(challenge.is_extended());
+        assert_eq!(parse_www_authenticate(r#"Basic challenge="otp-md5 499 ke1234""#), None);
+        assert_eq!(parse_www_authenticate(r#"OTP realm="admin""#), None);
+        assert_eq!(parse_www_authenticate(r#"OTP challenge="otp-md5 499 ke1234"#), None);
+        assert_eq!(parse_www_authenticate(r#"OTP challenge="otp-md5 499 ke\1234""#), None);
+    }
+
+    #[test]
+    fn refuses_to_format_header_injections () {
+        let challenge = parse_otp_challenge("otp-md5 499 ke1234").unwrap();
+        assert_eq!(format_www_authenticate(&challenge, Some("admin\r\nSet-Cookie: x=y")), None);
+        assert_eq!(format_www_authenticate(&challenge, Some("admin\0")), None);
+        let challenge = parse_otp_challenge("otp-md5 499 ke\"1234").unwrap();
+        assert_eq!(format_www_authenticate(&challenge, None), None);
+        let challenge = OTPChallenge { hash_alg: "md5\"", ..parse_otp_challenge("otp-md5 499 ke1234").unwrap() };
+        assert_eq!(format_www_authenticate(&challenge, None), None);
+    }
+
+    #[test]
+    fn round_trips_authorization () {
+        let response = parse_otp_response("word:INCH SEA ANNE LONG AHEM TOUR").unwrap();
+        let value = format_authorization(&response).unwrap();
+        assert_eq!(value, r#"OTP response="word:INCH SEA ANNE LONG AHEM TOUR""#);
+        assert!(parse_authorization(&value) == Some(response));
+        assert!(parse_authorization(r#"OTP response=hex:9E876134D90499DD"#)
+            == Some(OTPResponse::Current(HexOrWords::Hex([ 0x9E, 0x87, 0x61, 0x34, 0xD9, 0x04, 0x99, 0xDD ]))));
+        assert!(parse_authorization(r#"Bearer response="word:INCH SEA ANNE LONG AHEM TOUR""#).is_none());
+    }
+
+}
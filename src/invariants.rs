@@ -0,0 +1,154 @@
+//! Self-checks that encoding and decoding remain inverse operations. These are
+//! for integrators with custom dictionaries or formatters, who can run
+//! [check_invariants] in their own tests:
+//!
+//! ```rust
+//! use rfc2289_otp::{invariants::check_invariants, StandardDictionary};
+//! check_invariants(&StandardDictionary).unwrap();
+//! ```
+use core::fmt;
+use crate::{
+    convert_to_word_format,
+    convert_to_word_format_with_dictionary,
+    decode_hex_otp,
+    decode_word_format_with_dictionary,
+    decode_word_format_with_std_dict,
+    write_hex,
+    Dictionary,
+    HexStyle,
+    MAX_HEX_STRING_LEN,
+};
+
+/// The number of pseudo-random values, in addition to a few edge cases, that
+/// [check_invariants] tests.
+pub const SAMPLE_COUNT: usize = 4096;
+
+/// An invariant that did not hold.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum InvariantViolation {
+    /// Encoding this value as words and decoding them did not produce the same
+    /// value with a valid checksum.
+    Words([u8; 8]),
+    /// Formatting this value as hexadecimal in some style and decoding it did
+    /// not produce the same value.
+    Hex([u8; 8]),
+}
+
+impl fmt::Display for InvariantViolation {
+
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (kind, otp) = match self {
+            InvariantViolation::Words(otp) => ("words", otp),
+            InvariantViolation::Hex(otp) => ("hex", otp),
+        };
+        write!(f, "{} round trip failed for ", kind)?;
+        write_hex(f, otp, HexStyle::CONTIGUOUS_LOWERCASE)
+    }
+
+}
+
+/// A fixed-capacity sink for formatted hexadecimal.
+struct HexBuffer {
+    buf: [u8; MAX_HEX_STRING_LEN],
+    len: usize,
+}
+
+impl fmt::Write for HexBuffer {
+
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let end = self.len + s.len();
+        if end > self.buf.len() {
+            return Err(fmt::Error);
+        }
+        self.buf[self.len..end].copy_from_slice(s.as_bytes());
+        self.len = end;
+        Ok(())
+    }
+
+}
+
+/// Whether encoding `otp` as words from the standard dictionary and decoding
+/// them produces `otp` again, with a valid checksum.
+pub fn roundtrip_words (otp: &[u8; 8]) -> bool {
+    decode_word_format_with_std_dict(convert_to_word_format(otp)) == Some((*otp, true))
+}
+
+/// Whether encoding `otp` as words from `dictionary` and decoding them
+/// produces `otp` again, with a valid checksum.
+pub fn roundtrip_words_with_dictionary <D: Dictionary + ?Sized> (otp: &[u8; 8], dictionary: &D) -> bool {
+    convert_to_word_format_with_dictionary(otp, dictionary)
+        .and_then(|words| decode_word_format_with_dictionary(words, dictionary))
+        == Some((*otp, true))
+}
+
+/// Whether `s` decodes as hexadecimal, and formatting the decoded value in
+/// every [HexStyle] without a prefix decodes to the same value again.
+pub fn roundtrip_hex (s: &str) -> bool {
+    let Some(otp) = decode_hex_otp(s) else {
+        return false;
+    };
+    roundtrip_hex_otp(&otp)
+}
+
+fn roundtrip_hex_otp (otp: &[u8; 8]) -> bool {
+    let styles = [
+        HexStyle::CANONICAL,
+        HexStyle::CONTIGUOUS_LOWERCASE,
+        HexStyle::CONTIGUOUS_UPPERCASE,
+        HexStyle { grouped: true, uppercase: false, prefix: false },
+    ];
+    styles.iter().all(|style| {
+        let mut buf = HexBuffer { buf: [0; MAX_HEX_STRING_LEN], len: 0 };
+        write_hex(&mut buf, otp, *style).is_ok()
+            && core::str::from_utf8(&buf.buf[..buf.len]).ok().and_then(decode_hex_otp) == Some(*otp)
+    })
+}
+
+/// The values tested by [check_invariants]: a few edge cases, then
+/// [SAMPLE_COUNT] values from a fixed pseudo-random sequence (SplitMix64), so
+/// that failures are reproducible.
+pub fn samples () -> impl Iterator<Item = [u8; 8]> {
+    let edges = [ 0, u64::MAX, 0x5555_5555_5555_5555, 0xAAAA_AAAA_AAAA_AAAA, 1, 1 << 63 ];
+    let mut state: u64 = 0;
+    let random = core::iter::repeat_with(move || {
+        state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    });
+    edges.into_iter().chain(random.take(SAMPLE_COUNT)).map(u64::to_be_bytes)
+}
+
+/// Check that words from `dictionary` and hexadecimal round-trip for every
+/// value in [samples], returning the first violation, if any.
+pub fn check_invariants <D: Dictionary + ?Sized> (dictionary: &D) -> Result<(), InvariantViolation> {
+    for otp in samples() {
+        if !roundtrip_words_with_dictionary(&otp, dictionary) {
+            return Err(InvariantViolation::Words(otp));
+        }
+        if !roundtrip_hex_otp(&otp) {
+            return Err(InvariantViolation::Hex(otp));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::StandardDictionary;
+
+    #[test]
+    fn invariants_hold () {
+        assert_eq!(check_invariants(&StandardDictionary), Ok(()));
+        assert!(samples().all(|otp| roundtrip_words(&otp)));
+        assert!(roundtrip_hex("9E87 6134 D904 99DD"));
+        assert!(!roundtrip_hex("9E87 6134 D904 99D"));
+        let short: &[&str] = &[ "A" ];
+        assert_eq!(check_invariants(short), Err(InvariantViolation::Words([ 0xFF; 8 ])));
+    }
+
+}
@@ -9,6 +9,9 @@
 //! today for multifactor authentication: these are defined in other RFCs. This
 //! algorithm, however, is used in the `OTP` SASL mechanism as described in
 //! [IETF RFC 2444](https://www.rfc-editor.org/rfc/rfc2444.html).
+//!
+//! This library reports failures as errors rather than panicking, so that it can
+//! be used inside long-running daemons, such as in SASL plugins.
 //! 
 //! ## Security
 //!
@@ -25,6 +28,13 @@
 //! passphrase. Still, I **highly** recommend using the `sha1` algorithm
 //! exclusively. It is the newest and most secure of the three.
 //!
+//! This library also supports SHA-256 and SHA-512 through the `sha256` and `sha512`
+//! feature flags. These are **not** standardized: they are named `x-sha256` and
+//! `x-sha512` to make this clear, and their digests are folded to 64 bits by
+//! XORing each 8-byte block together, just like MD4 and MD5. Only use these if you
+//! control both the client and the server. Use `HashAlg::from_name_strict` to only
+//! accept the standardized algorithms.
+//!
 //! If more algorithms are ever made official, you should see the new algorithms
 //! [here](https://www.iana.org/assignments/otp-parameters/otp-parameters.xhtml).
 //!
@@ -36,10 +46,64 @@
 //! - `md5`: MD5 support
 //! - `sha1`: SHA1 support
 //! - `words`: Translation to and from dictionary words
-//! - `dyndig`: Support for any digest that implements `digest::DynDigest`
-//! - `parsing`: Parsing OTP strings
+//! - `dyndig`: Support for any digest that implements `digest::DynDigest`. This
+//!   requires `alloc`.
+//! - `parsing`: Parsing OTP strings; this enables both of the following
+//! - `parsing-challenge`: Parsing challenges, as clients do
+//! - `parsing-response`: Parsing and verifying responses, and formatting
+//!   challenges, as servers do. This includes the `server` module.
+//! - `alloc`: Helpers that return heap-allocated values, such as `String`
 //!
-//! All of the above are enabled by default.
+//! All of the above are enabled by default. With `alloc` and `dyndig` disabled,
+//! this library does not use `alloc` at all. The following are not enabled by
+//! default:
+//!
+//! - `packed-dictionary`: Store the standard dictionary as a compact,
+//!   length-prefixed byte blob instead of a table of `&str`, which saves several
+//!   kilobytes of flash on embedded targets at the cost of slower word lookups
+//! - `sha256`: Non-standard SHA-256 support, under the name `x-sha256`
+//! - `sha512`: Non-standard SHA-512 support, under the name `x-sha512`
+//! - `heapless`: Formatting of words and hex into fixed-capacity `heapless::String`s
+//! - `ct`: Look up dictionary words in constant time, so that the time taken to
+//!   decode words does not leak their position in the dictionary
+//! - `asm`: Use the assembly implementations of MD5, SHA1, and SHA-2, where
+//!   available. This is only supported on some architectures.
+//! - `test-vectors`: The official test vectors from Appendix C of RFC 2289, in the
+//!   `test_vectors` module, for use in your own tests
+//! - `conformance`: A conformance checker, in the `conformance` module, for
+//!   verifying that bindings and services built on this library still calculate
+//!   and decode OTPs correctly. This enables `test-vectors` and `words`.
+//! - `arbitrary`: Implementations of `arbitrary::Arbitrary` for the parsed types,
+//!   which generate structurally valid values, for fuzzing
+//! - `proptest`: `proptest` strategies for valid seeds, passphrases, and more, in
+//!   the `strategies` module
+//! - `nfkc`: Normalize passphrases to Unicode NFKC before hashing them
+//! - `rand`: Generation of seeds and new OTP sequences using any `rand_core::RngCore`
+//! - `getrandom`: `GetrandomRng`, which generates seeds using randomness from the
+//!   operating system
+//! - `wasm`: JavaScript bindings via `wasm-bindgen`, in the `wasm` module
+//! - `capi`: A flat C ABI, in the `capi` module, for linking from C; see
+//!   `cbindgen.toml` for generating a header
+//! - `uniffi`: Kotlin and Swift bindings via UniFFI, in the `mobile` module
+//! - `opiekeys`: Reading and writing entries of OPIE's `/etc/opiekeys` file, in
+//!   the `opiekeys` module
+//! - `cli`: The `otpcalc` command, a work-alike of `opiekey` and `skey`, and the
+//!   `otpinit` command, which creates or re-keys entries in an OPIE key file
+//! - `tracing`: Emit `tracing` events, with the target `rfc2289_otp`, when
+//!   responses are verified and sequences are provisioned, and debug-level spans
+//!   around OTP calculations, recording the algorithm and count. Passphrases and
+//!   OTP values are never recorded.
+//! - `unredacted-debug`: Include OTP values in the `Debug` output of parsed
+//!   responses, which are otherwise redacted unless wrapped using `reveal()`
+//! - `std`: Implementations that use `std`, such as `clock::StdClock`
+//! - `bytes`: Writing challenges and responses into any `bytes::BufMut`
+//! - `binary-state`: A fixed-size binary encoding of the server's `OtpState`,
+//!   for storage such as EEPROM
+//! - `serde`: Implementations of `serde::Serialize` and `serde::Deserialize` for
+//!   `HashAlg` and `sheet::OtpList`, and the challenge and response structures
+//!   for JSON APIs in `dto`
+//! - `http`: Carrying challenges and responses in `WWW-Authenticate` and
+//!   `Authorization` headers
 //!
 //! ## Usage
 //!
@@ -53,26 +117,34 @@
 //!
 //! ```rust
 //! let challenge_str = "otp-md5 487 dog2";
+//! # #[cfg(feature = "parsing-challenge")]
 //! let challenge = rfc2289_otp::parse_otp_challenge(challenge_str).unwrap();
 //! ```
 //!
 //! If it is a valid string, you should get a data structure that looks like this:
 //!
 //! ```rust
+//! # #[cfg(feature = "parsing-challenge")]
 //! pub struct OTPChallenge <'a> {
 //!     pub hash_alg: &'a str,
 //!     pub hash_count: usize,
 //!     pub seed: &'a str,
+//!     pub capabilities: rfc2289_otp::Capabilities<'a>,
 //! }
 //! ```
 //!
+//! Capability words such as `ext` may appear anywhere after the count, and are
+//! collected in `capabilities`. Use `is_extended()` to check for `ext`.
+//!
 //! You can use this data structure to calculate the OTP like so:
 //!
 //! ```rust
+//! # #[cfg(all(feature = "parsing-challenge", feature = "dyndig", feature = "md5"))] {
 //! let challenge = rfc2289_otp::OTPChallenge {
 //!     hash_alg: "md5",
 //!     hash_count: 200,
 //!     seed: "wibby123",
+//!     capabilities: Default::default(),
 //! };
 //! let extremely_secure_passphrase = "banana";
 //! let otp = rfc2289_otp::calculate_otp(
@@ -82,6 +154,7 @@
 //!     challenge.hash_count,
 //!     None,
 //! ).unwrap();
+//! # }
 //! ```
 //!
 //! If the algorithm was understood, and there wasn't any other problem, you should
@@ -98,7 +171,8 @@
 //!
 //! ```rust
 //! let otp_response = "hex:5Bf0 75d9 959d 036f";
-//! let r = rfc2289_otp::parse_otp_response(&otp_response).unwrap();
+//! # #[cfg(feature = "parsing-response")]
+//! let r = rfc2289_otp::parse_otp_response(otp_response).unwrap();
 //! ```
 //!
 //! If the syntax is valid, you should get an `OTPResponse` as shown below:
@@ -117,6 +191,7 @@
 //!     pub new_alg: &'a str,
 //!     pub new_seq_num: usize,
 //!     pub new_seed: &'a str,
+//!     pub extra: Option<&'a str>,
 //! }
 //!
 //! pub enum OTPResponse <'a> {
@@ -134,16 +209,164 @@
 //! let decoded = rfc2289_otp::decode_word_format_with_std_dict(words).unwrap();
 //! ```
 //!
+//! Compare the decoded value to the expected value using `verify_otp_eq`, which
+//! runs in constant time, rather than `==`. Or, use `verify_response`, which does
+//! all of the above in a single call.
+//!
 //! If the client response is one of the `Init` variants, how the server chooses to
 //! handle this is an implementation detail.
 
 #![no_std]
-use cow_utils::CowUtils;
-use md4::{Md4, Digest};
-use hex::FromHex;
+#![cfg_attr(not(test), deny(clippy::unwrap_used, clippy::expect_used, clippy::panic))]
+#[cfg(any(feature = "md4", feature = "md5", feature = "sha1", feature = "sha256", feature = "sha512"))]
+use digest::Digest;
+#[cfg(feature = "md4")]
+use md4::Md4;
+#[cfg(feature = "md5")]
+use md5::Md5;
+#[cfg(feature = "sha1")]
+use sha1::Sha1;
 
+#[cfg(any(feature = "alloc", test))]
 extern crate alloc;
-use alloc::{borrow::ToOwned, boxed::Box};
+// Static and dynamic libraries need the panic handler that `std` provides,
+// and UniFFI and the `std` feature need `std` itself.
+#[cfg(any(feature = "std", feature = "capi", feature = "uniffi"))]
+extern crate std;
+#[cfg(feature = "dyndig")]
+use alloc::boxed::Box;
+#[cfg(feature = "alloc")]
+use alloc::string::String;
+#[cfg(feature = "nfkc")]
+use alloc::borrow::Cow;
+
+/// Emit a `tracing` event with the target `rfc2289_otp`, if the `tracing`
+/// feature is enabled. Only non-sensitive fields may be recorded: never
+/// passphrases or OTP values.
+#[allow(unused_macros)]
+macro_rules! otp_event {
+    ($level:ident, $($arg:tt)+) => {{
+        #[cfg(feature = "tracing")]
+        tracing::$level!(target: "rfc2289_otp", $($arg)+);
+        #[cfg(not(feature = "tracing"))]
+        $crate::otp_trace_fields!($($arg)+);
+    }};
+}
+#[allow(unused_imports)]
+pub(crate) use otp_event;
+
+/// Enter a `tracing` span at the debug level, with the target `rfc2289_otp`,
+/// until the end of the enclosing block, if the `tracing` feature is enabled.
+/// As with `otp_event`, only non-sensitive fields may be recorded.
+#[allow(unused_macros)]
+macro_rules! otp_span {
+    ($name:expr $(, $($field:tt)+)?) => {
+        #[cfg(feature = "tracing")]
+        let _otp_span = tracing::debug_span!(target: "rfc2289_otp", $name $(, $($field)+)?).entered();
+        #[cfg(not(feature = "tracing"))]
+        $crate::otp_trace_fields!($($($field)+)?);
+    };
+}
+#[allow(unused_imports)]
+pub(crate) use otp_span;
+
+/// Refer to the fields of an `otp_event` or `otp_span`, without evaluating
+/// them, when the `tracing` feature is disabled, so that variables that are
+/// only ever traced are still used.
+#[allow(unused_macros)]
+macro_rules! otp_trace_fields {
+    () => {};
+    ($name:ident = % $value:expr $(, $($rest:tt)*)?) => {
+        let _ = || { let _ = &$value; };
+        $crate::otp_trace_fields!($($($rest)*)?);
+    };
+    ($name:ident = ? $value:expr $(, $($rest:tt)*)?) => {
+        let _ = || { let _ = &$value; };
+        $crate::otp_trace_fields!($($($rest)*)?);
+    };
+    ($name:ident = $value:expr $(, $($rest:tt)*)?) => {
+        let _ = || { let _ = &$value; };
+        $crate::otp_trace_fields!($($($rest)*)?);
+    };
+    ($name:ident $(, $($rest:tt)*)?) => {
+        let _ = || { let _ = &$name; };
+        $crate::otp_trace_fields!($($($rest)*)?);
+    };
+    ($message:literal $(, $arg:expr)* $(,)?) => {
+        $(let _ = || { let _ = &$arg; };)*
+    };
+}
+#[allow(unused_imports)]
+pub(crate) use otp_trace_fields;
+
+#[cfg(any(test, feature = "test-vectors"))]
+pub mod test_vectors;
+#[cfg(feature = "conformance")]
+pub mod conformance;
+#[cfg(all(feature = "arbitrary", feature = "parsing-challenge", feature = "parsing-response"))]
+mod fuzzing;
+#[cfg(feature = "proptest")]
+pub mod strategies;
+mod seed;
+pub use seed::*;
+mod error;
+pub use error::*;
+#[cfg(feature = "words")]
+mod dictionary;
+#[cfg(feature = "words")]
+pub use dictionary::*;
+#[cfg(feature = "words")]
+pub mod invariants;
+mod writer;
+pub use writer::*;
+mod profile;
+pub use profile::*;
+mod provisioning;
+pub use provisioning::*;
+#[cfg(any(feature = "wasm", feature = "uniffi"))]
+mod bindings;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+#[cfg(feature = "capi")]
+pub mod capi;
+#[cfg(feature = "uniffi")]
+pub mod mobile;
+#[cfg(feature = "opiekeys")]
+pub mod opiekeys;
+#[cfg(all(feature = "alloc", feature = "words"))]
+pub mod sheet;
+pub mod clock;
+#[cfg(all(feature = "parsing-response", feature = "words"))]
+pub mod server;
+/// Parsing of OTP challenges and responses, and the pieces of their grammars.
+#[cfg(any(feature = "parsing-challenge", feature = "parsing-response"))]
+pub mod parse {
+    pub mod combinators;
+}
+#[cfg(all(feature = "serde", feature = "alloc", feature = "parsing-challenge", feature = "parsing-response"))]
+pub mod dto;
+#[cfg(feature = "http")]
+pub mod http;
+mod redact;
+pub use redact::*;
+#[cfg(feature = "parsing-challenge")]
+mod uri;
+#[cfg(feature = "parsing-challenge")]
+pub use uri::*;
+#[cfg(all(feature = "parsing-response", feature = "alloc"))]
+mod owned;
+#[cfg(all(feature = "parsing-response", feature = "alloc"))]
+pub use owned::*;
+#[cfg(feature = "parsing-response")]
+mod stream;
+#[cfg(feature = "parsing-response")]
+pub use stream::*;
+#[cfg(all(feature = "parsing-challenge", feature = "parsing-response"))]
+mod classify;
+#[cfg(all(feature = "parsing-challenge", feature = "parsing-response"))]
+pub use classify::*;
+#[cfg(feature = "uniffi")]
+uniffi::setup_scaffolding!();
 
 /// Defined in [IETF RFC 1760](https://www.rfc-editor.org/rfc/rfc1760) for use
 /// in S/KEY, but used OTP in
@@ -153,7 +376,8 @@ use alloc::{borrow::ToOwned, boxed::Box};
 /// 
 /// Used in [convert_to_word_format] and [decode_word_format_with_std_dict].
 #[cfg(feature = "words")]
-pub const STANDARD_DICTIONARY: [&'static str; 2048] = [
+#[allow(clippy::large_const_arrays)]
+pub const STANDARD_DICTIONARY: [&str; 2048] = [
     "A",     "ABE",   "ACE",   "ACT",   "AD",    "ADA",   "ADD",
     "AGO",   "AID",   "AIM",   "AIR",   "ALL",   "ALP",   "AM",    "AMY",
     "AN",    "ANA",   "AND",   "ANN",   "ANT",   "ANY",   "APE",   "APS",
@@ -413,11 +637,200 @@ pub const STANDARD_DICTIONARY: [&'static str; 2048] = [
     "YOKE",
 ];
 
+/// The length, in bytes, of [STANDARD_DICTIONARY] when packed into
+/// length-prefixed words.
+#[cfg(feature = "packed-dictionary")]
+const PACKED_DICTIONARY_LEN: usize = {
+    let mut len = 0;
+    let mut i = 0;
+    while i < STANDARD_DICTIONARY.len() {
+        len += 1 + STANDARD_DICTIONARY[i].len();
+        i += 1;
+    }
+    len
+};
+
+/// [STANDARD_DICTIONARY] packed into a single blob of words, each preceded by
+/// a single byte giving its length. This is generated at compile time, and
+/// takes up a fraction of the space of the `&'static str` table, which is
+/// never emitted into the binary when the `packed-dictionary` feature is
+/// enabled.
+#[cfg(feature = "packed-dictionary")]
+static PACKED_STANDARD_DICTIONARY: [u8; PACKED_DICTIONARY_LEN] = {
+    let mut packed = [0u8; PACKED_DICTIONARY_LEN];
+    let mut offset = 0;
+    let mut i = 0;
+    while i < STANDARD_DICTIONARY.len() {
+        let word = STANDARD_DICTIONARY[i].as_bytes();
+        packed[offset] = word.len() as u8;
+        offset += 1;
+        let mut j = 0;
+        while j < word.len() {
+            packed[offset] = word[j];
+            offset += 1;
+            j += 1;
+        }
+        i += 1;
+    }
+    packed
+};
+
+/// Iterate over the words of the packed standard dictionary, in order.
+#[cfg(feature = "packed-dictionary")]
+fn packed_dictionary_words () -> impl Iterator<Item = &'static str> {
+    let mut rest: &'static [u8] = &PACKED_STANDARD_DICTIONARY;
+    core::iter::from_fn(move || {
+        let (len, tail) = rest.split_first()?;
+        let (word, tail) = tail.split_at(*len as usize);
+        rest = tail;
+        core::str::from_utf8(word).ok()
+    })
+}
+
+/// Get the word at `index` in the standard dictionary.
+#[cfg(all(feature = "words", not(feature = "packed-dictionary")))]
+fn std_dict_word (index: usize) -> &'static str {
+    STANDARD_DICTIONARY[index]
+}
+
+/// Get the word at `index` in the standard dictionary.
+#[cfg(feature = "packed-dictionary")]
+fn std_dict_word (index: usize) -> &'static str {
+    packed_dictionary_words().nth(index).unwrap_or("")
+}
+
+/// Get the index of `word` in the standard dictionary, ignoring case.
+#[cfg(all(feature = "words", not(feature = "packed-dictionary"), feature = "ct"))]
+fn std_dict_index (word: &str) -> Option<usize> {
+    let mut buf = [0u8; 4];
+    let word = uppercase_short_word(word, &mut buf)?;
+    word_position(STANDARD_DICTIONARY.iter().copied(), word)
+}
+
+/// Get the index of `word` in the standard dictionary, ignoring case, by
+/// binary search of [STANDARD_DICTIONARY_KEYS].
+#[cfg(all(feature = "words", not(feature = "packed-dictionary"), not(feature = "ct")))]
+fn std_dict_index (word: &str) -> Option<usize> {
+    let mut buf = [0u8; 4];
+    let word = uppercase_short_word(word, &mut buf)?;
+    let key = word_key(word.as_bytes());
+    if word.len() < 4 {
+        STANDARD_DICTIONARY_KEYS[..SHORT_WORD_COUNT].binary_search(&key).ok()
+    } else {
+        STANDARD_DICTIONARY_KEYS[SHORT_WORD_COUNT..].binary_search(&key).ok().map(|i| i + SHORT_WORD_COUNT)
+    }
+}
+
+/// Pack a word of up to four bytes into an integer, big-endian and padded with
+/// zeroes, so that keys compare in the same order as the words.
+#[cfg(all(feature = "words", not(feature = "packed-dictionary"), not(feature = "ct")))]
+const fn word_key (word: &[u8]) -> u32 {
+    let mut key: u32 = 0;
+    let mut i = 0;
+    while i < 4 {
+        key <<= 8;
+        if i < word.len() {
+            key |= word[i] as u32;
+        }
+        i += 1;
+    }
+    key
+}
+
+/// The number of words in [STANDARD_DICTIONARY] with fewer than four letters,
+/// which all precede the four-letter words.
+#[cfg(all(feature = "words", not(feature = "packed-dictionary"), not(feature = "ct")))]
+const SHORT_WORD_COUNT: usize = {
+    let mut i = 0;
+    while STANDARD_DICTIONARY[i].len() < 4 {
+        i += 1;
+    }
+    i
+};
+
+/// The [word_key] of every word in [STANDARD_DICTIONARY], generated at compile
+/// time. The short words and the four-letter words are each in alphabetical
+/// order, which is checked at compile time, so each half can be searched as a
+/// sorted table of integers.
+#[cfg(all(feature = "words", not(feature = "packed-dictionary"), not(feature = "ct")))]
+static STANDARD_DICTIONARY_KEYS: [u32; 2048] = {
+    let mut keys = [0u32; 2048];
+    let mut i = 0;
+    while i < STANDARD_DICTIONARY.len() {
+        keys[i] = word_key(STANDARD_DICTIONARY[i].as_bytes());
+        assert!(i == 0 || i == SHORT_WORD_COUNT || keys[i - 1] < keys[i], "dictionary is not sorted");
+        assert!(STANDARD_DICTIONARY[i].len() <= 4);
+        i += 1;
+    }
+    keys
+};
+
+/// Get the index of `word` in the standard dictionary, ignoring case.
+#[cfg(feature = "packed-dictionary")]
+fn std_dict_index (word: &str) -> Option<usize> {
+    let mut buf = [0u8; 4];
+    let word = uppercase_short_word(word, &mut buf)?;
+    word_position(packed_dictionary_words(), word)
+}
+
+/// Copy `word` into `buf` in uppercase, without allocating. Returns `None` if
+/// `word` is longer than four bytes, since no word in the standard dictionary
+/// is.
+#[cfg(feature = "words")]
+fn uppercase_short_word <'a> (word: &str, buf: &'a mut [u8; 4]) -> Option<&'a str> {
+    let buf = buf.get_mut(..word.len())?;
+    buf.copy_from_slice(word.as_bytes());
+    buf.make_ascii_uppercase();
+    core::str::from_utf8(buf).ok()
+}
+
+/// Get the index of `word` within `words`.
+#[cfg(all(feature = "packed-dictionary", not(feature = "ct")))]
+fn word_position <'a> (mut words: impl Iterator<Item = &'a str>, word: &str) -> Option<usize> {
+    words.position(|w| w == word)
+}
+
+/// Get the index of `word` within `words`, in time that does not depend on
+/// where (or whether) `word` appears in `words`: every word is examined, and
+/// the comparisons do not branch on the result.
+///
+/// This only supports words of up to four bytes, which is all that appear in
+/// the standard dictionary.
+#[cfg(feature = "ct")]
+fn word_position <'a> (words: impl Iterator<Item = &'a str>, word: &str) -> Option<usize> {
+    let key = ct_word_key(word.as_bytes());
+    let mut found: u64 = 0;
+    let mut index: u64 = 0;
+    for (i, w) in words.enumerate() {
+        let diff = key ^ ct_word_key(w.as_bytes());
+        // 1 if diff is zero, 0 otherwise.
+        let is_match = ((diff | diff.wrapping_neg()) >> 63) ^ 1;
+        found |= is_match;
+        index |= (i as u64) & is_match.wrapping_neg();
+    }
+    if core::hint::black_box(found) == 1 {
+        Some(index as usize)
+    } else {
+        None
+    }
+}
+
+/// Pack a word of up to four bytes and its length into a single integer, such
+/// that two words are equal if and only if their keys are equal. Words longer
+/// than four bytes produce keys that cannot match any shorter word.
+#[cfg(feature = "ct")]
+fn ct_word_key (word: &[u8]) -> u64 {
+    let mut key: u64 = word.len().min(0xFF) as u64;
+    for j in 0..4 {
+        key = (key << 8) | word.get(j).copied().unwrap_or(0) as u64;
+    }
+    key
+}
+
 
 /// Folds an arbitrary-length input (greater than 8 bytes) to 8 bytes according
 /// to the algorithm in Appendix A of
 /// [IETF RFC 2289](https://www.rfc-editor.org/rfc/rfc2289.html).
-#[cfg(any(feature = "md4", feature = "md5"))]
 pub fn fold_md (input: &mut [u8]) {
     let mut j = 0;
     for i in 8..input.len() {
@@ -433,32 +846,123 @@ pub fn fold_md (input: &mut [u8]) {
 /// digest into the `digest` field, which is defined as an array of five
 /// `uint32`s. Since the Rust SHA1 library outputs the digest into a 20-byte
 /// array instead, this implementation differs slightly in this regard.
-#[cfg(feature = "sha1")]
 pub fn fold_sha1 (digest: &mut [u8; 20]) {
-    fold_md(&mut digest[..]);
-    digest.swap(0, 3);
-    digest.swap(1, 2);
-    digest.swap(4, 7);
-    digest.swap(5, 6);
+    fold_to_64_bits(HashAlg::Sha1, digest);
+}
+
+/// Folds a digest produced by the hash algorithm `alg` to 64 bits, which are
+/// left in the first 8 bytes of `digest`, per Appendix A of
+/// [IETF RFC 2289](https://www.rfc-editor.org/rfc/rfc2289.html).
+///
+/// For all algorithms, every 8-byte block of the digest is XORed together, per
+/// [fold_md]. For [HashAlg::Sha1] only, the bytes of each resulting 32-bit
+/// word are then reversed, per [fold_sha1], because the reference
+/// implementation folds the digest as an array of little-endian `uint32`s.
+/// The non-standard algorithms defined by this library are folded exactly as
+/// MD4 and MD5 are.
+pub fn fold_to_64_bits (alg: HashAlg, digest: &mut [u8]) {
+    fold_md(digest);
+    if alg == HashAlg::Sha1 && digest.len() >= 8 {
+        digest.swap(0, 3);
+        digest.swap(1, 2);
+        digest.swap(4, 7);
+        digest.swap(5, 6);
+    }
 }
 
-const INIT_SIX_WORDS: [&'static str; 6] = [ "A", "A", "A", "A", "A", "A" ];
+#[cfg(feature = "words")]
+const INIT_SIX_WORDS: [&str; 6] = [ "A", "A", "A", "A", "A", "A" ];
+
+/// Compare two 64-bit OTP values in constant time, returning `true` if they
+/// are equal.
+///
+/// Servers should use this, rather than `==`, to compare the OTP value
+/// supplied by a client against the expected value, so that the time taken to
+/// compare does not leak how many leading bytes matched.
+pub fn verify_otp_eq (a: &[u8; 8], b: &[u8; 8]) -> bool {
+    let mut diff: u8 = 0;
+    for i in 0..8 {
+        diff |= a[i] ^ b[i];
+    }
+    core::hint::black_box(diff) == 0
+}
 
 /// Calculate the checksum, per section 6.0 of
 /// [IETF RFC 2289](https://www.rfc-editor.org/rfc/rfc2289.html).
-pub fn calculate_checksum (input: &[u8; 8]) -> u64 {
-    input
-        .iter()
-        .map(|num| -> u64 {
-            let n = *num;
-            ((n & 0b0000_0011)
-            + ((n & 0b0000_1100) >> 2)
-            + ((n & 0b0011_0000) >> 4)
-            + ((n & 0b1100_0000) >> 6)).into()
-        })
-        .reduce(|acc, curr| acc + curr)
-        .unwrap()
-        & 0b11
+///
+/// See [checksum_2bit].
+pub const fn calculate_checksum (input: &[u8; 8]) -> u64 {
+    checksum_2bit(input) as u64
+}
+
+/// Calculate the two-bit checksum of a 64-bit value, per section 6.0 of
+/// [IETF RFC 2289](https://www.rfc-editor.org/rfc/rfc2289.html): the sum of
+/// its 32 pairs of bits, modulo four.
+pub const fn checksum_2bit (input: &[u8; 8]) -> u8 {
+    checksum_2bit_slice(input)
+}
+
+/// Calculate the two-bit checksum of [checksum_2bit] over any number of bytes:
+/// the sum of their pairs of bits, modulo four. This is for custom encodings
+/// and diagnostics; OTP values are always eight bytes.
+///
+/// ```rust
+/// use rfc2289_otp::{checksum_2bit, checksum_2bit_slice};
+/// let otp = [ 0x9E, 0x87, 0x61, 0x34, 0xD9, 0x04, 0x99, 0xDD ];
+/// assert_eq!(checksum_2bit_slice(&otp), checksum_2bit(&otp));
+/// assert_eq!(checksum_2bit_slice(&[ 0b0101 ]), 2);
+/// ```
+pub const fn checksum_2bit_slice (input: &[u8]) -> u8 {
+    let mut sum: u8 = 0;
+    let mut i = 0;
+    while i < input.len() {
+        let n = input[i];
+        sum = sum
+            .wrapping_add(n & 0b0000_0011)
+            .wrapping_add((n & 0b0000_1100) >> 2)
+            .wrapping_add((n & 0b0011_0000) >> 4)
+            .wrapping_add((n & 0b1100_0000) >> 6);
+        i += 1;
+    }
+    sum & 0b11
+}
+
+/// Set the checksum in the 11-bit dictionary index of the last of six words.
+/// The last word carries the final nine bits of the 64-bit value in its high
+/// bits, and the two-bit checksum, per [checksum_2bit], in its low two bits.
+pub const fn apply_checksum_to_last_word (index: u16, checksum: u8) -> u16 {
+    (index & 0b111_1111_1100) | (checksum & 0b11) as u16
+}
+
+/// Get the checksum from the 11-bit dictionary index of the last of six
+/// words. The inverse of [apply_checksum_to_last_word].
+pub const fn extract_checksum (last_index: u16) -> u8 {
+    (last_index & 0b11) as u8
+}
+
+/// Get the 11-bit index of `word` in the standard dictionary defined in
+/// [IETF RFC 1760](https://www.rfc-editor.org/rfc/rfc1760), ignoring case.
+///
+/// Returns `None` if the word does not appear in the standard dictionary.
+///
+/// The inverse of [index_to_word].
+#[cfg(feature = "words")]
+pub fn word_index (word: &str) -> Option<u16> {
+    std_dict_index(word).map(|i| i as u16)
+}
+
+/// Get the word at the 11-bit `index` in the standard dictionary defined in
+/// [IETF RFC 1760](https://www.rfc-editor.org/rfc/rfc1760).
+///
+/// Returns `None` if `index` does not fit in 11 bits.
+///
+/// The inverse of [word_index].
+#[cfg(feature = "words")]
+pub fn index_to_word (index: u16) -> Option<&'static str> {
+    if index as usize >= STANDARD_DICTIONARY.len() {
+        return None;
+    }
+    Some(std_dict_word(index as usize))
 }
 
 /// Encode a 64-bit value using the standard dictionary words defined in
@@ -469,289 +973,1519 @@ pub fn calculate_checksum (input: &[u8; 8]) -> u64 {
 /// The inverse of [decode_word_format_with_std_dict].
 #[cfg(feature = "words")]
 pub fn convert_to_word_format (result: &[u8; 8]) -> [&'static str; 6] {
-    let checksum: u64 = calculate_checksum(result);
-    let mut result = u64::from_be_bytes(*result);
     let mut output: [&'static str; 6] = INIT_SIX_WORDS;
-    for i in 0..5 {
-        let bits = (result & (0b11111111111 << (64 - 11))) >> (64 - 11); // 11 bits
-        output[i] = STANDARD_DICTIONARY[bits as usize];
-        result = result.wrapping_shl(11);
+    for (word, index) in output.iter_mut().zip(otp_to_word_indices(result)) {
+        *word = std_dict_word(index as usize);
     }
-    let bits: u64 = ((result & (0b11111111111 << (64 - 11))) >> (64 - 11)) + checksum; // 11 bits
-    output[5] = STANDARD_DICTIONARY[bits as usize];
     output
 }
 
-/// Decode a 64-bit value using the standard dictionary words defined in
-/// [IETF RFC 1760](https://www.rfc-editor.org/rfc/rfc1760) for use in S/KEY,
-/// and used OTP in
-/// [IETF RFC 2289](https://www.rfc-editor.org/rfc/rfc2289.html).
+/// Split a 64-bit value into the 11-bit dictionary indices of its six words,
+/// with the checksum in the low two bits of the last. This is a `const fn`,
+/// so it can be used to generate fixtures at compile time:
 ///
-/// Returns `None` if a word does not appear in the standard dictionary.
-/// Otherwise, returns the decoded bytes and a `bool` indicating whether the
-/// checksum was valid, respectively.
+/// ```rust
+/// use rfc2289_otp::{otp_to_word_indices, STANDARD_DICTIONARY};
+/// const INDICES: [u16; 6] = otp_to_word_indices(&[ 0x9E, 0x87, 0x61, 0x34, 0xD9, 0x04, 0x99, 0xDD ]);
+/// const FIRST_WORD: &str = STANDARD_DICTIONARY[INDICES[0] as usize];
+/// assert_eq!(FIRST_WORD, "INCH");
+/// ```
 ///
-/// The inverse of [convert_to_word_format].
-#[cfg(feature = "words")]
-pub fn decode_word_format_with_std_dict (words: [&str; 6]) -> Option<([u8; 8], bool)> {
+/// The inverse of [word_indices_to_otp].
+pub const fn otp_to_word_indices (otp: &[u8; 8]) -> [u16; 6] {
+    let checksum = checksum_2bit(otp);
+    let mut result = u64::from_be_bytes(*otp);
+    let mut output = [0u16; 6];
+    let mut i = 0;
+    while i < output.len() {
+        output[i] = ((result & (0b11111111111 << (64 - 11))) >> (64 - 11)) as u16; // 11 bits
+        result = result.wrapping_shl(11);
+        i += 1;
+    }
+    output[5] = apply_checksum_to_last_word(output[5], checksum);
+    output
+}
+
+/// Combine the 11-bit dictionary indices of six words into a 64-bit value,
+/// returning it and a `bool` indicating whether the checksum in the last index
+/// was valid, respectively. Bits above the lowest eleven of each index are
+/// ignored.
+///
+/// The inverse of [otp_to_word_indices].
+pub const fn word_indices_to_otp (indices: &[u16; 6]) -> ([u8; 8], bool) {
     let mut output: u64 = 0;
-    for word in words.iter().take(5) {
-        let bits = STANDARD_DICTIONARY.iter().position(|w| *w == *word)?;
-        output <<= 11;
-        output |= bits as u64;
-    }
-    // The last word has special treatment: it's two final bits are a checksum.
-    let bits = STANDARD_DICTIONARY.iter().position(|w| *w == words[5])?;
-    output <<= 9;
-    output |= bits as u64 / 4; // mod by 2^9 just to make sure we don't add checksum bits
-    let checksum_bits = bits as u64 % 4;
+    let mut i = 0;
+    while i < 5 {
+        output = (output << 11) | (indices[i] & 0x7FF) as u64;
+        i += 1;
+    }
+    // The last word carries nine bits of the value, then the checksum.
+    let last = indices[5] & 0x7FF;
+    output = (output << 9) | (last >> 2) as u64;
     let output = output.to_be_bytes();
-    let checksum: u64 = calculate_checksum(&output);
-    Some((output, checksum_bits == checksum))
+    (output, extract_checksum(last) == checksum_2bit(&output))
 }
 
-// TODO: Move to documentation
+/// Encode a 64-bit value as a single string of six space-separated words from
+/// the standard dictionary, such as `"AURA ALOE HURL WING BERG WAIT"`. If
+/// `with_prefix` is `true`, the string is prefixed with `word:`, making it a
+/// valid OTP response per Section 3 of
+/// [IETF RFC 2243](https://www.rfc-editor.org/rfc/rfc2243).
+///
+/// See [convert_to_word_format].
+#[cfg(all(feature = "words", feature = "alloc"))]
+pub fn otp_to_word_string (otp: &[u8; 8], with_prefix: bool) -> String {
+    let mut s = String::with_capacity(MAX_WORD_STRING_LEN);
+    // Writing to a String cannot fail.
+    let _ = write_words(&mut s, otp, with_prefix);
+    s
+}
 
+/// Write a 64-bit value as six space-separated words from the standard
+/// dictionary to any [core::fmt::Write] sink, such as a `heapless::String`.
+/// If `with_prefix` is `true`, the words are prefixed with `word:`. This does
+/// not allocate.
+///
+/// See [otp_to_word_string].
+#[cfg(feature = "words")]
+pub fn write_words <W: core::fmt::Write> (w: &mut W, otp: &[u8; 8], with_prefix: bool) -> core::fmt::Result {
+    let style = if with_prefix { WordStyle::UPPERCASE.with_prefix() } else { WordStyle::UPPERCASE };
+    write_words_with_style(w, otp, style)
+}
 
-/// A parsed OTP challenge string per Section 2.1 of
-/// [IETF RFC 2243](https://www.rfc-editor.org/rfc/rfc2243).
-#[cfg(feature = "parsing")]
-#[derive(Debug)]
-pub struct OTPChallenge <'a> {
-    pub hash_alg: &'a str,
-    pub hash_count: usize,
-    pub seed: &'a str,
+/// How six dictionary words should be formatted.
+///
+/// The [Default] style is uppercase, as the words appear in the standard
+/// dictionary. Since words are decoded case-insensitively, as by
+/// [decode_word_str], either case can be read back.
+#[cfg(feature = "words")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct WordStyle {
+    /// Use uppercase letters.
+    pub uppercase: bool,
+    /// Prepend `word:`, making the output a valid OTP response per Section 3
+    /// of [IETF RFC 2243](https://www.rfc-editor.org/rfc/rfc2243).
+    pub prefix: bool,
 }
 
-/// A parsed OTP init string per Section 4.1 of
-/// [IETF RFC 2243](https://www.rfc-editor.org/rfc/rfc2243).
-#[cfg(feature = "parsing")]
-pub fn parse_otp_challenge <'a> (s: &'a str) -> Option<OTPChallenge<'a>> {
-    if s.len() < 9 { // This is the smallest that an OTP challenge can be.
-        return None;
+#[cfg(feature = "words")]
+impl WordStyle {
+
+    /// Uppercase words, like `INCH SEA ANNE LONG AHEM TOUR`.
+    pub const UPPERCASE: WordStyle = WordStyle { uppercase: true, prefix: false };
+
+    /// Lowercase words, like `inch sea anne long ahem tour`, for terminals
+    /// and label printers that prefer them.
+    pub const LOWERCASE: WordStyle = WordStyle { uppercase: false, prefix: false };
+
+    /// The same style, but with `word:` prepended.
+    pub const fn with_prefix (self) -> WordStyle {
+        WordStyle { prefix: true, ..self }
     }
-    if s.len() > 128 {
-        return None; // To prevent denial of service via outrageous values.
+
+}
+
+#[cfg(feature = "words")]
+impl Default for WordStyle {
+
+    fn default() -> Self {
+        WordStyle::UPPERCASE
     }
-    if !s.starts_with("otp-") {
-        return None;
+
+}
+
+/// Write a 64-bit value as six space-separated words from the standard
+/// dictionary in the given style to any [core::fmt::Write] sink. This does not
+/// allocate.
+///
+/// See [format_words].
+#[cfg(feature = "words")]
+pub fn write_words_with_style <W: core::fmt::Write> (w: &mut W, otp: &[u8; 8], style: WordStyle) -> core::fmt::Result {
+    if style.prefix {
+        w.write_str("word:")?;
     }
-    let x = &s[4..];
-    let mut hash_alg: Option<&'a str> = None;
-    let mut seed: Option<&'a str> = None;
-    let mut count: Option<usize> = None;
-    for token in x.split_ascii_whitespace() {
-        if hash_alg.is_none() {
-            hash_alg = Some(token);
+    for (i, word) in convert_to_word_format(otp).iter().enumerate() {
+        if i > 0 {
+            w.write_char(' ')?;
         }
-        else if count.is_none() {
-            count = Some(usize::from_str_radix(token, 10).ok()?);
-        }
-        else if seed.is_none() {
-            seed = Some(token);
-            break;
+        if style.uppercase {
+            w.write_str(word)?;
+        } else {
+            for c in word.chars() {
+                w.write_char(c.to_ascii_lowercase())?;
+            }
         }
     }
-    Some(OTPChallenge{
-        hash_alg: hash_alg?,
-        seed: seed?,
-        hash_count: count?,
-    })
+    Ok(())
 }
 
-pub type Hex64Bit = [u8; 8];
-
-/// A Hex value or dictionary words
-#[cfg(feature = "parsing")]
-#[derive(Debug, PartialEq, Eq)]
-pub enum HexOrWords <'a> {
-    Hex(Hex64Bit),
-    Words(&'a str),
+/// Format a 64-bit value as six space-separated words from the standard
+/// dictionary in the given style.
+///
+/// ```rust
+/// use rfc2289_otp::{format_words, WordStyle};
+/// let otp = [ 0x9E, 0x87, 0x61, 0x34, 0xD9, 0x04, 0x99, 0xDD ];
+/// assert_eq!(format_words(&otp, WordStyle::LOWERCASE), "inch sea anne long ahem tour");
+/// assert_eq!(format_words(&otp, WordStyle::UPPERCASE.with_prefix()), "word:INCH SEA ANNE LONG AHEM TOUR");
+/// ```
+#[cfg(all(feature = "words", feature = "alloc"))]
+pub fn format_words (otp: &[u8; 8], style: WordStyle) -> String {
+    let mut s = String::with_capacity(MAX_WORD_STRING_LEN);
+    // Writing to a String cannot fail.
+    let _ = write_words_with_style(&mut s, otp, style);
+    s
 }
 
-impl HexOrWords<'_> {
+/// The NATO phonetic alphabet, as standardized by ICAO, from `Alfa` to `Zulu`.
+const NATO_ALPHABET: [&str; 26] = [
+    "Alfa", "Bravo", "Charlie", "Delta", "Echo", "Foxtrot", "Golf", "Hotel",
+    "India", "Juliett", "Kilo", "Lima", "Mike", "November", "Oscar", "Papa",
+    "Quebec", "Romeo", "Sierra", "Tango", "Uniform", "Victor", "Whiskey",
+    "X-ray", "Yankee", "Zulu",
+];
 
-    pub fn try_into_bytes (&self) -> Option<[u8; 8]> {
-        match self {
-            HexOrWords::Hex(h) => Some(h.to_owned()),
-            HexOrWords::Words(w) => {
-                let mut w = w.split_ascii_whitespace();
-                let six_words = [ w.next(), w.next(), w.next(), w.next(), w.next(), w.next() ];
-                if six_words[5].is_none() {
-                    return None;
-                }
-                if w.next().is_some() {
-                    return None;
-                }
-                let six_words = [
-                    six_words[0].unwrap(),
-                    six_words[1].unwrap(),
-                    six_words[2].unwrap(),
-                    six_words[3].unwrap(),
-                    six_words[4].unwrap(),
-                    six_words[5].unwrap(),
-                ];
-                let maybe_64bits = decode_word_format_with_std_dict(six_words);
-                if maybe_64bits.is_none() {
-                    return None;
-                }
-                let (v, valid_checksum) = maybe_64bits.unwrap();
-                if !valid_checksum {
-                    return None;
-                }
-                Some(v)
-            },
+/// The NATO phonetic code word for an ASCII letter, in either case, such as
+/// `Bravo` for `b`, or `None` if `c` is not an ASCII letter.
+pub fn nato_phonetic (c: char) -> Option<&'static str> {
+    if !c.is_ascii_alphabetic() {
+        return None;
+    }
+    NATO_ALPHABET.get((c.to_ascii_uppercase() as u8 - b'A') as usize).copied()
+}
+
+/// Write a 64-bit value as six words from the standard dictionary, each
+/// followed by the NATO phonetic code words for its letters, such as
+/// `BERG (Bravo Echo Romeo Golf)`, for reading OTPs aloud over the phone.
+/// This does not allocate.
+///
+/// ```rust
+/// # #[cfg(feature = "alloc")] {
+/// use rfc2289_otp::format_words_with_phonetics;
+/// let otp = [ 0x9E, 0x87, 0x61, 0x34, 0xD9, 0x04, 0x99, 0xDD ];
+/// assert!(format_words_with_phonetics(&otp).starts_with("INCH (India November Charlie Hotel) SEA (Sierra Echo Alfa)"));
+/// # }
+/// ```
+#[cfg(feature = "words")]
+pub fn write_words_with_phonetics <W: core::fmt::Write> (w: &mut W, otp: &[u8; 8]) -> core::fmt::Result {
+    for (i, word) in convert_to_word_format(otp).iter().enumerate() {
+        if i > 0 {
+            w.write_char(' ')?;
+        }
+        w.write_str(word)?;
+        w.write_str(" (")?;
+        for (j, c) in word.chars().enumerate() {
+            if j > 0 {
+                w.write_char(' ')?;
+            }
+            match nato_phonetic(c) {
+                Some(code) => w.write_str(code)?,
+                None => w.write_char(c)?,
+            }
         }
+        w.write_char(')')?;
     }
+    Ok(())
+}
 
+/// Format a 64-bit value as six words, each followed by its NATO phonetic
+/// spelling.
+///
+/// See [write_words_with_phonetics].
+#[cfg(all(feature = "words", feature = "alloc"))]
+pub fn format_words_with_phonetics (otp: &[u8; 8]) -> String {
+    let mut s = String::new();
+    // Writing to a String cannot fail.
+    let _ = write_words_with_phonetics(&mut s, otp);
+    s
 }
 
-/// A parsed OTP init string per Section 4.1 of
-/// [IETF RFC 2243](https://www.rfc-editor.org/rfc/rfc2243).
-#[cfg(feature = "parsing")]
-#[derive(Debug)]
-pub struct OTPInit <'a> {
-    pub current_otp: HexOrWords<'a>,
-    pub new_otp: HexOrWords<'a>,
-    pub new_alg: &'a str,
-    pub new_seq_num: usize,
-    pub new_seed: &'a str,
+/// The maximum length of six words from the standard dictionary, separated by
+/// spaces and prefixed with `word:`, as produced by [write_words].
+pub const MAX_WORD_STRING_LEN: usize = 34;
+
+/// The maximum length of a 64-bit value in hexadecimal, grouped and prefixed
+/// with `hex:`, as produced by [write_hex].
+pub const MAX_HEX_STRING_LEN: usize = 23;
+
+/// Format a 64-bit value as six space-separated words from the standard
+/// dictionary into a fixed-capacity `heapless::String`, without allocating.
+///
+/// See [write_words].
+#[cfg(all(feature = "words", feature = "heapless"))]
+pub fn otp_to_word_heapless_string (otp: &[u8; 8], with_prefix: bool) -> heapless::String<MAX_WORD_STRING_LEN> {
+    let mut s = heapless::String::new();
+    // The capacity accommodates the longest possible output.
+    let _ = write_words(&mut s, otp, with_prefix);
+    s
 }
 
-/// A parsed OTP response per Sections 3 and 4 of
-/// [IETF RFC 2243](https://www.rfc-editor.org/rfc/rfc2243).
-#[cfg(feature = "parsing")]
-#[derive(Debug)]
-pub enum OTPResponse <'a> {
-    Init(OTPInit <'a>),
-    Current(HexOrWords<'a>)
+/// Format a 64-bit value as hexadecimal into a fixed-capacity
+/// `heapless::String`, without allocating.
+///
+/// See [write_hex].
+#[cfg(feature = "heapless")]
+pub fn format_hex_heapless (otp: &[u8; 8], style: HexStyle) -> heapless::String<MAX_HEX_STRING_LEN> {
+    let mut s = heapless::String::new();
+    // The capacity accommodates the longest possible output.
+    let _ = write_hex(&mut s, otp, style);
+    s
 }
 
-/// Parse OTP `init-hex-response` per Section 4.1 of
-/// [IETF RFC 2243](https://www.rfc-editor.org/rfc/rfc2243).
-#[cfg(feature = "parsing")]
-fn parse_otp_init_hex <'a> (s: &'a str) -> Option<OTPInit<'a>> {
-    let mut sections = s.split(":");
-    let current_otp = sections.next()?.cow_replace(" ", "");
-    let new_params = sections.next()?;
-    let new_otp = sections.next()?.cow_replace(" ", "");
-    if sections.next().is_some() {
-        return None;
-    }
-    let current_otp = <Hex64Bit>::from_hex(current_otp.cow_replace("\t", "").as_ref()).ok()?;
-    let new_otp = <Hex64Bit>::from_hex(new_otp.cow_replace("\t", "").as_ref()).ok()?;
-    let mut params = new_params.split(" ");
-    let algorithm = params.next()?;
-    let sequence_number = params.next()?;
-    let seed = params.next()?;
-    let sequence_number = <usize>::from_str_radix(sequence_number, 10).ok()?;
-    Some(OTPInit {
-        current_otp: HexOrWords::Hex(current_otp),
-        new_otp: HexOrWords::Hex(new_otp),
-        new_alg: algorithm,
-        new_seq_num: sequence_number,
-        new_seed: seed,
-    })
+/// How a 64-bit OTP value should be formatted as hexadecimal.
+///
+/// The [Default] style is the canonical one used in the examples of
+/// [IETF RFC 2289](https://www.rfc-editor.org/rfc/rfc2289.html): four groups of
+/// four uppercase hexadecimal digits, like `9E87 6134 D904 99DD`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct HexStyle {
+    /// Separate the digits into four groups of four with spaces.
+    pub grouped: bool,
+    /// Use uppercase hexadecimal digits.
+    pub uppercase: bool,
+    /// Prepend `hex:`, making the output a valid OTP response per Section 3
+    /// of [IETF RFC 2243](https://www.rfc-editor.org/rfc/rfc2243).
+    pub prefix: bool,
 }
 
-/// Parse OTP `init-word-response` per Section 4.1 of
-/// [IETF RFC 2243](https://www.rfc-editor.org/rfc/rfc2243).
-#[cfg(feature = "parsing")]
-fn parse_otp_init_word <'a> (s: &'a str) -> Option<OTPInit<'a>> {
-    let mut sections = s.split(":");
-    let current_otp = sections.next()?;
-    let new_params = sections.next()?;
-    let new_otp = sections.next()?;
-    if sections.next().is_some() {
-        return None;
+impl HexStyle {
+
+    /// Four groups of four uppercase digits, like `9E87 6134 D904 99DD`.
+    pub const CANONICAL: HexStyle = HexStyle { grouped: true, uppercase: true, prefix: false };
+
+    /// Contiguous lowercase digits, like `9e876134d90499dd`, as printed by
+    /// OpenBSD's `skey(1)`.
+    pub const CONTIGUOUS_LOWERCASE: HexStyle = HexStyle { grouped: false, uppercase: false, prefix: false };
+
+    /// Contiguous uppercase digits, like `9E876134D90499DD`.
+    pub const CONTIGUOUS_UPPERCASE: HexStyle = HexStyle { grouped: false, uppercase: true, prefix: false };
+
+    /// The same style, but with `hex:` prepended.
+    pub const fn with_prefix (self) -> HexStyle {
+        HexStyle { prefix: true, ..self }
     }
-    let mut params = new_params.split(" ");
-    let algorithm = params.next()?;
-    let sequence_number = params.next()?;
-    let seed = params.next()?;
-    let sequence_number = <usize>::from_str_radix(sequence_number, 10).ok()?;
-    Some(OTPInit {
-        current_otp: HexOrWords::Words(current_otp),
-        new_otp: HexOrWords::Words(new_otp),
-        new_alg: algorithm,
-        new_seq_num: sequence_number,
-        new_seed: seed,
-    })
+
 }
 
-/// Parse OTP init strings per Section 4.1 of
-/// [IETF RFC 2243](https://www.rfc-editor.org/rfc/rfc2243).
-#[cfg(feature = "parsing")]
-pub fn parse_otp_init <'a> (s: &'a str) -> Option<OTPInit<'a>> {
-    if s.len() <= 50 || s.len() > 100 { // Arbitrary upper limit
-        return None;
-    }
-    if s.starts_with("init-hex:") {
-        parse_otp_init_hex(&s[9..])
-    }
-    else if s.starts_with("init-word:") {
-        parse_otp_init_word(&s[10..])
-    }
-    else {
-        None
+impl Default for HexStyle {
+
+    fn default() -> Self {
+        HexStyle::CANONICAL
     }
+
 }
 
-/// Parse OTP response strings per Sections 3 and 4 of
-/// [IETF RFC 2243](https://www.rfc-editor.org/rfc/rfc2243).
-#[cfg(feature = "parsing")]
-pub fn parse_otp_response <'a> (s: &'a str) -> Option<OTPResponse<'a>> {
-    if s.len() < 20 || s.len() > 100 { // Arbitrary upper limit
-        return None;
+/// How an OTP value is formatted in a response sent to a server.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum ResponseFormat {
+    /// Six words from the standard dictionary, prefixed with `word:`. Per
+    /// Section 6.0 of [IETF RFC 2289](https://www.rfc-editor.org/rfc/rfc2289.html),
+    /// clients should use this by default.
+    #[default]
+    Words,
+    /// Hexadecimal in the canonical [HexStyle], prefixed with `hex:`.
+    Hex,
+    /// Words if they come from the standard dictionary, and hexadecimal
+    /// otherwise, since the server might not know a custom dictionary.
+    Auto,
+}
+
+#[cfg(feature = "words")]
+impl ResponseFormat {
+
+    /// Resolve [ResponseFormat::Auto] to either words or hexadecimal,
+    /// depending on whether `dictionary` is the standard dictionary.
+    pub fn resolve <D: Dictionary + ?Sized> (self, dictionary: &D) -> ResponseFormat {
+        match self {
+            ResponseFormat::Auto if dictionary.is_standard() => ResponseFormat::Words,
+            ResponseFormat::Auto => ResponseFormat::Hex,
+            format => format,
+        }
+    }
+
+}
+
+/// Write a 64-bit value as hexadecimal in the given style to any
+/// [core::fmt::Write] sink, such as a `heapless::String`. This does not
+/// allocate.
+///
+/// See [format_hex].
+pub fn write_hex <W: core::fmt::Write> (w: &mut W, otp: &[u8; 8], style: HexStyle) -> core::fmt::Result {
+    let digits: &[u8; 16] = if style.uppercase {
+        b"0123456789ABCDEF"
+    } else {
+        b"0123456789abcdef"
+    };
+    if style.prefix {
+        w.write_str("hex:")?;
+    }
+    for (i, byte) in otp.iter().enumerate() {
+        if style.grouped && i > 0 && i % 2 == 0 {
+            w.write_char(' ')?;
+        }
+        w.write_char(digits[(byte >> 4) as usize] as char)?;
+        w.write_char(digits[(byte & 0x0F) as usize] as char)?;
+    }
+    Ok(())
+}
+
+/// Format a 64-bit value as hexadecimal in the given style.
+///
+/// ```rust
+/// use rfc2289_otp::{format_hex, HexStyle};
+/// let otp = [ 0x9E, 0x87, 0x61, 0x34, 0xD9, 0x04, 0x99, 0xDD ];
+/// assert_eq!(format_hex(&otp, HexStyle::CANONICAL), "9E87 6134 D904 99DD");
+/// assert_eq!(format_hex(&otp, HexStyle::CONTIGUOUS_LOWERCASE.with_prefix()), "hex:9e876134d90499dd");
+/// ```
+#[cfg(feature = "alloc")]
+pub fn format_hex (otp: &[u8; 8], style: HexStyle) -> String {
+    let mut s = String::with_capacity(MAX_HEX_STRING_LEN);
+    // Writing to a String cannot fail.
+    let _ = write_hex(&mut s, otp, style);
+    s
+}
+
+/// Decode a 64-bit value using the standard dictionary words defined in
+/// [IETF RFC 1760](https://www.rfc-editor.org/rfc/rfc1760) for use in S/KEY,
+/// and used OTP in
+/// [IETF RFC 2289](https://www.rfc-editor.org/rfc/rfc2289.html).
+///
+/// Returns `None` if a word does not appear in the standard dictionary.
+/// Otherwise, returns the decoded bytes and a `bool` indicating whether the
+/// checksum was valid, respectively. Words are matched case-insensitively.
+///
+/// The inverse of [convert_to_word_format].
+#[cfg(feature = "words")]
+pub fn decode_word_format_with_std_dict (words: [&str; 6]) -> Option<([u8; 8], bool)> {
+    let mut indices = [0u16; 6];
+    for (index, word) in indices.iter_mut().zip(words) {
+        *index = std_dict_index(word)? as u16;
+    }
+    Some(word_indices_to_otp(&indices))
+}
+
+/// An error decoding dictionary words into a 64-bit OTP value.
+#[cfg(feature = "words")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WordDecodeError {
+    /// There were not exactly six words. Contains the number of words found.
+    WrongWordCount(usize),
+    /// A word does not appear in the dictionary.
+    ///
+    /// This does not borrow the word itself, since that would give this error,
+    /// and every error that wraps it, such as [OtpError], a lifetime, so that
+    /// none of them could be returned as a `'static` error. Instead,
+    /// [WordDecodeError::word] finds the word in the input by its index.
+    UnknownWord {
+        /// The zero-based index of the word among the six words.
+        index: usize,
+    },
+    /// The checksum encoded in the last word did not match the decoded value.
+    BadChecksum,
+}
+
+#[cfg(feature = "words")]
+impl WordDecodeError {
+
+    /// Returns `true` if the input looks like it was meant to be six words,
+    /// but was mistyped: that is, if a word was unknown or the checksum failed,
+    /// rather than the input not having six words at all.
+    pub fn is_probable_typo (&self) -> bool {
+        matches!(self, WordDecodeError::UnknownWord { .. } | WordDecodeError::BadChecksum)
+    }
+
+    /// Returns the word that was not found in the dictionary, borrowed from
+    /// `input`, which must be the string that failed to decode. This lets user
+    /// interfaces highlight the offending word.
+    ///
+    /// ```rust
+    /// use rfc2289_otp::decode_word_str;
+    /// let input = "AURA ALOE HURL WINGS BERG WAIT";
+    /// let e = decode_word_str(input).unwrap_err();
+    /// assert_eq!(e.word(input), Some("WINGS"));
+    /// ```
+    pub fn word <'a> (&self, input: &'a str) -> Option<&'a str> {
+        match self {
+            WordDecodeError::UnknownWord { index } => input.split_ascii_whitespace().nth(*index),
+            _ => None,
+        }
+    }
+
+}
+
+#[cfg(feature = "words")]
+impl core::fmt::Display for WordDecodeError {
+
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            WordDecodeError::WrongWordCount(n) => write!(f, "expected six words, but found {}", n),
+            WordDecodeError::UnknownWord { index } => write!(f, "word {} not found in dictionary", index + 1),
+            WordDecodeError::BadChecksum => f.write_str("invalid checksum"),
+        }
+    }
+
+}
+
+/// Decode a single string of six whitespace-separated words, such as
+/// `"AURA ALOE HURL WING BERG WAIT"`, to a 64-bit value using the standard
+/// dictionary defined in [IETF RFC 1760](https://www.rfc-editor.org/rfc/rfc1760).
+///
+/// Unlike [decode_word_format_with_std_dict], this validates the checksum.
+/// Words are matched case-insensitively, so words written in any
+/// [WordStyle] can be decoded.
+#[cfg(feature = "words")]
+pub fn decode_word_str (s: &str) -> Result<[u8; 8], WordDecodeError> {
+    decode_word_str_with(s, word_index)
+}
+
+/// Decode six whitespace-separated words as [decode_word_str] does, looking
+/// each up with `word_index`, for profiles that read words differently.
+#[cfg(feature = "words")]
+pub(crate) fn decode_word_str_with (
+    s: &str,
+    word_index: impl Fn(&str) -> Option<u16>,
+) -> Result<[u8; 8], WordDecodeError> {
+    let mut words = s.split_ascii_whitespace();
+    let mut six_words: [&str; 6] = INIT_SIX_WORDS;
+    for (i, word) in six_words.iter_mut().enumerate() {
+        *word = words.next().ok_or(WordDecodeError::WrongWordCount(i))?;
+    }
+    let extra_words = words.count();
+    if extra_words > 0 {
+        return Err(WordDecodeError::WrongWordCount(6 + extra_words));
+    }
+    let mut indices = [0u16; 6];
+    for (i, (index, word)) in indices.iter_mut().zip(six_words).enumerate() {
+        *index = word_index(word).ok_or(WordDecodeError::UnknownWord { index: i })?;
+    }
+    let (v, valid_checksum) = word_indices_to_otp(&indices);
+    if !valid_checksum {
+        return Err(WordDecodeError::BadChecksum);
+    }
+    Ok(v)
+}
+
+/// Returns `true` if `a` can be turned into `b` by inserting, deleting, or
+/// substituting at most one character, ignoring ASCII case.
+#[cfg(all(feature = "words", feature = "alloc"))]
+fn within_one_edit (a: &[u8], b: &[u8]) -> bool {
+    let (short, long) = if a.len() <= b.len() { (a, b) } else { (b, a) };
+    if long.len() - short.len() > 1 {
+        return false;
+    }
+    let prefix = short.iter()
+        .zip(long)
+        .take_while(|(x, y)| x.eq_ignore_ascii_case(y))
+        .count();
+    let skip_short = if short.len() == long.len() { 1 } else { 0 };
+    let short_rest = short.get(prefix + skip_short..).unwrap_or_default();
+    let long_rest = long.get(prefix + 1..).unwrap_or_default();
+    short_rest.eq_ignore_ascii_case(long_rest)
+}
+
+/// Suggest words from the standard dictionary defined in
+/// [IETF RFC 1760](https://www.rfc-editor.org/rfc/rfc1760) that are within an
+/// edit distance of one of `input`, ignoring case, so that a client can ask
+/// "did you mean BERG?" rather than re-prompting for all six words. Pair this
+/// with [WordDecodeError::word] to find the word that was mistyped.
+///
+/// The suggestions are returned in dictionary order, and include `input`
+/// itself if it is a word in the dictionary.
+///
+/// ```rust
+/// use rfc2289_otp::suggest_word;
+/// assert!(suggest_word("BERF").contains(&"BERG"));
+/// assert!(suggest_word("XYZZY").is_empty());
+/// ```
+#[cfg(all(feature = "words", feature = "alloc"))]
+pub fn suggest_word (input: &str) -> alloc::vec::Vec<&'static str> {
+    #[cfg(not(feature = "packed-dictionary"))]
+    let words = STANDARD_DICTIONARY.iter().copied();
+    #[cfg(feature = "packed-dictionary")]
+    let words = packed_dictionary_words();
+    words
+        .filter(|word| within_one_edit(input.as_bytes(), word.as_bytes()))
+        .collect()
+}
+
+// TODO: Move to documentation
+
+
+/// A parsed OTP challenge string per Section 2.1 of
+/// [IETF RFC 2243](https://www.rfc-editor.org/rfc/rfc2243).
+#[cfg(feature = "parsing-challenge")]
+#[derive(Debug, PartialEq, Eq)]
+pub struct OTPChallenge <'a> {
+    pub hash_alg: &'a str,
+    pub hash_count: usize,
+    pub seed: &'a str,
+    /// Capability words that followed the count, such as `ext`.
+    pub capabilities: Capabilities<'a>,
+}
+
+#[cfg(feature = "parsing-challenge")]
+impl <'a> OTPChallenge <'a> {
+
+    /// Use `alg` as the algorithm if this challenge has none, as when it was
+    /// constructed for an [IETF RFC 1760](https://www.rfc-editor.org/rfc/rfc1760)
+    /// S/KEY peer, whose algorithm is implied. See also
+    /// [parse_otp_challenge_with_default_alg].
+    pub fn with_default_alg (mut self, alg: HashAlg) -> Self {
+        if self.hash_alg.is_empty() {
+            self.hash_alg = alg.name();
+        }
+        self
+    }
+
+    /// Whether the server indicated that it accepts the extended responses
+    /// of [IETF RFC 2243](https://www.rfc-editor.org/rfc/rfc2243), including
+    /// re-initialization.
+    pub fn is_extended (&self) -> bool {
+        self.capabilities.contains("ext")
+    }
+
+    /// Calculate the OTP value for this challenge from `passphrase`, and
+    /// format it as a complete response in the given format, ready to be sent
+    /// to the server. The seed is lowercased and the word checksum is
+    /// encoded, as required.
+    ///
+    /// ```rust
+    /// use rfc2289_otp::{parse_otp_challenge, ResponseFormat};
+    /// let challenge = parse_otp_challenge("otp-md5 0 TeSt").unwrap();
+    /// assert_eq!(
+    ///     challenge.respond("This is a test.", ResponseFormat::Words).unwrap(),
+    ///     "word:INCH SEA ANNE LONG AHEM TOUR",
+    /// );
+    /// ```
+    #[cfg(all(feature = "words", feature = "alloc"))]
+    pub fn respond (&self, passphrase: &str, format: ResponseFormat) -> Result<String, CalculateError> {
+        self.respond_with_dictionary(passphrase, format, &StandardDictionary)
+    }
+
+    /// Like [OTPChallenge::respond], but words are taken from `dictionary`,
+    /// and [ResponseFormat::Auto] chooses hexadecimal if it is not the
+    /// standard dictionary. If `dictionary` is missing a word that is needed,
+    /// hexadecimal is used instead of words.
+    #[cfg(all(feature = "words", feature = "alloc"))]
+    pub fn respond_with_dictionary <D: Dictionary + ?Sized> (
+        &self,
+        passphrase: &str,
+        format: ResponseFormat,
+        dictionary: &D,
+    ) -> Result<String, CalculateError> {
+        let otp = calculate_otp_with_options(
+            self.hash_alg,
+            passphrase,
+            self.seed,
+            self.hash_count,
+            &CalculateOptions::default(),
+        )?;
+        if format.resolve(dictionary) == ResponseFormat::Words {
+            let mut s = String::with_capacity(MAX_WORD_STRING_LEN);
+            if write_words_with_dictionary(&mut s, &otp, dictionary, true).is_ok() {
+                return Ok(s);
+            }
+        }
+        Ok(format_hex(&otp, HexStyle::CANONICAL.with_prefix()))
+    }
+
+}
+
+/// Capability words that a challenge may contain in addition to the seed.
+/// These are matched case-insensitively.
+#[cfg(feature = "parsing-challenge")]
+pub const KNOWN_CAPABILITIES: [&str; 1] = [ "ext" ];
+
+#[cfg(feature = "parsing-challenge")]
+fn is_known_capability (token: &str) -> bool {
+    KNOWN_CAPABILITIES.iter().any(|c| c.eq_ignore_ascii_case(token))
+}
+
+/// The capability words of an OTP challenge: every token after the count
+/// other than the seed, in the order that they appeared.
+#[cfg(feature = "parsing-challenge")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Capabilities <'a> {
+    tokens: &'a str,
+    seed_index: Option<usize>,
+}
+
+#[cfg(feature = "parsing-challenge")]
+impl <'a> Capabilities <'a> {
+
+    /// Iterate over the capability words.
+    pub fn iter (&self) -> impl Iterator<Item = &'a str> + 'a {
+        let seed_index = self.seed_index;
+        self.tokens
+            .split_ascii_whitespace()
+            .enumerate()
+            .filter(move |(i, _)| Some(*i) != seed_index)
+            .map(|(_, token)| token)
+    }
+
+    /// Whether `capability` is present, compared case-insensitively.
+    pub fn contains (&self, capability: &str) -> bool {
+        self.iter().any(|c| c.eq_ignore_ascii_case(capability))
+    }
+
+    /// Whether there are no capability words.
+    pub fn is_empty (&self) -> bool {
+        self.iter().next().is_none()
+    }
+
+}
+
+#[cfg(feature = "parsing-challenge")]
+impl PartialEq for Capabilities<'_> {
+
+    fn eq (&self, other: &Self) -> bool {
+        self.iter().eq(other.iter())
+    }
+
+}
+
+#[cfg(feature = "parsing-challenge")]
+impl Eq for Capabilities<'_> {}
+
+/// A parsed OTP init string per Section 4.1 of
+/// [IETF RFC 2243](https://www.rfc-editor.org/rfc/rfc2243).
+///
+/// Any number of capability words, such as `ext`, may appear after the count,
+/// before or after the seed. The seed is the first token after the count that
+/// is not one of the [KNOWN_CAPABILITIES], or, if there is no such token, the
+/// first token after the count.
+#[cfg(feature = "parsing-challenge")]
+pub fn parse_otp_challenge <'a> (s: &'a str) -> Option<OTPChallenge<'a>> {
+    if s.len() < 9 { // This is the smallest that an OTP challenge can be.
+        return None;
+    }
+    if s.len() > 128 {
+        return None; // To prevent denial of service via outrageous values.
+    }
+    if !s.starts_with("otp-") {
+        return None;
+    }
+    let (rest, hash_alg) = parse::combinators::token(&s[4..])?;
+    parse_challenge_count_and_seed(hash_alg, rest)
+}
+
+/// Like [parse_otp_challenge], but also accepts challenges in which the
+/// algorithm is implied, as sent by
+/// [IETF RFC 1760](https://www.rfc-editor.org/rfc/rfc1760) S/KEY peers: either
+/// `s/key` or nothing, followed by the count and seed. For these, `hash_alg` is
+/// the name of `default_alg`.
+///
+/// ```rust
+/// use rfc2289_otp::{parse_otp_challenge_with_default_alg, HashAlg};
+/// let challenge = parse_otp_challenge_with_default_alg("s/key 99 th91334", HashAlg::Md4).unwrap();
+/// assert_eq!((challenge.hash_alg, challenge.hash_count, challenge.seed), ("md4", 99, "th91334"));
+/// let challenge = parse_otp_challenge_with_default_alg("otp-sha1 99 th91334", HashAlg::Md4).unwrap();
+/// assert_eq!(challenge.hash_alg, "sha1");
+/// ```
+#[cfg(feature = "parsing-challenge")]
+pub fn parse_otp_challenge_with_default_alg <'a> (s: &'a str, default_alg: HashAlg) -> Option<OTPChallenge<'a>> {
+    if s.starts_with("otp-") {
+        return parse_otp_challenge(s);
+    }
+    if s.len() > 128 {
+        return None;
+    }
+    let rest = match parse::combinators::token(s)? {
+        (rest, first) if first.eq_ignore_ascii_case("s/key") => rest,
+        _ => s,
+    };
+    parse_challenge_count_and_seed(default_alg.name(), rest)
+}
+
+/// Parse the count, seed, and capability words that follow the algorithm of
+/// a challenge.
+#[cfg(feature = "parsing-challenge")]
+fn parse_challenge_count_and_seed <'a> (hash_alg: &'a str, rest: &'a str) -> Option<OTPChallenge<'a>> {
+    let (tokens, hash_count) = parse::combinators::count(rest)?;
+    let seed_index = tokens
+        .split_ascii_whitespace()
+        .position(|t| !is_known_capability(t))
+        .unwrap_or(0);
+    let seed = tokens.split_ascii_whitespace().nth(seed_index)?;
+    Some(OTPChallenge{
+        hash_alg,
+        hash_count,
+        seed,
+        capabilities: Capabilities { tokens, seed_index: Some(seed_index) },
+    })
+}
+
+/// An error parsing an OTP challenge with [parse_otp_challenge_strict].
+#[cfg(feature = "parsing-challenge")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ChallengeParseError {
+    /// The challenge is not syntactically valid.
+    Malformed,
+    /// The seed is not one to sixteen alphanumeric characters.
+    BadSeed,
+}
+
+#[cfg(feature = "parsing-challenge")]
+impl core::fmt::Display for ChallengeParseError {
+
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            ChallengeParseError::Malformed => f.write_str("malformed OTP challenge"),
+            ChallengeParseError::BadSeed => f.write_str("invalid seed"),
+        }
+    }
+
+}
+
+/// Like [parse_otp_challenge], but also requires the seed to follow the
+/// grammar of Section 6.0 of
+/// [IETF RFC 2289](https://www.rfc-editor.org/rfc/rfc2289.html): one to
+/// sixteen alphanumeric characters. Seeds containing other characters, such
+/// as `:`, would produce init responses that cannot be parsed.
+#[cfg(feature = "parsing-challenge")]
+pub fn parse_otp_challenge_strict <'a> (s: &'a str) -> Result<OTPChallenge<'a>, ChallengeParseError> {
+    let challenge = parse_otp_challenge(s).ok_or(ChallengeParseError::Malformed)?;
+    if !is_valid_seed(challenge.seed) {
+        return Err(ChallengeParseError::BadSeed);
+    }
+    Ok(challenge)
+}
+
+pub type Hex64Bit = [u8; 8];
+
+/// A Hex value or dictionary words
+#[cfg(feature = "parsing-response")]
+#[derive(PartialEq, Eq)]
+pub enum HexOrWords <'a> {
+    Hex(Hex64Bit),
+    Words(&'a str),
+}
+
+#[cfg(all(feature = "parsing-response", feature = "words"))]
+impl HexOrWords<'_> {
+
+    /// Decode this value to 64 bits, returning `None` if it is invalid for any
+    /// reason. Use [HexOrWords::decode] to find out why.
+    pub fn try_into_bytes (&self) -> Option<[u8; 8]> {
+        self.decode().ok()
+    }
+
+    /// Decode this value to 64 bits, using the standard dictionary if it is
+    /// words. Returns an error describing why the words could not be decoded,
+    /// if they could not.
+    pub fn decode (&self) -> Result<[u8; 8], WordDecodeError> {
+        match self {
+            HexOrWords::Hex(h) => Ok(*h),
+            HexOrWords::Words(w) => decode_word_str(w),
+        }
+    }
+
+    /// Returns `true` if `self` and `other` encode the same 64-bit value,
+    /// regardless of whether either is hex or words, or how they are spaced or
+    /// capitalized. If either cannot be decoded, this falls back to comparing
+    /// them structurally, as `==` does.
+    pub fn semantically_eq (&self, other: &HexOrWords<'_>) -> bool {
+        match (self.decode(), other.decode()) {
+            (Ok(a), Ok(b)) => verify_otp_eq(&a, &b),
+            _ => self == other,
+        }
+    }
+
+}
+
+/// A parsed OTP init string per Section 4.1 of
+/// [IETF RFC 2243](https://www.rfc-editor.org/rfc/rfc2243).
+#[cfg(feature = "parsing-response")]
+#[derive(PartialEq, Eq)]
+pub struct OTPInit <'a> {
+    pub current_otp: HexOrWords<'a>,
+    pub new_otp: HexOrWords<'a>,
+    pub new_alg: &'a str,
+    pub new_seq_num: usize,
+    pub new_seed: &'a str,
+    /// Any `:`-separated fields after the new OTP, which future extensions
+    /// may add, without the `:` that precedes the first, or `None` if there
+    /// were none. These are preserved so that the response can be forwarded
+    /// intact by [write_otp_response].
+    pub extra: Option<&'a str>,
+}
+
+#[cfg(feature = "parsing-response")]
+impl <'a> OTPInit <'a> {
+
+    /// Iterate over the fields in [OTPInit::extra].
+    pub fn extra_fields (&self) -> impl Iterator<Item = &'a str> + 'a {
+        self.extra.into_iter().flat_map(|extra| extra.split(':'))
+    }
+
+    /// Decode the OTP value for the current sequence.
+    #[cfg(feature = "words")]
+    pub fn current_bytes (&self) -> Result<[u8; 8], WordDecodeError> {
+        self.current_otp.decode()
+    }
+
+    /// Decode the first OTP value of the new sequence.
+    #[cfg(feature = "words")]
+    pub fn new_bytes (&self) -> Result<[u8; 8], WordDecodeError> {
+        self.new_otp.decode()
+    }
+
+    /// Decode both OTP values, and check that the new algorithm is supported,
+    /// that the new count is from one to [DEFAULT_MAX_COUNT], and that the new
+    /// seed is valid per [is_valid_seed].
+    #[cfg(feature = "words")]
+    pub fn validate (&self) -> Result<ValidatedInit, InitValidationError> {
+        let current_otp = self.current_bytes().map_err(InitValidationError::CurrentOtp)?;
+        let new_otp = self.new_bytes().map_err(InitValidationError::NewOtp)?;
+        let new_alg = HashAlg::from_name(self.new_alg)
+            .filter(HashAlg::is_enabled)
+            .ok_or(InitValidationError::UnsupportedAlgorithm)?;
+        if !(1..=DEFAULT_MAX_COUNT).contains(&self.new_seq_num) {
+            return Err(InitValidationError::BadCount);
+        }
+        let new_seed = Seed::new(self.new_seed).ok_or(InitValidationError::BadSeed)?;
+        Ok(ValidatedInit { current_otp, new_otp, new_alg, new_seq_num: self.new_seq_num, new_seed })
+    }
+
+}
+
+/// The contents of an init response, decoded and validated by
+/// [OTPInit::validate].
+#[cfg(all(feature = "parsing-response", feature = "words"))]
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct ValidatedInit {
+    /// The OTP value for the current sequence.
+    pub current_otp: [u8; 8],
+    /// The first OTP value of the new sequence.
+    pub new_otp: [u8; 8],
+    /// The hash algorithm of the new sequence, which is enabled.
+    pub new_alg: HashAlg,
+    /// The count of the new sequence.
+    pub new_seq_num: usize,
+    /// The seed of the new sequence.
+    pub new_seed: Seed,
+}
+
+/// An error validating an init response with [OTPInit::validate].
+#[cfg(all(feature = "parsing-response", feature = "words"))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum InitValidationError {
+    /// The OTP value for the current sequence could not be decoded.
+    CurrentOtp(WordDecodeError),
+    /// The first OTP value of the new sequence could not be decoded.
+    NewOtp(WordDecodeError),
+    /// The new hash algorithm is not understood, or its feature flag is not
+    /// enabled.
+    UnsupportedAlgorithm,
+    /// The new count is zero, or exceeds [DEFAULT_MAX_COUNT].
+    BadCount,
+    /// The new seed is not one to sixteen alphanumeric characters.
+    BadSeed,
+}
+
+#[cfg(all(feature = "parsing-response", feature = "words"))]
+impl core::fmt::Display for InitValidationError {
+
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            InitValidationError::CurrentOtp(e) => write!(f, "current OTP: {}", e),
+            InitValidationError::NewOtp(e) => write!(f, "new OTP: {}", e),
+            InitValidationError::UnsupportedAlgorithm => f.write_str("unsupported hash algorithm"),
+            InitValidationError::BadCount => f.write_str("invalid hash count"),
+            InitValidationError::BadSeed => f.write_str("invalid seed"),
+        }
     }
-    if s.starts_with("hex:") {
-        let h = <Hex64Bit>::from_hex(&s[4..].cow_replace(" ", "").cow_replace("\t", "").as_ref()).ok()?;
+
+}
+
+/// A parsed OTP response per Sections 3 and 4 of
+/// [IETF RFC 2243](https://www.rfc-editor.org/rfc/rfc2243).
+#[cfg(feature = "parsing-response")]
+#[derive(PartialEq, Eq)]
+pub enum OTPResponse <'a> {
+    Init(OTPInit <'a>),
+    Current(HexOrWords<'a>)
+}
+
+/// Decode 64 bits of hexadecimal, in either case, ignoring any spaces and tabs
+/// between digits, as allowed by Section 3 of
+/// [IETF RFC 2243](https://www.rfc-editor.org/rfc/rfc2243). Returns `None` if
+/// there are not exactly sixteen hexadecimal digits.
+///
+/// The digits may be grouped in any way, or not at all, as when a terminal
+/// drops the spaces between groups when they are copied, leaving the groups
+/// distinguishable only by their case. This applies to every hexadecimal
+/// value of a response, including both of those in an `init-hex` response.
+///
+/// This decodes each digit directly into the output, so it does not allocate.
+pub fn decode_hex_otp (s: &str) -> Option<[u8; 8]> {
+    let mut out = [0u8; 8];
+    let mut nibbles = s
+        .bytes()
+        .filter(|b| *b != b' ' && *b != b'\t')
+        .map(|b| (b as char).to_digit(16));
+    for byte in out.iter_mut() {
+        let high = nibbles.next()??;
+        let low = nibbles.next()??;
+        *byte = ((high << 4) | low) as u8;
+    }
+    if nibbles.next().is_some() {
+        return None;
+    }
+    Some(out)
+}
+
+/// Parse OTP `init-hex-response` per Section 4.1 of
+/// [IETF RFC 2243](https://www.rfc-editor.org/rfc/rfc2243).
+#[cfg(feature = "parsing-response")]
+fn parse_otp_init_hex <'a> (s: &'a str) -> Option<OTPInit<'a>> {
+    let mut sections = s.splitn(4, ':');
+    let current_otp = sections.next()?;
+    let new_params = sections.next()?;
+    let new_otp = sections.next()?;
+    let extra = sections.next();
+    let current_otp = decode_hex_otp(current_otp)?;
+    let new_otp = decode_hex_otp(new_otp)?;
+    let mut params = new_params.split(" ");
+    let algorithm = params.next()?;
+    let sequence_number = params.next()?;
+    let seed = params.next()?;
+    let sequence_number = sequence_number.parse::<usize>().ok()?;
+    Some(OTPInit {
+        current_otp: HexOrWords::Hex(current_otp),
+        new_otp: HexOrWords::Hex(new_otp),
+        new_alg: algorithm,
+        new_seq_num: sequence_number,
+        new_seed: seed,
+        extra,
+    })
+}
+
+/// Parse OTP `init-word-response` per Section 4.1 of
+/// [IETF RFC 2243](https://www.rfc-editor.org/rfc/rfc2243).
+#[cfg(feature = "parsing-response")]
+fn parse_otp_init_word <'a> (s: &'a str) -> Option<OTPInit<'a>> {
+    let mut sections = s.splitn(4, ':');
+    let current_otp = sections.next()?;
+    let new_params = sections.next()?;
+    let new_otp = sections.next()?;
+    let extra = sections.next();
+    let mut params = new_params.split(" ");
+    let algorithm = params.next()?;
+    let sequence_number = params.next()?;
+    let seed = params.next()?;
+    let sequence_number = sequence_number.parse::<usize>().ok()?;
+    Some(OTPInit {
+        current_otp: HexOrWords::Words(current_otp),
+        new_otp: HexOrWords::Words(new_otp),
+        new_alg: algorithm,
+        new_seq_num: sequence_number,
+        new_seed: seed,
+        extra,
+    })
+}
+
+/// Parse OTP init strings per Section 4.1 of
+/// [IETF RFC 2243](https://www.rfc-editor.org/rfc/rfc2243).
+#[cfg(feature = "parsing-response")]
+pub fn parse_otp_init <'a> (s: &'a str) -> Option<OTPInit<'a>> {
+    if s.len() < 50 || s.len() > MAX_RESPONSE_LEN {
+        return None;
+    }
+    if let Some(rest) = s.strip_prefix("init-hex:") {
+        parse_otp_init_hex(rest)
+    }
+    else if let Some(rest) = s.strip_prefix("init-word:") {
+        parse_otp_init_word(rest)
+    }
+    else {
+        None
+    }
+}
+
+/// Options for [parse_otp_response_with_options].
+#[cfg(feature = "parsing-response")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ParseResponseOptions {
+    /// Accept bare six words or sixteen hexadecimal digits, with no `word:` or
+    /// `hex:` prefix, as users often paste into command-line clients. This is
+    /// not allowed by [IETF RFC 2243](https://www.rfc-editor.org/rfc/rfc2243),
+    /// so it defaults to `false`.
+    pub accept_bare: bool,
+}
+
+/// Parse a response with no prefix, which must be six words of one to four
+/// letters, or sixteen hexadecimal digits. Input that could be either is
+/// treated as words.
+#[cfg(feature = "parsing-response")]
+fn parse_bare_otp <'a> (s: &'a str) -> Option<HexOrWords<'a>> {
+    use parse::combinators::{hex_group, six_words};
+    if let Some((rest, words)) = six_words(s) {
+        if rest.trim_matches(|c: char| c.is_ascii_whitespace()).is_empty() {
+            return Some(HexOrWords::Words(words));
+        }
+    }
+    let (rest, hex) = hex_group(s)?;
+    rest.trim_matches([' ', '\t']).is_empty().then_some(HexOrWords::Hex(hex))
+}
+
+/// Parse OTP response strings per Sections 3 and 4 of
+/// [IETF RFC 2243](https://www.rfc-editor.org/rfc/rfc2243).
+#[cfg(feature = "parsing-response")]
+pub fn parse_otp_response <'a> (s: &'a str) -> Option<OTPResponse<'a>> {
+    parse_otp_response_with_options(s, &ParseResponseOptions::default())
+}
+
+/// Parse an OTP response directly from an untrusted network buffer, as
+/// [parse_otp_response] does. Buffers longer than `max_len` bytes, or than
+/// [MAX_RESPONSE_LEN], are rejected before any of their bytes are read, and the
+/// rest must be printable ASCII or tabs. This never allocates, and the
+/// response borrows from `buf`.
+///
+/// ```rust
+/// use rfc2289_otp::parse_otp_response_bytes;
+/// assert!(parse_otp_response_bytes(b"word:INCH SEA ANNE LONG AHEM TOUR", 64).is_some());
+/// assert!(parse_otp_response_bytes(b"word:INCH SEA ANNE LONG AHEM TOUR", 32).is_none());
+/// assert!(parse_otp_response_bytes(b"word:INCH SEA ANNE LONG AHEM TOUR\r\n", 64).is_none());
+/// ```
+#[cfg(feature = "parsing-response")]
+pub fn parse_otp_response_bytes (buf: &[u8], max_len: usize) -> Option<OTPResponse<'_>> {
+    if buf.len() > max_len.min(MAX_RESPONSE_LEN) {
+        return None;
+    }
+    if !buf.iter().all(|&b| b == b'\t' || (b' '..=b'~').contains(&b)) {
+        return None;
+    }
+    parse_otp_response(core::str::from_utf8(buf).ok()?)
+}
+
+/// Parse OTP response strings as [parse_otp_response] does, with the given
+/// options.
+///
+/// ```rust
+/// use rfc2289_otp::{parse_otp_response_with_options, HexOrWords, OTPResponse, ParseResponseOptions};
+/// let options = ParseResponseOptions { accept_bare: true };
+/// assert_eq!(
+///     parse_otp_response_with_options("inch sea anne long ahem tour", &options),
+///     Some(OTPResponse::Current(HexOrWords::Words("inch sea anne long ahem tour"))),
+/// );
+/// assert!(parse_otp_response_with_options("9E87 6134 D904 99DD", &options).is_some());
+/// assert!(parse_otp_response_with_options("9E87 6134 D904 99DD", &Default::default()).is_none());
+/// ```
+#[cfg(feature = "parsing-response")]
+pub fn parse_otp_response_with_options <'a> (s: &'a str, options: &ParseResponseOptions) -> Option<OTPResponse<'a>> {
+    if options.accept_bare && s.len() <= MAX_RESPONSE_LEN && !s.contains(':') {
+        return parse_bare_otp(s).map(OTPResponse::Current);
+    }
+    if s.len() < 20 || s.len() > MAX_RESPONSE_LEN {
+        return None;
+    }
+    if let Some(rest) = s.strip_prefix("hex:") {
+        let h = decode_hex_otp(rest)?;
         Some(OTPResponse::Current(HexOrWords::Hex(h)))
     }
-    else if s.starts_with("word:") {
-        Some(OTPResponse::Current(HexOrWords::Words(&s[5..])))
+    else if let Some(rest) = s.strip_prefix("word:") {
+        Some(OTPResponse::Current(HexOrWords::Words(rest)))
+    }
+    else if let Some(rest) = s.strip_prefix("init-hex:") {
+        parse_otp_init_hex(rest).map(OTPResponse::Init)
+    }
+    else if let Some(rest) = s.strip_prefix("init-word:") {
+        parse_otp_init_word(rest).map(OTPResponse::Init)
+    }
+    else {
+        None
+    }
+}
+
+/// Write a parsed response back out, such as when forwarding it. Hexadecimal is
+/// written in [HexStyle::CANONICAL], words are written as they were parsed,
+/// and any [OTPInit::extra] fields are preserved, so that parsing the output
+/// yields the same response.
+///
+/// Returns an error if an init response mixes hexadecimal and words, which
+/// cannot be represented.
+///
+/// ```rust
+/// use rfc2289_otp::{parse_otp_response, write_otp_response};
+/// let s = "init-word:INCH SEA ANNE LONG AHEM TOUR:md5 499 ke1235:GLOB SOFT CALL ARCH FLEW FREY:ext1:ext2";
+/// let mut out = String::new();
+/// write_otp_response(&mut out, &parse_otp_response(s).unwrap()).unwrap();
+/// assert_eq!(out, s);
+/// ```
+#[cfg(feature = "parsing-response")]
+pub fn write_otp_response <W: core::fmt::Write> (w: &mut W, response: &OTPResponse<'_>) -> core::fmt::Result {
+    fn write_value <W: core::fmt::Write> (w: &mut W, value: &HexOrWords<'_>) -> core::fmt::Result {
+        match value {
+            HexOrWords::Hex(h) => write_hex(w, h, HexStyle::CANONICAL),
+            HexOrWords::Words(words) => w.write_str(words),
+        }
+    }
+    match response {
+        OTPResponse::Current(value) => {
+            w.write_str(if matches!(value, HexOrWords::Hex(_)) { "hex:" } else { "word:" })?;
+            write_value(w, value)
+        },
+        OTPResponse::Init(init) => {
+            let prefix = match (&init.current_otp, &init.new_otp) {
+                (HexOrWords::Hex(_), HexOrWords::Hex(_)) => "init-hex:",
+                (HexOrWords::Words(_), HexOrWords::Words(_)) => "init-word:",
+                _ => return Err(core::fmt::Error),
+            };
+            w.write_str(prefix)?;
+            write_value(w, &init.current_otp)?;
+            write!(w, ":{} {} {}:", init.new_alg, init.new_seq_num, init.new_seed)?;
+            write_value(w, &init.new_otp)?;
+            if let Some(extra) = init.extra {
+                write!(w, ":{}", extra)?;
+            }
+            Ok(())
+        },
+    }
+}
+
+/// An error canonicalizing a response with [canonicalize_response].
+#[cfg(all(feature = "parsing-response", feature = "words"))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum CanonicalizeError {
+    /// The response is not syntactically valid.
+    Malformed,
+    /// The response's words could not be decoded.
+    Undecodable(WordDecodeError),
+}
+
+#[cfg(all(feature = "parsing-response", feature = "words"))]
+impl core::fmt::Display for CanonicalizeError {
+
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            CanonicalizeError::Malformed => f.write_str("malformed OTP response"),
+            CanonicalizeError::Undecodable(e) => e.fmt(f),
+        }
+    }
+
+}
+
+/// Parse a response, decode its OTP values, and write it back out in a
+/// canonical form, so that responses which encode the same values can be
+/// deduplicated or compared as strings. Hexadecimal is written in
+/// [HexStyle::CANONICAL], words are written in uppercase from the standard
+/// dictionary with single spaces, and the algorithm and seed of init
+/// responses are lowercased. Any [OTPInit::extra] fields are kept as they were.
+///
+/// ```rust
+/// use rfc2289_otp::canonicalize_response;
+/// assert_eq!(canonicalize_response("hex:5bf075d9959d036f").unwrap(), "hex:5BF0 75D9 959D 036F");
+/// assert_eq!(
+///     canonicalize_response("word:inch  sea anne long ahem tour").unwrap(),
+///     "word:INCH SEA ANNE LONG AHEM TOUR",
+/// );
+/// ```
+#[cfg(all(feature = "parsing-response", feature = "words", feature = "alloc"))]
+pub fn canonicalize_response (s: &str) -> Result<String, CanonicalizeError> {
+    use core::fmt::Write;
+
+    fn write_value (out: &mut String, value: &HexOrWords<'_>) -> Result<(), CanonicalizeError> {
+        let otp = value.decode().map_err(CanonicalizeError::Undecodable)?;
+        match value {
+            HexOrWords::Hex(_) => { let _ = write_hex(out, &otp, HexStyle::CANONICAL); },
+            HexOrWords::Words(_) => { let _ = write_words(out, &otp, false); },
+        }
+        Ok(())
+    }
+
+    let response = parse_otp_response(s).ok_or(CanonicalizeError::Malformed)?;
+    let mut out = String::new();
+    match &response {
+        OTPResponse::Current(value) => {
+            out.push_str(if matches!(value, HexOrWords::Hex(_)) { "hex:" } else { "word:" });
+            write_value(&mut out, value)?;
+        },
+        OTPResponse::Init(init) => {
+            out.push_str(if matches!(init.current_otp, HexOrWords::Hex(_)) { "init-hex:" } else { "init-word:" });
+            write_value(&mut out, &init.current_otp)?;
+            let _ = write!(
+                out,
+                ":{} {} {}:",
+                init.new_alg.to_ascii_lowercase(),
+                init.new_seq_num,
+                init.new_seed.to_ascii_lowercase(),
+            );
+            write_value(&mut out, &init.new_otp)?;
+            if let Some(extra) = init.extra {
+                out.push(':');
+                out.push_str(extra);
+            }
+        },
     }
-    else if s.starts_with("init-hex:") {
-        parse_otp_init_hex(&s[9..]).map(|i| OTPResponse::Init(i))
+    Ok(out)
+}
+
+/// Clean up an OTP response as typed or pasted by a user, so that
+/// [parse_otp_response] will accept it. Surrounding whitespace and quotes are
+/// removed, runs of whitespace are collapsed to single spaces, words are
+/// uppercased, and hexadecimal is written in [HexStyle::CANONICAL]. Bare words
+/// or hexadecimal are given a `word:` or `hex:` prefix, as
+/// [ParseResponseOptions::accept_bare] would recognize them.
+///
+/// Nothing is decoded against a dictionary, so this does not check that the
+/// response is valid. Init responses only have their whitespace cleaned up.
+///
+/// ```rust
+/// use rfc2289_otp::sanitize_user_otp_input;
+/// assert_eq!(sanitize_user_otp_input(" \"inch sea  anne long ahem tour\"\n"), "word:INCH SEA ANNE LONG AHEM TOUR");
+/// assert_eq!(sanitize_user_otp_input("9e876134 d90499dd"), "hex:9E87 6134 D904 99DD");
+/// assert_eq!(sanitize_user_otp_input("HEX: 9e876134d90499dd"), "hex:9E87 6134 D904 99DD");
+/// ```
+#[cfg(all(feature = "parsing-response", feature = "alloc"))]
+pub fn sanitize_user_otp_input (s: &str) -> String {
+    const QUOTES: &[char] = &[ '"', '\'', '`', '\u{2018}', '\u{2019}', '\u{201C}', '\u{201D}' ];
+
+    fn push_collapsed (out: &mut String, s: &str, uppercase: bool) {
+        for (i, word) in s.split_ascii_whitespace().enumerate() {
+            if i > 0 {
+                out.push(' ');
+            }
+            if uppercase {
+                out.extend(word.chars().map(|c| c.to_ascii_uppercase()));
+            } else {
+                out.push_str(word);
+            }
+        }
     }
-    else if s.starts_with("init-word:") {
-        parse_otp_init_word(&s[10..]).map(|i| OTPResponse::Init(i))
+
+    let s = s.trim().trim_matches(QUOTES).trim();
+    let (is_hex, body) = match s.split_once(':') {
+        Some((prefix, rest)) if prefix.trim().eq_ignore_ascii_case("hex") => (true, rest),
+        Some((prefix, rest)) if prefix.trim().eq_ignore_ascii_case("word") => (false, rest),
+        Some(_) => {
+            let mut out = String::with_capacity(s.len());
+            push_collapsed(&mut out, s, false);
+            return out;
+        },
+        None => match parse_bare_otp(s) {
+            Some(HexOrWords::Hex(_)) => (true, s),
+            Some(HexOrWords::Words(_)) => (false, s),
+            None => {
+                let mut out = String::with_capacity(s.len());
+                push_collapsed(&mut out, s, false);
+                return out;
+            },
+        },
+    };
+    let mut out = String::with_capacity(MAX_HEX_STRING_LEN.max(s.len()) + 5);
+    if is_hex {
+        out.push_str("hex:");
+        match decode_hex_otp(body) {
+            // Writing to a String cannot fail.
+            Some(otp) => { let _ = write_hex(&mut out, &otp, HexStyle::CANONICAL); },
+            None => push_collapsed(&mut out, body, true),
+        }
+    } else {
+        out.push_str("word:");
+        push_collapsed(&mut out, body, true);
     }
-    else {
-        None
+    out
+}
+
+/// The result of verifying an OTP response against the expected OTP value
+/// using [verify_response].
+#[cfg(all(feature = "parsing-response", feature = "words"))]
+#[derive(PartialEq, Eq)]
+pub enum VerifyResult <'a> {
+    /// The response matched the expected value.
+    Match,
+    /// The response did not match the expected value.
+    Mismatch,
+    /// The response was an `init-hex` or `init-word` response whose current
+    /// OTP matched the expected value. The server must decide whether to
+    /// re-initialize the sequence using the new parameters and `new_otp`.
+    Reinit {
+        /// The init response, with the parameters of the new sequence.
+        init: &'a OTPInit<'a>,
+        /// The decoded OTP value of the new sequence, at
+        /// [OTPInit::new_seq_num], which the server stores if it accepts
+        /// the new sequence.
+        new_otp: [u8; 8],
+    },
+}
+
+/// Verify a parsed OTP response against the `expected` OTP value, decoding
+/// words (case-insensitively) if necessary, and comparing in constant time.
+///
+/// If the response is an init response, its current OTP is verified, and, if it
+/// matches, [VerifyResult::Reinit] is returned with the decoded new OTP, so the
+/// server can decide how to handle re-initialization.
+///
+/// Returns an error if any words in the response could not be decoded.
+#[cfg(all(feature = "parsing-response", feature = "words"))]
+pub fn verify_response <'a> (
+    expected: &[u8; 8],
+    response: &'a OTPResponse<'a>,
+) -> Result<VerifyResult<'a>, WordDecodeError> {
+    let result = match response {
+        OTPResponse::Current(current) => current.decode().map(|current| {
+            if verify_otp_eq(expected, &current) {
+                VerifyResult::Match
+            } else {
+                VerifyResult::Mismatch
+            }
+        }),
+        OTPResponse::Init(init) => init.current_otp.decode()
+            .and_then(|current| Ok((current, init.new_otp.decode()?)))
+            .map(|(current, new_otp)| {
+                if verify_otp_eq(expected, &current) {
+                    VerifyResult::Reinit { init, new_otp }
+                } else {
+                    VerifyResult::Mismatch
+                }
+            }),
+    };
+    match &result {
+        Ok(VerifyResult::Match) => otp_event!(info, outcome = "match", "OTP verification succeeded"),
+        Ok(VerifyResult::Mismatch) => otp_event!(warn, outcome = "mismatch", "OTP verification failed"),
+        Ok(VerifyResult::Reinit { init, .. }) => otp_event!(
+            info,
+            outcome = "reinit",
+            new_alg = init.new_alg,
+            new_count = init.new_seq_num,
+            "OTP re-initialization accepted",
+        ),
+        Err(e) => otp_event!(warn, outcome = "undecodable", error = %e, "OTP verification failed"),
     }
+    result
 }
 
-/// Calculates the One-Time Pad using an arbitrary dynamic digest object
+/// Calculates the One-Time Pad using an arbitrary dynamic digest object.
+///
+/// This allows any digest that implements `digest::DynDigest` to be used, and
+/// allows a single digest instance to be reused across many calculations: the
+/// digest is reset before it is used.
+///
+/// Digests are folded to 64 bits as [fold_to_64_bits] folds those of
+/// `fold_alg`: per [fold_sha1] for [HashAlg::Sha1], and per [fold_md] for all
+/// others. Digests of algorithms that this library does not define should be
+/// folded as [HashAlg::Md5] is, unless they are meant to be folded as SHA-1 is.
+///
+/// Returns [CalculateError::UnsupportedDigestSize] if the digest's output is
+/// smaller than 8 bytes or larger than [MAX_DIGEST_SIZE].
 #[cfg(feature = "dyndig")]
-fn calculate_otp_via_digest (
+pub fn calculate_otp_with_digest (
     hasher: &mut dyn digest::DynDigest,
+    fold_alg: HashAlg,
     passphrase: &str,
-    seed: &str,
+    lowercased_seed: &str,
     count: usize,
-) -> [u8; 8] {
-    hasher.update(seed.as_bytes());
+) -> Result<[u8; 8], CalculateError> {
+    let output_size = hasher.output_size();
+    otp_span!("calculate_otp", digest_size = output_size, count);
+    let mut digest_bytes = [0u8; MAX_DIGEST_SIZE];
+    let digest_bytes = match digest_bytes.get_mut(0..output_size) {
+        Some(d) if output_size >= 8 => d,
+        _ => return Err(CalculateError::UnsupportedDigestSize(output_size)),
+    };
+    let mut otp = [0u8; 8];
+    hasher.reset();
+    hasher.update(lowercased_seed.as_bytes());
     hasher.update(passphrase.as_bytes());
-    let mut digest_bytes: [u8; 64] = [0; 64]; // Will accommodate a theoretical SHA-1024.
-    hasher.finalize_into_reset(digest_bytes.as_mut_slice()).expect("hash size too large");
-    fold_md(&mut digest_bytes);
-    let mut prev_hash = digest_bytes;
+    for i in 0..=count {
+        if i > 0 {
+            hasher.update(&otp);
+        }
+        hasher.finalize_into_reset(digest_bytes)
+            .map_err(|_| CalculateError::UnsupportedDigestSize(output_size))?;
+        fold_to_64_bits(fold_alg, digest_bytes);
+        otp.copy_from_slice(&digest_bytes[0..8]);
+    }
+    Ok(otp)
+}
+
+/// A hash function with which OTP values can be calculated: a [Digest],
+/// together with the [HashAlg] whose folding its digests follow.
+///
+/// This is implemented for the hash functions of each enabled algorithm, and
+/// may be implemented for others, which can then be used with
+/// [calculate_otp_with_hash].
+#[cfg(any(feature = "md4", feature = "md5", feature = "sha1", feature = "sha256", feature = "sha512"))]
+pub trait OtpHash: Digest + digest::FixedOutputReset + Sized {
+
+    /// The algorithm that this hash function implements. Its digests are
+    /// folded to 64 bits as [fold_to_64_bits] folds those of this algorithm.
+    const ALG: HashAlg;
+
+    /// Hash the concatenation of `parts`, and fold the digest to 64 bits.
+    fn hash_and_fold (parts: &[&[u8]]) -> [u8; 8] {
+        let mut hasher = Self::new();
+        for part in parts {
+            Digest::update(&mut hasher, part);
+        }
+        Self::finalize_and_fold_reset(&mut hasher)
+    }
+
+    /// Finish the digest of everything given to `hasher`, fold it to 64 bits,
+    /// and reset `hasher`, so that it can be reused without being set up
+    /// again.
+    fn finalize_and_fold_reset (hasher: &mut Self) -> [u8; 8] {
+        let mut digest_bytes = digest::Output::<Self>::default();
+        Digest::finalize_into_reset(hasher, &mut digest_bytes);
+        fold_to_64_bits(Self::ALG, &mut digest_bytes);
+        let mut out = [0u8; 8];
+        out.copy_from_slice(&digest_bytes[0..8]);
+        out
+    }
+
+}
+
+#[cfg(feature = "md4")]
+impl OtpHash for Md4 {
+    const ALG: HashAlg = HashAlg::Md4;
+}
+
+#[cfg(feature = "md5")]
+impl OtpHash for Md5 {
+    const ALG: HashAlg = HashAlg::Md5;
+}
+
+#[cfg(feature = "sha1")]
+impl OtpHash for Sha1 {
+    const ALG: HashAlg = HashAlg::Sha1;
+}
+
+#[cfg(feature = "sha256")]
+impl OtpHash for sha2::Sha256 {
+    const ALG: HashAlg = HashAlg::Sha256;
+}
+
+#[cfg(feature = "sha512")]
+impl OtpHash for sha2::Sha512 {
+    const ALG: HashAlg = HashAlg::Sha512;
+}
+
+/// Calculate an OTP value using the hash function `H`, with the seed already
+/// lowercased: the seed and passphrase are hashed and folded once, which is
+/// the value for count zero, and that is hashed and folded `count` more times.
+///
+/// Every algorithm-specific function, such as [calculate_md5_otp], is built on
+/// this. Unlike them, this does not limit `count`.
+#[cfg(any(feature = "md4", feature = "md5", feature = "sha1", feature = "sha256", feature = "sha512"))]
+pub fn calculate_otp_with_hash <H: OtpHash> (
+    passphrase: &str,
+    lowercased_seed: &str,
+    count: usize,
+) -> [u8; 8] {
+    // One hasher is reset and reused for every step, rather than set up anew,
+    // which matters most for low counts.
+    let mut hasher = H::new();
+    Digest::update(&mut hasher, lowercased_seed.as_bytes());
+    Digest::update(&mut hasher, passphrase.as_bytes());
+    let mut otp = H::finalize_and_fold_reset(&mut hasher);
     for _ in 0..count {
-        hasher.update(&prev_hash[0..hasher.output_size()]);
-        hasher.finalize_into_reset(digest_bytes.as_mut_slice()).expect("hash size too large");
-        fold_md(&mut digest_bytes[0..hasher.output_size()]);
-        prev_hash = digest_bytes;
-    }
-    [
-        prev_hash[0],
-        prev_hash[1],
-        prev_hash[2],
-        prev_hash[3],
-        prev_hash[4],
-        prev_hash[5],
-        prev_hash[6],
-        prev_hash[7],
-    ]
+        Digest::update(&mut hasher, otp);
+        otp = H::finalize_and_fold_reset(&mut hasher);
+    }
+    otp
 }
 
 /// Calculates the One-Time Pad using the `md4` algorithm.
@@ -761,29 +2495,7 @@ pub fn calculate_md4_otp (
     lowercased_seed: &str,
     count: usize,
 ) -> Option<[u8; 8]> {
-    let mut m = Md4::new();
-    m.update(lowercased_seed.as_bytes());
-    m.update(passphrase.as_bytes());
-    let mut digest_bytes = m.finalize();
-    fold_md(&mut digest_bytes);
-    let mut prev_hash = digest_bytes;
-    for _ in 0..count {
-        let mut m = Md4::new();
-        m.update(&prev_hash[0..8]);
-        let mut digest_bytes = m.finalize();
-        fold_md(&mut digest_bytes);
-        prev_hash = digest_bytes;
-    }
-    Some([
-        prev_hash[0],
-        prev_hash[1],
-        prev_hash[2],
-        prev_hash[3],
-        prev_hash[4],
-        prev_hash[5],
-        prev_hash[6],
-        prev_hash[7],
-    ])
+    Some(calculate_otp_with_hash::<Md4>(passphrase, lowercased_seed, count))
 }
 
 /// Calculates the One-Time Pad using the `md5` algorithm.
@@ -793,242 +2505,1425 @@ pub fn calculate_md5_otp (
     lowercased_seed: &str,
     count: usize,
 ) -> Option<[u8; 8]> {
-    let digest = md5::compute([
-        lowercased_seed.as_ref(),
-        passphrase,
-    ].concat());
-    let mut digest_bytes = digest.0;
-    fold_md(&mut digest_bytes);
-    let mut prev_hash = digest_bytes;
-    for _ in 0..count {
-        let mut digest_bytes = md5::compute(&prev_hash[0..8]).0;
-        fold_md(&mut digest_bytes);
-        prev_hash = digest_bytes;
-    }
-    Some([
-        prev_hash[0],
-        prev_hash[1],
-        prev_hash[2],
-        prev_hash[3],
-        prev_hash[4],
-        prev_hash[5],
-        prev_hash[6],
-        prev_hash[7],
-    ])
+    Some(calculate_otp_with_hash::<Md5>(passphrase, lowercased_seed, count))
+}
+
+/// Calculates the One-Time Pad using the `sha1` algorithm.
+#[cfg(feature = "sha1")]
+pub fn calculate_sha1_otp (
+    passphrase: &str,
+    lowercased_seed: &str,
+    count: usize,
+) -> Option<[u8; 8]> {
+    Some(calculate_otp_with_hash::<Sha1>(passphrase, lowercased_seed, count))
+}
+
+/// Calculates the One-Time Pad using the non-standard `x-sha256` algorithm.
+///
+/// The SHA-256 digest is folded to 64 bits per [fold_to_64_bits]: that is, by XORing
+/// each of its four 8-byte blocks together.
+#[cfg(feature = "sha256")]
+pub fn calculate_sha256_otp (
+    passphrase: &str,
+    lowercased_seed: &str,
+    count: usize,
+) -> Option<[u8; 8]> {
+    Some(calculate_otp_with_hash::<sha2::Sha256>(passphrase, lowercased_seed, count))
+}
+
+/// Calculates the One-Time Pad using the non-standard `x-sha512` algorithm.
+///
+/// The SHA-512 digest is folded to 64 bits per [fold_to_64_bits]: that is, by XORing
+/// each of its eight 8-byte blocks together.
+#[cfg(feature = "sha512")]
+pub fn calculate_sha512_otp (
+    passphrase: &str,
+    lowercased_seed: &str,
+    count: usize,
+) -> Option<[u8; 8]> {
+    Some(calculate_otp_with_hash::<sha2::Sha512>(passphrase, lowercased_seed, count))
+}
+
+/// A hash algorithm for use with the OTP algorithm.
+///
+/// Only [HashAlg::Md4], [HashAlg::Md5], and [HashAlg::Sha1] are registered
+/// with [IANA](https://www.iana.org/assignments/otp-parameters/otp-parameters.xhtml).
+/// The others are extensions defined by this library, and have names prefixed
+/// with `x-` so they cannot be mistaken for standard algorithms.
+///
+/// Every variant exists regardless of which feature flags are enabled, but OTP
+/// values can only be calculated using algorithms whose feature flags are.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum HashAlg {
+    /// MD4, named `md4`, per [IETF RFC 1320](https://www.rfc-editor.org/rfc/rfc1320).
+    Md4,
+    /// MD5, named `md5`, per [IETF RFC 1321](https://www.rfc-editor.org/rfc/rfc1321).
+    Md5,
+    /// SHA-1, named `sha1`, per [IETF RFC 3174](https://www.rfc-editor.org/rfc/rfc3174).
+    Sha1,
+    /// SHA-256, named `x-sha256`, which is not registered with IANA.
+    Sha256,
+    /// SHA-512, named `x-sha512`, which is not registered with IANA.
+    Sha512,
+}
+
+impl HashAlg {
+
+    /// Every hash algorithm, in order.
+    pub const ALL: [HashAlg; 5] = [
+        HashAlg::Md4,
+        HashAlg::Md5,
+        HashAlg::Sha1,
+        HashAlg::Sha256,
+        HashAlg::Sha512,
+    ];
+
+    /// The name of this algorithm, as used in OTP challenges and init
+    /// responses.
+    pub const fn name (&self) -> &'static str {
+        match self {
+            HashAlg::Md4 => "md4",
+            HashAlg::Md5 => "md5",
+            HashAlg::Sha1 => "sha1",
+            HashAlg::Sha256 => "x-sha256",
+            HashAlg::Sha512 => "x-sha512",
+        }
+    }
+
+    /// Get the algorithm that has the given name, including the non-standard
+    /// algorithms defined by this library.
+    ///
+    /// Use [HashAlg::from_name_strict] to only recognize standard algorithms.
+    pub fn from_name (name: &str) -> Option<HashAlg> {
+        match name {
+            "md4" => Some(HashAlg::Md4),
+            "md5" => Some(HashAlg::Md5),
+            "sha1" => Some(HashAlg::Sha1),
+            "x-sha256" => Some(HashAlg::Sha256),
+            "x-sha512" => Some(HashAlg::Sha512),
+            _ => None,
+        }
+    }
+
+    /// Get the algorithm that has the given name, only recognizing those that
+    /// are registered with IANA.
+    pub fn from_name_strict (name: &str) -> Option<HashAlg> {
+        HashAlg::from_name(name).filter(HashAlg::is_iana_registered)
+    }
+
+    /// Whether this algorithm is registered with IANA for use with OTP.
+    pub const fn is_iana_registered (&self) -> bool {
+        matches!(self, HashAlg::Md4 | HashAlg::Md5 | HashAlg::Sha1)
+    }
+
+    /// Whether this algorithm is deprecated: MD4 and MD5 are no longer
+    /// considered secure, and SHA1 should be used instead.
+    pub const fn is_deprecated (&self) -> bool {
+        matches!(self, HashAlg::Md4 | HashAlg::Md5)
+    }
+
+    /// Whether the feature flag for this algorithm is enabled, so that OTP
+    /// values can be calculated using it.
+    pub const fn is_enabled (&self) -> bool {
+        match self {
+            HashAlg::Md4 => cfg!(feature = "md4"),
+            HashAlg::Md5 => cfg!(feature = "md5"),
+            HashAlg::Sha1 => cfg!(feature = "sha1"),
+            HashAlg::Sha256 => cfg!(feature = "sha256"),
+            HashAlg::Sha512 => cfg!(feature = "sha512"),
+        }
+    }
+
+}
+
+impl core::fmt::Display for HashAlg {
+
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(self.name())
+    }
+
+}
+
+/// A function that takes a digest name and returns a corresponding
+/// `DynDigest`, if the name is recognized.
+#[cfg(feature = "dyndig")]
+pub type DigestGetter = fn(&str) -> Option<Box<dyn digest::DynDigest>>;
+
+/// The minimum length of a passphrase, in characters, recommended by Section
+/// 6.0 of [IETF RFC 2289](https://www.rfc-editor.org/rfc/rfc2289.html).
+pub const MIN_PASSPHRASE_LEN: usize = 10;
+
+/// The maximum length of a passphrase, in characters, that all OTP generators
+/// must support, per Section 6.0 of
+/// [IETF RFC 2289](https://www.rfc-editor.org/rfc/rfc2289.html). Longer
+/// passphrases may not work with all generators.
+pub const MAX_PASSPHRASE_LEN: usize = 63;
+
+/// The minimum number of distinct characters in a passphrase below which
+/// [check_passphrase] considers it to lack diversity.
+const MIN_DISTINCT_PASSPHRASE_CHARS: usize = 5;
+
+/// The result of checking the quality of a passphrase using
+/// [check_passphrase].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PassphraseCheck {
+    /// The length of the passphrase, in characters.
+    pub length: usize,
+    /// The number of distinct characters in the passphrase.
+    pub distinct_chars: usize,
+    /// The number of classes of characters (lowercase letters, uppercase
+    /// letters, digits, and everything else) used in the passphrase.
+    pub char_classes: u8,
+    /// The passphrase is shorter than [MIN_PASSPHRASE_LEN].
+    pub too_short: bool,
+    /// The passphrase is longer than [MAX_PASSPHRASE_LEN], and so may not work
+    /// with all OTP generators.
+    pub too_long: bool,
+    /// The passphrase uses very few distinct characters.
+    pub low_diversity: bool,
+    /// The passphrase is the same as the seed, ignoring case.
+    pub matches_seed: bool,
+}
+
+impl PassphraseCheck {
+
+    /// Returns `true` if no problems were found with the passphrase.
+    pub fn is_acceptable (&self) -> bool {
+        !self.too_short && !self.too_long && !self.low_diversity && !self.matches_seed
+    }
+
+}
+
+/// Check the quality of a passphrase that is to be used with `seed`, so that
+/// clients can reject trivially weak passphrases, as recommended by Section
+/// 6.0 of [IETF RFC 2289](https://www.rfc-editor.org/rfc/rfc2289.html).
+///
+/// This evaluates its length, the diversity of its characters, and whether it
+/// is the same as the seed. It does not allocate.
+pub fn check_passphrase (passphrase: &str, seed: &str) -> PassphraseCheck {
+    let mut ascii_seen: u128 = 0;
+    let mut non_ascii_chars: usize = 0;
+    let mut classes: u8 = 0;
+    let mut length: usize = 0;
+    for c in passphrase.chars() {
+        length += 1;
+        if c.is_ascii() {
+            ascii_seen |= 1 << (c as u32);
+        } else {
+            non_ascii_chars += 1;
+        }
+        classes |= if c.is_ascii_lowercase() {
+            0b0001
+        } else if c.is_ascii_uppercase() {
+            0b0010
+        } else if c.is_ascii_digit() {
+            0b0100
+        } else {
+            0b1000
+        };
+    }
+    // Non-ASCII characters are assumed to be distinct, to avoid allocating.
+    let distinct_chars = ascii_seen.count_ones() as usize + non_ascii_chars;
+    PassphraseCheck {
+        length,
+        distinct_chars,
+        char_classes: classes.count_ones() as u8,
+        too_short: length < MIN_PASSPHRASE_LEN,
+        too_long: length > MAX_PASSPHRASE_LEN,
+        low_diversity: distinct_chars < MIN_DISTINCT_PASSPHRASE_CHARS,
+        matches_seed: passphrase.eq_ignore_ascii_case(seed),
+    }
+}
+
+/// Normalize a passphrase to Unicode Normalization Form KC (NFKC), so that
+/// passphrases that look the same produce the same OTP values, regardless of
+/// whether, for instance, accented characters were entered as one code point
+/// or two. This does not allocate if the passphrase is already normalized.
+///
+/// When the `nfkc` feature is enabled, [calculate_otp],
+/// [calculate_otp_with_options], [calculate_otp_with_alg], and
+/// [calculate_otp_multi] do this before hashing the passphrase. Note that
+/// peers that do not normalize passphrases will calculate different OTP values
+/// for passphrases that are not already normalized.
+#[cfg(feature = "nfkc")]
+pub fn normalize_passphrase (passphrase: &str) -> Cow<'_, str> {
+    use unicode_normalization::{is_nfkc_quick, IsNormalized, UnicodeNormalization};
+    match is_nfkc_quick(passphrase.chars()) {
+        IsNormalized::Yes => Cow::Borrowed(passphrase),
+        _ => Cow::Owned(passphrase.nfkc().collect()),
+    }
+}
+
+/// The default maximum hash count that will be calculated by [calculate_otp]
+/// and [calculate_otp_with_options]. This is far larger than the hash count
+/// that any legitimate OTP sequence will use, but small enough that a malicious
+/// server cannot make a client hash for minutes.
+pub const DEFAULT_MAX_COUNT: usize = 1 << 20;
+
+/// A seed, lowercased for hashing. Seeds containing uppercase letters are
+/// lowercased on the stack if they are no longer than [MAX_SEED_LEN], and, with
+/// the `alloc` feature, on the heap otherwise, as they may be if
+/// [CalculateOptions::max_seed_len] is raised.
+enum LowercasedSeed <'a> {
+    Borrowed(&'a str),
+    Inline([u8; MAX_SEED_LEN], usize),
+    #[cfg(feature = "alloc")]
+    Owned(String),
+}
+
+impl <'a> LowercasedSeed<'a> {
+
+    /// Lowercase `seed`. Without the `alloc` feature, this returns `None` if
+    /// it contains uppercase letters and is longer than [MAX_SEED_LEN] bytes.
+    fn new (seed: &'a str) -> Option<Self> {
+        if !seed.bytes().any(|b| b.is_ascii_uppercase()) {
+            return Some(LowercasedSeed::Borrowed(seed));
+        }
+        if seed.len() <= MAX_SEED_LEN {
+            let mut bytes = [0u8; MAX_SEED_LEN];
+            bytes[..seed.len()].copy_from_slice(seed.as_bytes());
+            bytes[..seed.len()].make_ascii_lowercase();
+            return Some(LowercasedSeed::Inline(bytes, seed.len()));
+        }
+        #[cfg(feature = "alloc")]
+        return Some(LowercasedSeed::Owned(seed.to_ascii_lowercase()));
+        #[cfg(not(feature = "alloc"))]
+        None
+    }
+
+}
+
+impl AsRef<str> for LowercasedSeed<'_> {
+
+    fn as_ref(&self) -> &str {
+        match self {
+            LowercasedSeed::Borrowed(s) => s,
+            // Lowercasing ASCII letters in valid UTF-8 leaves it valid.
+            LowercasedSeed::Inline(bytes, len) => core::str::from_utf8(&bytes[..*len]).unwrap_or_default(),
+            #[cfg(feature = "alloc")]
+            LowercasedSeed::Owned(s) => s,
+        }
+    }
+
+}
+
+/// The largest digest, in bytes, that [calculate_otp_with_digest] accepts.
+/// This will accommodate a theoretical SHA-1024.
+#[cfg(feature = "dyndig")]
+pub const MAX_DIGEST_SIZE: usize = 64;
+
+/// An error calculating an OTP value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum CalculateError {
+    /// The hash algorithm is not understood, or its feature flag is not
+    /// enabled.
+    UnsupportedAlgorithm,
+    /// The hash count exceeds the maximum. Contains the maximum.
+    CountTooLarge(usize),
+    /// The seed is longer than [CalculateOptions::max_seed_len], or, without
+    /// the `alloc` feature, it is longer than [MAX_SEED_LEN] bytes and
+    /// contains uppercase letters, so it cannot be lowercased.
+    SeedTooLong,
+    /// A digest supplied via the `dyndig` feature has an output size, which
+    /// this contains, that is smaller than 8 bytes or larger than
+    /// `MAX_DIGEST_SIZE`.
+    UnsupportedDigestSize(usize),
+}
+
+impl core::fmt::Display for CalculateError {
+
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            CalculateError::UnsupportedAlgorithm => f.write_str("unsupported hash algorithm"),
+            CalculateError::CountTooLarge(max) => write!(f, "hash count exceeds the maximum of {}", max),
+            CalculateError::SeedTooLong => f.write_str("seed too long"),
+            CalculateError::UnsupportedDigestSize(size) => write!(f, "unsupported digest size of {} bytes", size),
+        }
+    }
+
+}
+
+/// Options for [calculate_otp_with_options].
+#[derive(Debug, Clone, Copy)]
+pub struct CalculateOptions {
+    /// The largest hash count that will be calculated. Larger counts are
+    /// rejected before any hashing begins. Defaults to [DEFAULT_MAX_COUNT].
+    pub max_count: usize,
+    /// The longest seed, in bytes, that will be hashed. Defaults to
+    /// [MAX_SEED_LEN], since a compliant peer could not match the OTP values
+    /// of a longer seed. Set this to `usize::MAX` for interoperability testing
+    /// against non-conforming implementations. Without the `alloc` feature,
+    /// longer seeds must be lowercase, since they cannot be lowercased without
+    /// allocating.
+    pub max_seed_len: usize,
+    /// A function that takes a digest name and returns a corresponding
+    /// `DynDigest`, used for algorithms that are not built in.
+    #[cfg(feature = "dyndig")]
+    pub get_digest: Option<DigestGetter>,
+}
+
+impl Default for CalculateOptions {
+
+    fn default() -> Self {
+        CalculateOptions {
+            max_count: DEFAULT_MAX_COUNT,
+            max_seed_len: MAX_SEED_LEN,
+            #[cfg(feature = "dyndig")]
+            get_digest: None,
+        }
+    }
+
+}
+
+/// Calculate an OTP value from supplied parameters, per Section 6.0 of
+/// [IETF RFC 2289](https://www.rfc-editor.org/rfc/rfc2289.html).
+///
+/// Returns `None` if the algorithm is not understood, if `count` exceeds
+/// [DEFAULT_MAX_COUNT], or if the seed is longer than [MAX_SEED_LEN]. Use
+/// [calculate_otp_with_options] to find out which, or to change the maximum.
+///
+/// The `maybe_get_digest` function is a function that takes a digest name and
+/// returns a corresponding `DynDigest`. This is so the types of hash algorithms
+/// supported can be extended. This argument is only present if the `dyndig`
+/// feature flag is enabled.
+pub fn calculate_otp (
+    hash_alg: &str,
+    passphrase: &str,
+    seed: &str,
+    count: usize,
+    #[cfg(feature = "dyndig")]
+    maybe_get_digest: Option<DigestGetter>,
+) -> Option<[u8; 8]> {
+    let options = CalculateOptions {
+        #[cfg(feature = "dyndig")]
+        get_digest: maybe_get_digest,
+        ..Default::default()
+    };
+    calculate_otp_with_options(hash_alg, passphrase, seed, count, &options).ok()
+}
+
+/// Calculate an OTP value from supplied parameters, per Section 6.0 of
+/// [IETF RFC 2289](https://www.rfc-editor.org/rfc/rfc2289.html), returning
+/// an error describing why it could not be calculated, if it could not.
+pub fn calculate_otp_with_options (
+    hash_alg: &str,
+    passphrase: &str,
+    seed: &str,
+    count: usize,
+    options: &CalculateOptions,
+) -> Result<[u8; 8], CalculateError> {
+    if count > options.max_count {
+        return Err(CalculateError::CountTooLarge(options.max_count));
+    }
+    if seed.len() > options.max_seed_len {
+        return Err(CalculateError::SeedTooLong);
+    }
+    let lowercased_seed = LowercasedSeed::new(seed).ok_or(CalculateError::SeedTooLong)?;
+    #[cfg(feature = "nfkc")]
+    let passphrase = normalize_passphrase(passphrase);
+    #[cfg(feature = "nfkc")]
+    let passphrase = passphrase.as_ref();
+    let alg = HashAlg::from_name(hash_alg);
+    let otp = alg.and_then(|alg| calculate_lowercased_otp_with_alg(alg, passphrase, lowercased_seed.as_ref(), count));
+    #[cfg(feature = "dyndig")]
+    if otp.is_none() {
+        if let Some(mut digest) = options.get_digest.and_then(|get_digest| get_digest(hash_alg)) {
+            // Algorithms that this library does not define are folded as its
+            // own non-standard algorithms are.
+            let fold_alg = alg.unwrap_or(HashAlg::Md5);
+            return calculate_otp_with_digest(digest.as_mut(), fold_alg, passphrase, lowercased_seed.as_ref(), count);
+        }
+    }
+    otp.ok_or(CalculateError::UnsupportedAlgorithm)
+}
+
+/// Calculate an OTP value from supplied parameters using the given algorithm,
+/// per Section 6.0 of [IETF RFC 2289](https://www.rfc-editor.org/rfc/rfc2289.html).
+///
+/// Returns `None` if the feature flag for the algorithm is not enabled, if
+/// `count` exceeds [DEFAULT_MAX_COUNT], or if the seed is longer than
+/// [MAX_SEED_LEN].
+pub fn calculate_otp_with_alg (
+    alg: HashAlg,
+    passphrase: &str,
+    seed: &str,
+    count: usize,
+) -> Option<[u8; 8]> {
+    if count > DEFAULT_MAX_COUNT || seed.len() > MAX_SEED_LEN {
+        return None;
+    }
+    let lowercased_seed = LowercasedSeed::new(seed)?;
+    #[cfg(feature = "nfkc")]
+    let passphrase = normalize_passphrase(passphrase);
+    #[cfg(feature = "nfkc")]
+    let passphrase = passphrase.as_ref();
+    calculate_lowercased_otp_with_alg(alg, passphrase, lowercased_seed.as_ref(), count)
+}
+
+/// Calculate the first step of an OTP sequence: the hash of the lowercased
+/// seed followed by the passphrase, folded to 64 bits, per Section 6.0 of
+/// [IETF RFC 2289](https://www.rfc-editor.org/rfc/rfc2289.html). This is the
+/// OTP value for count zero, from which the value for any other count follows
+/// by [advance_otp], so it is useful for caching checkpoints of a sequence.
+///
+/// Returns `None` if the feature flag for the algorithm is not enabled.
+///
+/// ```rust
+/// use rfc2289_otp::{advance_otp, calculate_otp_with_alg, initial_step, HashAlg};
+/// # #[cfg(feature = "md5")] {
+/// let first = initial_step(HashAlg::Md5, "This is a test.", "TeSt").unwrap();
+/// assert_eq!(
+///     advance_otp(HashAlg::Md5, &first, 99),
+///     calculate_otp_with_alg(HashAlg::Md5, "This is a test.", "TeSt", 99),
+/// );
+/// # }
+/// ```
+pub fn initial_step (alg: HashAlg, passphrase: &str, seed: &str) -> Option<[u8; 8]> {
+    calculate_otp_with_alg(alg, passphrase, seed, 0)
+}
+
+/// Calculate an OTP value using the given algorithm, with the seed already
+/// lowercased.
+// With no hash algorithms enabled, every arm of the match is compiled out.
+#[cfg_attr(
+    not(any(feature = "md4", feature = "md5", feature = "sha1", feature = "sha256", feature = "sha512")),
+    allow(unused_variables),
+)]
+fn calculate_lowercased_otp_with_alg (
+    alg: HashAlg,
+    passphrase: &str,
+    lowercased_seed: &str,
+    count: usize,
+) -> Option<[u8; 8]> {
+    otp_span!("calculate_otp", alg = alg.name(), count);
+    match alg {
+        #[cfg(feature = "md4")]
+        HashAlg::Md4 => calculate_md4_otp(passphrase, lowercased_seed, count),
+        #[cfg(feature = "md5")]
+        HashAlg::Md5 => calculate_md5_otp(passphrase, lowercased_seed, count),
+        #[cfg(feature = "sha1")]
+        HashAlg::Sha1 => calculate_sha1_otp(passphrase, lowercased_seed, count),
+        #[cfg(feature = "sha256")]
+        HashAlg::Sha256 => calculate_sha256_otp(passphrase, lowercased_seed, count),
+        #[cfg(feature = "sha512")]
+        HashAlg::Sha512 => calculate_sha512_otp(passphrase, lowercased_seed, count),
+        #[allow(unreachable_patterns)]
+        _ => None,
+    }
+}
+
+/// Hash and fold an OTP value once more, which yields the OTP value for the
+/// next higher count. Returns `None` if the feature flag for `alg` is not
+/// enabled.
+// With no hash algorithms enabled, every arm of the match is compiled out.
+#[cfg_attr(
+    not(any(feature = "md4", feature = "md5", feature = "sha1", feature = "sha256", feature = "sha512")),
+    allow(unused_variables),
+)]
+pub(crate) fn next_otp (alg: HashAlg, otp: &[u8; 8]) -> Option<[u8; 8]> {
+    match alg {
+        #[cfg(feature = "md4")]
+        HashAlg::Md4 => Some(Md4::hash_and_fold(&[ otp ])),
+        #[cfg(feature = "md5")]
+        HashAlg::Md5 => Some(Md5::hash_and_fold(&[ otp ])),
+        #[cfg(feature = "sha1")]
+        HashAlg::Sha1 => Some(Sha1::hash_and_fold(&[ otp ])),
+        #[cfg(feature = "sha256")]
+        HashAlg::Sha256 => Some(sha2::Sha256::hash_and_fold(&[ otp ])),
+        #[cfg(feature = "sha512")]
+        HashAlg::Sha512 => Some(sha2::Sha512::hash_and_fold(&[ otp ])),
+        #[allow(unreachable_patterns)]
+        _ => None,
+    }
+}
+
+/// Hash and fold an existing OTP value `steps` times, which yields the OTP
+/// value of the same sequence at a count `steps` higher.
+///
+/// Since each OTP value is the hash of the one for the count below it, this
+/// can be used to check that an OTP value at one count precedes another at a
+/// higher count, without knowing the passphrase. Returns `None` if the feature
+/// flag for `alg` is not enabled.
+///
+/// This takes time proportional to `steps`, so callers should bound it, such
+/// as by [DEFAULT_MAX_COUNT], if it comes from an untrusted source.
+pub fn advance_otp (alg: HashAlg, otp: &[u8; 8], steps: usize) -> Option<[u8; 8]> {
+    otp_span!("advance_otp", alg = alg.name(), steps);
+    let mut otp = *otp;
+    for _ in 0..steps {
+        otp = next_otp(alg, &otp)?;
+    }
+    if steps == 0 && !alg.is_enabled() {
+        return None;
+    }
+    Some(otp)
+}
+
+/// Check whether two OTP values, at the given counts, belong to the same
+/// sequence: that is, whether hashing the one with the lower count forward
+/// yields the other. The final comparison is made in constant time.
+///
+/// This is useful for auditing imported OTP databases, or for checking that a
+/// client's response follows from a previously accepted one. Returns `None`
+/// if the feature flag for `alg` is not enabled, or if the counts differ by
+/// more than [DEFAULT_MAX_COUNT].
+pub fn same_chain (
+    alg: HashAlg,
+    otp_a: &[u8; 8],
+    count_a: usize,
+    otp_b: &[u8; 8],
+    count_b: usize,
+) -> Option<bool> {
+    let (lower, higher, steps) = if count_a <= count_b {
+        (otp_a, otp_b, count_b - count_a)
+    } else {
+        (otp_b, otp_a, count_a - count_b)
+    };
+    if steps > DEFAULT_MAX_COUNT {
+        return None;
+    }
+    let advanced = advance_otp(alg, lower, steps)?;
+    Some(verify_otp_eq(&advanced, higher))
 }
 
-/// Calculates the One-Time Pad using the `sha1` algorithm.
-#[cfg(feature = "sha1")]
-pub fn calculate_sha1_otp (
-    passphrase: &str,
-    lowercased_seed: &str,
-    count: usize,
-) -> Option<[u8; 8]> {
-    let mut m = sha1_smol::Sha1::new();
-    m.update(lowercased_seed.as_bytes());
-    m.update(passphrase.as_bytes());
-    let mut digest_bytes = m.digest().bytes();
-    fold_sha1(&mut digest_bytes);
-    let mut prev_hash = digest_bytes;
-    for _ in 0..count {
-        let mut m = sha1_smol::Sha1::new();
-        m.update(&prev_hash[0..8]);
-        let mut digest_bytes = m.digest().bytes();
-        fold_sha1(&mut digest_bytes);
-        prev_hash = digest_bytes;
-    }
-    Some([
-        prev_hash[0],
-        prev_hash[1],
-        prev_hash[2],
-        prev_hash[3],
-        prev_hash[4],
-        prev_hash[5],
-        prev_hash[6],
-        prev_hash[7],
-    ])
+/// OTP values for the same passphrase, seed, and count, calculated under
+/// multiple algorithms by [calculate_otp_multi].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MultiOtp {
+    otps: [Option<[u8; 8]>; HashAlg::ALL.len()],
 }
 
-/// Calculate an OTP value from supplied parameters, per Section 6.0 of
-/// [IETF RFC 2289](https://www.rfc-editor.org/rfc/rfc2289.html).
+impl MultiOtp {
+
+    /// Get the OTP value calculated using `alg`, if it was.
+    pub fn get (&self, alg: HashAlg) -> Option<[u8; 8]> {
+        self.otps[alg as usize]
+    }
+
+    /// Iterate over the algorithms and the OTP values calculated using them.
+    pub fn iter (&self) -> impl Iterator<Item = (HashAlg, [u8; 8])> + '_ {
+        HashAlg::ALL
+            .iter()
+            .zip(self.otps.iter())
+            .filter_map(|(alg, otp)| Some((*alg, (*otp)?)))
+    }
+
+}
+
+/// Calculate OTP values for the same passphrase, seed, and count under each of
+/// the given algorithms, such as when migrating users from one algorithm to
+/// another.
 ///
-/// Returns `None` if the algorithm is not understood.
-/// 
-/// The `maybe_get_digest` function is a function that takes a digest name and
-/// returns a corresponding `DynDigest`. This is so the types of hash algorithms
-/// supported can be extended. This argument is only present if the `dyndig`
-/// feature flag is enabled.
-pub fn calculate_otp (
-    hash_alg: &str,
+/// Algorithms whose feature flags are not enabled are omitted from the output.
+/// Returns [CalculateError::CountTooLarge] if `count` exceeds
+/// [DEFAULT_MAX_COUNT], or [CalculateError::SeedTooLong] if the seed is longer
+/// than [MAX_SEED_LEN].
+pub fn calculate_otp_multi (
+    algs: &[HashAlg],
     passphrase: &str,
     seed: &str,
     count: usize,
-    #[cfg(feature = "dyndig")]
-    maybe_get_digest: Option<fn(&str) -> Option<Box<dyn digest::DynDigest>>>,
-) -> Option<[u8; 8]> {
-    let lowercased_seed = seed.cow_to_ascii_lowercase();
-    match hash_alg {
-        #[cfg(feature = "md4")]
-        "md4" => calculate_md4_otp(passphrase, lowercased_seed.as_ref(), count),
-        #[cfg(feature = "md5")]
-        "md5" => calculate_md5_otp(passphrase, lowercased_seed.as_ref(), count),
-        #[cfg(feature = "sha1")]
-        "sha1" => calculate_sha1_otp(passphrase, lowercased_seed.as_ref(), count),
-        #[cfg(feature = "dyndig")]
-        _ => {
-            let get_digest = maybe_get_digest?;
-            let mut digest = get_digest(hash_alg)?;
-            Some(calculate_otp_via_digest(digest.as_mut(), passphrase, lowercased_seed.as_ref(), count))
-        },
-        #[cfg(not(feature = "dyndig"))]
-        _ => {
-            None
-        }
+) -> Result<MultiOtp, CalculateError> {
+    otp_span!("calculate_otp_multi", algs = algs.len(), count);
+    if count > DEFAULT_MAX_COUNT {
+        return Err(CalculateError::CountTooLarge(DEFAULT_MAX_COUNT));
+    }
+    if seed.len() > MAX_SEED_LEN {
+        return Err(CalculateError::SeedTooLong);
     }
+    let lowercased_seed = LowercasedSeed::new(seed).ok_or(CalculateError::SeedTooLong)?;
+    let mut output = MultiOtp::default();
+    #[cfg(feature = "nfkc")]
+    let passphrase = normalize_passphrase(passphrase);
+    #[cfg(feature = "nfkc")]
+    let passphrase = passphrase.as_ref();
+    for alg in algs {
+        output.otps[*alg as usize] = calculate_lowercased_otp_with_alg(*alg, passphrase, lowercased_seed.as_ref(), count);
+    }
+    Ok(output)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    // TODO: Test folding
-
-    type TestCase = (&'static str, &'static str, usize, [u8; 8], &'static str);
-
-    const OFFICIAL_MD4_TEST_CASES: [TestCase; 9] = [
-        ("This is a test.", "TeSt",      0, [ 0xD1, 0x85, 0x42, 0x18, 0xEB, 0xBB, 0x0B, 0x51 ], "ROME MUG FRED SCAN LIVE LACE"),
-        ("This is a test.", "TeSt",      1, [ 0x63, 0x47, 0x3E, 0xF0, 0x1C, 0xD0, 0xB4, 0x44 ], "CARD SAD MINI RYE COL KIN"),
-        ("This is a test.", "TeSt",     99, [ 0xC5, 0xE6, 0x12, 0x77, 0x6E, 0x6C, 0x23, 0x7A ], "NOTE OUT IBIS SINK NAVE MODE"),
-        ("AbCdEfGhIjK", "alpha1",        0, [ 0x50, 0x07, 0x6F, 0x47, 0xEB, 0x1A, 0xDE, 0x4E ], "AWAY SEN ROOK SALT LICE MAP"),
-        ("AbCdEfGhIjK", "alpha1",        1, [ 0x65, 0xD2, 0x0D, 0x19, 0x49, 0xB5, 0xF7, 0xAB ], "CHEW GRIM WU HANG BUCK SAID"),
-        ("AbCdEfGhIjK", "alpha1",       99, [ 0xD1, 0x50, 0xC8, 0x2C, 0xCE, 0x6F, 0x62, 0xD1 ], "ROIL FREE COG HUNK WAIT COCA"),
-        ("OTP's are good", "correct",    0, [ 0x84, 0x9C, 0x79, 0xD4, 0xF6, 0xF5, 0x53, 0x88 ], "FOOL STEM DONE TOOL BECK NILE"),
-        ("OTP's are good", "correct",    1, [ 0x8C, 0x09, 0x92, 0xFB, 0x25, 0x08, 0x47, 0xB1 ], "GIST AMOS MOOT AIDS FOOD SEEM"),
-        ("OTP's are good", "correct",   99, [ 0x3F, 0x3B, 0xF4, 0xB4, 0x14, 0x5F, 0xD7, 0x4B ], "TAG SLOW NOV MIN WOOL KENO"),
-    ];
-
-    const OFFICIAL_MD5_TEST_CASES: [TestCase; 9] = [
-        ("This is a test.", "TeSt",      0, [ 0x9E, 0x87, 0x61, 0x34, 0xD9, 0x04, 0x99, 0xDD ], "INCH SEA ANNE LONG AHEM TOUR"),
-        ("This is a test.", "TeSt",      1, [ 0x79, 0x65, 0xE0, 0x54, 0x36, 0xF5, 0x02, 0x9F ], "EASE OIL FUM CURE AWRY AVIS"),
-        ("This is a test.", "TeSt",     99, [ 0x50, 0xFE, 0x19, 0x62, 0xC4, 0x96, 0x58, 0x80 ], "BAIL TUFT BITS GANG CHEF THY"),
-        ("AbCdEfGhIjK", "alpha1",        0, [ 0x87, 0x06, 0x6D, 0xD9, 0x64, 0x4B, 0xF2, 0x06 ], "FULL PEW DOWN ONCE MORT ARC"),
-        ("AbCdEfGhIjK", "alpha1",        1, [ 0x7C, 0xD3, 0x4C, 0x10, 0x40, 0xAD, 0xD1, 0x4B ], "FACT HOOF AT FIST SITE KENT"),
-        ("AbCdEfGhIjK", "alpha1",       99, [ 0x5A, 0xA3, 0x7A, 0x81, 0xF2, 0x12, 0x14, 0x6C ], "BODE HOP JAKE STOW JUT RAP"),
-        ("OTP's are good", "correct",    0, [ 0xF2, 0x05, 0x75, 0x39, 0x43, 0xDE, 0x4C, 0xF9 ], "ULAN NEW ARMY FUSE SUIT EYED"),
-        ("OTP's are good", "correct",    1, [ 0xDD, 0xCD, 0xAC, 0x95, 0x6F, 0x23, 0x49, 0x37 ], "SKIM CULT LOB SLAM POE HOWL"),
-        ("OTP's are good", "correct",   99, [ 0xB2, 0x03, 0xE2, 0x8F, 0xA5, 0x25, 0xBE, 0x47 ], "LONG IVY JULY AJAR BOND LEE"),
-    ];
+    #[allow(unused_imports)]
+    use crate::test_vectors;
 
-    const OFFICIAL_SHA1_TEST_CASES: [TestCase; 9] = [
-        ("This is a test.", "TeSt",      0, [ 0xBB, 0x9E, 0x6A, 0xE1, 0x97, 0x9D, 0x8F, 0xF4 ], "MILT VARY MAST OK SEES WENT"),
-        ("This is a test.", "TeSt",      1, [ 0x63, 0xD9, 0x36, 0x63, 0x97, 0x34, 0x38, 0x5B ], "CART OTTO HIVE ODE VAT NUT"),
-        ("This is a test.", "TeSt",     99, [ 0x87, 0xFE, 0xC7, 0x76, 0x8B, 0x73, 0xCC, 0xF9 ], "GAFF WAIT SKID GIG SKY EYED"),
-        ("AbCdEfGhIjK", "alpha1",        0, [ 0xAD, 0x85, 0xF6, 0x58, 0xEB, 0xE3, 0x83, 0xC9 ], "LEST OR HEEL SCOT ROB SUIT"),
-        ("AbCdEfGhIjK", "alpha1",        1, [ 0xD0, 0x7C, 0xE2, 0x29, 0xB5, 0xCF, 0x11, 0x9B ], "RITE TAKE GELD COST TUNE RECK"),
-        ("AbCdEfGhIjK", "alpha1",       99, [ 0x27, 0xBC, 0x71, 0x03, 0x5A, 0xAF, 0x3D, 0xC6 ], "MAY STAR TIN LYON VEDA STAN"),
-        ("OTP's are good", "correct",    0, [ 0xD5, 0x1F, 0x3E, 0x99, 0xBF, 0x8E, 0x6F, 0x0B ], "RUST WELT KICK FELL TAIL FRAU"),
-        ("OTP's are good", "correct",    1, [ 0x82, 0xAE, 0xB5, 0x2D, 0x94, 0x37, 0x74, 0xE4 ], "FLIT DOSE ALSO MEW DRUM DEFY"),
-        ("OTP's are good", "correct",   99, [ 0x4F, 0x29, 0x6A, 0x74, 0xFE, 0x15, 0x67, 0xEC ], "AURA ALOE HURL WING BERG WAIT"),
-    ];
+    #[test]
+    #[cfg(all(feature = "md4", feature = "words", feature = "dyndig"))]
+    fn passes_official_md4_test_cases() {
+        for test_case in test_vectors::MD4 {
+            let otp = calculate_otp("md4", test_case.passphrase, test_case.seed, test_case.count, None).unwrap();
+            assert_eq!(otp, test_case.otp);
+            let words = convert_to_word_format(&otp);
+            assert_eq!(words.join(" "), test_case.words);
+        }
+    }
 
     #[test]
     #[cfg(all(feature = "md4", feature = "words"))]
-    fn passes_official_md4_test_cases() {
-        for test_case in OFFICIAL_MD4_TEST_CASES {
-            let otp = calculate_otp("md4", test_case.0, test_case.1, test_case.2, None).unwrap();
-            assert_eq!(otp, test_case.3);
+    fn passes_official_md4_test_cases_with_options() {
+        for test_case in test_vectors::MD4 {
+            let otp = calculate_otp_with_options("md4", test_case.passphrase, test_case.seed, test_case.count, &CalculateOptions::default()).unwrap();
+            assert_eq!(otp, test_case.otp);
             let words = convert_to_word_format(&otp);
-            assert_eq!(words.join(" "), test_case.4);
+            assert_eq!(words.join(" "), test_case.words);
         }
     }
 
     #[test]
-    #[cfg(all(feature = "md5", feature = "words"))]
+    #[cfg(all(feature = "md5", feature = "words", feature = "dyndig"))]
     fn passes_official_md5_test_cases() {
-        for test_case in OFFICIAL_MD5_TEST_CASES {
-            let otp = calculate_otp("md5", test_case.0, test_case.1, test_case.2, None).unwrap();
-            assert_eq!(otp, test_case.3);
+        for test_case in test_vectors::MD5 {
+            let otp = calculate_otp("md5", test_case.passphrase, test_case.seed, test_case.count, None).unwrap();
+            assert_eq!(otp, test_case.otp);
             let words = convert_to_word_format(&otp);
-            assert_eq!(words.join(" "), test_case.4);
+            assert_eq!(words.join(" "), test_case.words);
         }
     }
 
     #[test]
-    #[cfg(all(feature = "sha1", feature = "words"))]
+    #[cfg(all(feature = "md5", feature = "words"))]
+    fn passes_official_md5_test_cases_with_options() {
+        for test_case in test_vectors::MD5 {
+            let otp = calculate_otp_with_options("md5", test_case.passphrase, test_case.seed, test_case.count, &CalculateOptions::default()).unwrap();
+            assert_eq!(otp, test_case.otp);
+            let words = convert_to_word_format(&otp);
+            assert_eq!(words.join(" "), test_case.words);
+        }
+    }
+
+    #[test]
+    #[cfg(all(feature = "sha1", feature = "words", feature = "dyndig"))]
     fn passes_official_sha1_test_cases() {
-        for test_case in OFFICIAL_SHA1_TEST_CASES {
-            let otp = calculate_otp("sha1", test_case.0, test_case.1, test_case.2, None).unwrap();
-            assert_eq!(otp, test_case.3);
+        for test_case in test_vectors::SHA1 {
+            let otp = calculate_otp("sha1", test_case.passphrase, test_case.seed, test_case.count, None).unwrap();
+            assert_eq!(otp, test_case.otp);
+            let words = convert_to_word_format(&otp);
+            assert_eq!(words.join(" "), test_case.words);
+            let decoded = decode_word_format_with_std_dict(words).unwrap();
+            assert_eq!(decoded, (test_case.otp, true));
+        }
+    }
+
+    #[test]
+    #[cfg(all(feature = "sha1", feature = "words"))]
+    fn passes_official_sha1_test_cases_with_options() {
+        for test_case in test_vectors::SHA1 {
+            let otp = calculate_otp_with_options("sha1", test_case.passphrase, test_case.seed, test_case.count, &CalculateOptions::default()).unwrap();
+            assert_eq!(otp, test_case.otp);
             let words = convert_to_word_format(&otp);
-            assert_eq!(words.join(" "), test_case.4);
+            assert_eq!(words.join(" "), test_case.words);
             let decoded = decode_word_format_with_std_dict(words).unwrap();
-            assert_eq!(decoded, (test_case.3, true));
+            assert_eq!(decoded, (test_case.otp, true));
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn formats_test_vectors_as_hex () {
+        for test_case in test_vectors::all() {
+            assert_eq!(format_hex(&test_case.otp, HexStyle::CANONICAL), test_case.hex);
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "words")]
+    fn maps_words_to_indices () {
+        assert_eq!(word_index("A"), Some(0));
+        assert_eq!(word_index("YOKE"), Some(2047));
+        assert_eq!(word_index("BANANA"), None);
+        assert_eq!(index_to_word(0), Some("A"));
+        assert_eq!(index_to_word(2047), Some("YOKE"));
+        assert_eq!(index_to_word(2048), None);
+        for i in 0..2048 {
+            assert_eq!(word_index(index_to_word(i).unwrap()), Some(i));
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "words")]
+    fn decodes_word_str () {
+        assert_eq!(decode_word_str("AURA ALOE HURL WING BERG WAIT"), Ok([ 0x4F, 0x29, 0x6A, 0x74, 0xFE, 0x15, 0x67, 0xEC ]));
+        assert_eq!(decode_word_str(" AURA\tALOE HURL  WING BERG WAIT "), Ok([ 0x4F, 0x29, 0x6A, 0x74, 0xFE, 0x15, 0x67, 0xEC ]));
+        assert_eq!(decode_word_str("AURA ALOE HURL WING BERG"), Err(WordDecodeError::WrongWordCount(5)));
+        assert_eq!(decode_word_str("AURA ALOE HURL WING BERG WAIT WAIT"), Err(WordDecodeError::WrongWordCount(7)));
+        assert_eq!(decode_word_str("AURA ALOE HURL WING BERG WAITS"), Err(WordDecodeError::UnknownWord { index: 5 }));
+        assert_eq!(WordDecodeError::UnknownWord { index: 5 }.word(" AURA ALOE HURL WING BERG\tWAITS"), Some("WAITS"));
+        assert_eq!(WordDecodeError::BadChecksum.word("AURA ALOE HURL WING BERG WAITS"), None);
+        assert_eq!(decode_word_str("AURA ALOE HURL WING BERG WAIL"), Err(WordDecodeError::BadChecksum));
+    }
+
+    #[test]
+    #[cfg(all(feature = "words", feature = "alloc"))]
+    fn formats_words_in_either_case () {
+        let otp = [ 0x4F, 0x29, 0x6A, 0x74, 0xFE, 0x15, 0x67, 0xEC ];
+        assert_eq!(format_words(&otp, WordStyle::default()), otp_to_word_string(&otp, false));
+        assert_eq!(format_words(&otp, WordStyle::UPPERCASE.with_prefix()), otp_to_word_string(&otp, true));
+        let lower = format_words(&otp, WordStyle::LOWERCASE.with_prefix());
+        assert_eq!(lower, "word:aura aloe hurl wing berg wait");
+        assert_eq!(decode_word_str(&lower["word:".len()..]), Ok(otp));
+    }
+
+    #[test]
+    #[cfg(all(feature = "words", feature = "alloc"))]
+    fn formats_words_with_phonetics () {
+        assert_eq!(nato_phonetic('a'), Some("Alfa"));
+        assert_eq!(nato_phonetic('Z'), Some("Zulu"));
+        assert_eq!(nato_phonetic('1'), None);
+        assert_eq!(nato_phonetic('\u{C9}'), None);
+        let otp = [ 0x4F, 0x29, 0x6A, 0x74, 0xFE, 0x15, 0x67, 0xEC ];
+        assert_eq!(
+            format_words_with_phonetics(&otp),
+            "AURA (Alfa Uniform Romeo Alfa) ALOE (Alfa Lima Oscar Echo) HURL (Hotel Uniform Romeo Lima) \
+            WING (Whiskey India November Golf) BERG (Bravo Echo Romeo Golf) WAIT (Whiskey Alfa India Tango)",
+        );
+    }
+
+    #[test]
+    #[cfg(all(feature = "words", feature = "alloc"))]
+    fn suggests_words () {
+        assert!(suggest_word("WAITS").contains(&"WAIT"));
+        assert!(suggest_word("wing").contains(&"WING"));
+        assert!(suggest_word("WNG").contains(&"WING"));
+        assert!(suggest_word("WINK").contains(&"WING"));
+        assert!(!suggest_word("WINK").contains(&"WAIT"));
+        assert!(!suggest_word("WIGN").contains(&"WING"));
+        assert!(suggest_word("").contains(&"A"));
+        assert!(suggest_word("WAITED").is_empty());
+    }
+
+    #[test]
+    #[cfg(all(feature = "words", feature = "alloc"))]
+    fn converts_otp_to_word_string () {
+        let otp = [ 0x4F, 0x29, 0x6A, 0x74, 0xFE, 0x15, 0x67, 0xEC ];
+        assert_eq!(otp_to_word_string(&otp, false), "AURA ALOE HURL WING BERG WAIT");
+        assert_eq!(otp_to_word_string(&otp, true), "word:AURA ALOE HURL WING BERG WAIT");
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn formats_hex () {
+        let otp = [ 0x5B, 0xf0, 0x75, 0xd9, 0x95, 0x9d, 0x03, 0x6f ];
+        assert_eq!(format_hex(&otp, HexStyle::default()), "5BF0 75D9 959D 036F");
+        assert_eq!(format_hex(&otp, HexStyle::CANONICAL.with_prefix()), "hex:5BF0 75D9 959D 036F");
+        assert_eq!(format_hex(&otp, HexStyle::CONTIGUOUS_LOWERCASE), "5bf075d9959d036f");
+        assert_eq!(format_hex(&otp, HexStyle::CONTIGUOUS_UPPERCASE), "5BF075D9959D036F");
+        let lower_grouped = HexStyle { grouped: true, uppercase: false, prefix: true };
+        assert_eq!(format_hex(&otp, lower_grouped), "hex:5bf0 75d9 959d 036f");
+    }
+
+    #[test]
+    #[cfg(feature = "words")]
+    fn writes_to_fixed_buffers () {
+        use core::fmt::Write;
+
+        struct Buf { bytes: [u8; 64], len: usize }
+        impl Write for Buf {
+            fn write_str(&mut self, s: &str) -> core::fmt::Result {
+                let end = self.len + s.len();
+                self.bytes.get_mut(self.len..end).ok_or(core::fmt::Error)?.copy_from_slice(s.as_bytes());
+                self.len = end;
+                Ok(())
+            }
+        }
+
+        let otp = [ 0x4F, 0x29, 0x6A, 0x74, 0xFE, 0x15, 0x67, 0xEC ];
+        let mut buf = Buf { bytes: [0; 64], len: 0 };
+        write_words(&mut buf, &otp, true).unwrap();
+        assert_eq!(&buf.bytes[..buf.len], b"word:AURA ALOE HURL WING BERG WAIT");
+        let mut buf = Buf { bytes: [0; 64], len: 0 };
+        write_hex(&mut buf, &otp, HexStyle::CANONICAL.with_prefix()).unwrap();
+        assert_eq!(&buf.bytes[..buf.len], b"hex:4F29 6A74 FE15 67EC");
+        assert_eq!(buf.len, MAX_HEX_STRING_LEN);
+    }
+
+    #[test]
+    #[cfg(all(feature = "words", feature = "heapless"))]
+    fn formats_into_heapless_strings () {
+        // This word string is as long as it can possibly be.
+        let (otp, _) = decode_word_format_with_std_dict([ "ABED", "ABET", "ABLE", "ABUT", "ACHE", "ACID" ]).unwrap();
+        let words = otp_to_word_heapless_string(&otp, true);
+        assert!(words.starts_with("word:ABED ABET ABLE ABUT ACHE "));
+        assert_eq!(words.len(), MAX_WORD_STRING_LEN);
+        let hex = format_hex_heapless(&otp, HexStyle::CANONICAL.with_prefix());
+        assert_eq!(hex.len(), MAX_HEX_STRING_LEN);
+    }
+
+    #[test]
+    #[cfg(all(feature = "parsing-response", feature = "words"))]
+    fn distinguishes_word_decode_failures () {
+        let five = HexOrWords::Words("AURA ALOE HURL WING BERG");
+        let seven = HexOrWords::Words("AURA ALOE HURL WING BERG WAIT WAIT");
+        let unknown = HexOrWords::Words("AURA ALOE HURL WINGS BERG WAIT");
+        let bad_checksum = HexOrWords::Words("AURA ALOE HURL WING BERG WAIL");
+        assert_eq!(five.decode(), Err(WordDecodeError::WrongWordCount(5)));
+        assert_eq!(seven.decode(), Err(WordDecodeError::WrongWordCount(7)));
+        assert_eq!(unknown.decode(), Err(WordDecodeError::UnknownWord { index: 3 }));
+        assert_eq!(bad_checksum.decode(), Err(WordDecodeError::BadChecksum));
+        assert!(!five.decode().unwrap_err().is_probable_typo());
+        assert!(unknown.decode().unwrap_err().is_probable_typo());
+        assert!(bad_checksum.decode().unwrap_err().is_probable_typo());
+        assert_eq!(bad_checksum.try_into_bytes(), None);
+    }
+
+    #[test]
+    #[cfg(feature = "ct")]
+    fn finds_word_positions_in_constant_time () {
+        let words = [ "A", "AB", "ABC", "ABCD", "ABCE" ];
+        for (i, w) in words.iter().enumerate() {
+            assert_eq!(word_position(words.iter().copied(), w), Some(i));
+        }
+        assert_eq!(word_position(words.iter().copied(), ""), None);
+        assert_eq!(word_position(words.iter().copied(), "ABCDE"), None);
+        assert_eq!(word_position(words.iter().copied(), "B"), None);
+        assert_eq!(word_index("YOKE"), Some(2047));
+    }
+
+    #[test]
+    fn compares_otps_in_constant_time () {
+        let a = [ 0x4F, 0x29, 0x6A, 0x74, 0xFE, 0x15, 0x67, 0xEC ];
+        let mut b = a;
+        assert!(verify_otp_eq(&a, &b));
+        b[7] ^= 1;
+        assert!(!verify_otp_eq(&a, &b));
+        assert!(!verify_otp_eq(&a, &[0; 8]));
+    }
+
+    #[test]
+    #[cfg(all(feature = "parsing-response", feature = "words"))]
+    fn verifies_responses () {
+        let expected = [ 0x4F, 0x29, 0x6A, 0x74, 0xFE, 0x15, 0x67, 0xEC ];
+        let hex = parse_otp_response("hex:4F29 6A74 FE15 67EC").unwrap();
+        let words = parse_otp_response("word:aura Aloe HURL wing berg wait").unwrap();
+        let wrong = parse_otp_response("hex:4F29 6A74 FE15 67ED").unwrap();
+        let typo = parse_otp_response("word:AURA ALOE HURL WING BERG WAIL").unwrap();
+        assert_eq!(verify_response(&expected, &hex), Ok(VerifyResult::Match));
+        assert_eq!(verify_response(&expected, &words), Ok(VerifyResult::Match));
+        assert_eq!(verify_response(&expected, &wrong), Ok(VerifyResult::Mismatch));
+        assert_eq!(verify_response(&expected, &typo), Err(WordDecodeError::BadChecksum));
+
+        let init = parse_otp_response("init-hex:4F29 6A74 FE15 67EC:sha1 499 ke1235:3712 dcb4 aa53 16c1").unwrap();
+        let OTPResponse::Init(i) = &init else { panic!() };
+        assert_eq!(verify_response(&expected, &init), Ok(VerifyResult::Reinit {
+            init: i,
+            new_otp: [ 0x37, 0x12, 0xdc, 0xb4, 0xaa, 0x53, 0x16, 0xc1 ],
+        }));
+        assert_eq!(verify_response(&[0; 8], &init), Ok(VerifyResult::Mismatch));
+    }
+
+    #[test]
+    #[cfg(all(feature = "parsing-response", feature = "words"))]
+    fn compares_hex_and_words_semantically () {
+        let hex = HexOrWords::Hex([ 0x4F, 0x29, 0x6A, 0x74, 0xFE, 0x15, 0x67, 0xEC ]);
+        let words = HexOrWords::Words("AURA ALOE HURL WING BERG WAIT");
+        let spaced = HexOrWords::Words(" aura  aloe hurl wing berg wait");
+        assert_ne!(hex, words);
+        assert!(hex.semantically_eq(&words));
+        assert!(words.semantically_eq(&hex));
+        assert!(words.semantically_eq(&spaced));
+        assert!(!hex.semantically_eq(&HexOrWords::Hex([0; 8])));
+        let invalid = HexOrWords::Words("NOT AN OTP");
+        assert!(invalid.semantically_eq(&invalid));
+        assert!(!invalid.semantically_eq(&hex));
+    }
+
+    #[test]
+    #[cfg(feature = "packed-dictionary")]
+    fn packed_dictionary_matches_standard_dictionary () {
+        assert!(packed_dictionary_words().eq(STANDARD_DICTIONARY.iter().copied()));
+        assert_eq!(std_dict_word(2047), "YOKE");
+        assert_eq!(std_dict_index("AURA"), STANDARD_DICTIONARY.iter().position(|w| *w == "AURA"));
+    }
+
+    #[test]
+    #[cfg(all(feature = "dyndig", feature = "md5", feature = "sha1"))]
+    fn passes_official_test_cases_with_reused_digest () {
+        let mut md5_digest = Md5::new();
+        let mut sha1_digest = Sha1::new();
+        for test_case in test_vectors::MD5 {
+            let seed = test_case.seed.to_ascii_lowercase();
+            let otp = calculate_otp_with_digest(&mut md5_digest, HashAlg::Md5, test_case.passphrase, &seed, test_case.count);
+            assert_eq!(otp, Ok(test_case.otp));
+        }
+        for test_case in test_vectors::SHA1 {
+            let seed = test_case.seed.to_ascii_lowercase();
+            let otp = calculate_otp_with_digest(&mut sha1_digest, HashAlg::Sha1, test_case.passphrase, &seed, test_case.count);
+            assert_eq!(otp, Ok(test_case.otp));
+            let otp = calculate_otp_with_digest(&mut sha1_digest, HashAlg::Md5, test_case.passphrase, &seed, test_case.count);
+            assert_ne!(otp, Ok(test_case.otp));
+        }
+    }
+
+    /// A digest whose output, of the given size, is always zero.
+    #[cfg(feature = "dyndig")]
+    struct ZeroDigest(usize);
+
+    #[cfg(feature = "dyndig")]
+    impl digest::DynDigest for ZeroDigest {
+
+        fn update(&mut self, _data: &[u8]) {}
+
+        fn finalize_into(self, buf: &mut [u8]) -> Result<(), digest::InvalidBufferSize> {
+            buf.fill(0);
+            Ok(())
+        }
+
+        fn finalize_into_reset(&mut self, buf: &mut [u8]) -> Result<(), digest::InvalidBufferSize> {
+            buf.fill(0);
+            Ok(())
+        }
+
+        fn reset(&mut self) {}
+
+        fn output_size(&self) -> usize {
+            self.0
+        }
+
+    }
+
+    #[test]
+    #[cfg(feature = "dyndig")]
+    fn rejects_unsupported_digest_sizes () {
+        for size in [ 0, 4, 7, MAX_DIGEST_SIZE + 1 ] {
+            assert_eq!(
+                calculate_otp_with_digest(&mut ZeroDigest(size), HashAlg::Md5, "This is a test.", "test", 0),
+                Err(CalculateError::UnsupportedDigestSize(size)),
+            );
+        }
+        for size in [ 8, 20, MAX_DIGEST_SIZE ] {
+            assert_eq!(calculate_otp_with_digest(&mut ZeroDigest(size), HashAlg::Md5, "This is a test.", "test", 5), Ok([ 0; 8 ]));
+        }
+        fn get_digest (_: &str) -> Option<Box<dyn digest::DynDigest>> {
+            Some(Box::new(ZeroDigest(4)))
+        }
+        let options = CalculateOptions { get_digest: Some(get_digest), ..Default::default() };
+        assert_eq!(
+            calculate_otp_with_options("tiny", "This is a test.", "TeSt", 0, &options),
+            Err(CalculateError::UnsupportedDigestSize(4)),
+        );
+    }
+
+    #[test]
+    #[cfg(all(feature = "sha256", feature = "dyndig"))]
+    fn calculates_sha256_otps () {
+        assert_eq!(calculate_otp("x-sha256", "This is a test.", "TeSt", 0, None), Some([ 0xCA, 0x6F, 0xF2, 0x6F, 0x82, 0x24, 0x41, 0x1D ]));
+        assert_eq!(calculate_otp("x-sha256", "This is a test.", "TeSt", 1, None), Some([ 0x8F, 0xD9, 0x32, 0x43, 0x7A, 0xCE, 0x9E, 0x0F ]));
+        assert_eq!(calculate_otp("x-sha256", "OTP's are good", "correct", 99, None), Some([ 0xA2, 0x55, 0x12, 0x6A, 0x1B, 0xEB, 0x08, 0x85 ]));
+    }
+
+    #[test]
+    #[cfg(feature = "sha256")]
+    fn calculates_sha256_otps_with_options () {
+        assert_eq!(calculate_otp_with_options("x-sha256", "This is a test.", "TeSt", 0, &CalculateOptions::default()), Ok([ 0xCA, 0x6F, 0xF2, 0x6F, 0x82, 0x24, 0x41, 0x1D ]));
+        assert_eq!(calculate_otp_with_options("x-sha256", "This is a test.", "TeSt", 1, &CalculateOptions::default()), Ok([ 0x8F, 0xD9, 0x32, 0x43, 0x7A, 0xCE, 0x9E, 0x0F ]));
+        assert_eq!(calculate_otp_with_options("x-sha256", "OTP's are good", "correct", 99, &CalculateOptions::default()), Ok([ 0xA2, 0x55, 0x12, 0x6A, 0x1B, 0xEB, 0x08, 0x85 ]));
+    }
+
+    #[test]
+    #[cfg(all(feature = "sha512", feature = "dyndig"))]
+    fn calculates_sha512_otps () {
+        assert_eq!(calculate_otp("x-sha512", "This is a test.", "TeSt", 0, None), Some([ 0x1B, 0x63, 0x33, 0x9A, 0x97, 0x10, 0x8E, 0xAA ]));
+        assert_eq!(calculate_otp("x-sha512", "This is a test.", "TeSt", 1, None), Some([ 0x15, 0x55, 0xE3, 0xFA, 0xC6, 0x90, 0xC5, 0x99 ]));
+        assert_eq!(calculate_otp("x-sha512", "OTP's are good", "correct", 99, None), Some([ 0xC3, 0xCB, 0x56, 0x95, 0x5B, 0x4F, 0x99, 0xF0 ]));
+    }
+
+    #[test]
+    #[cfg(feature = "sha512")]
+    fn calculates_sha512_otps_with_options () {
+        assert_eq!(calculate_otp_with_options("x-sha512", "This is a test.", "TeSt", 0, &CalculateOptions::default()), Ok([ 0x1B, 0x63, 0x33, 0x9A, 0x97, 0x10, 0x8E, 0xAA ]));
+        assert_eq!(calculate_otp_with_options("x-sha512", "This is a test.", "TeSt", 1, &CalculateOptions::default()), Ok([ 0x15, 0x55, 0xE3, 0xFA, 0xC6, 0x90, 0xC5, 0x99 ]));
+        assert_eq!(calculate_otp_with_options("x-sha512", "OTP's are good", "correct", 99, &CalculateOptions::default()), Ok([ 0xC3, 0xCB, 0x56, 0x95, 0x5B, 0x4F, 0x99, 0xF0 ]));
+    }
+
+    #[test]
+    fn maps_hash_algorithm_names () {
+        for alg in HashAlg::ALL {
+            assert_eq!(HashAlg::from_name(alg.name()), Some(alg));
+        }
+        assert_eq!(HashAlg::from_name_strict("sha1"), Some(HashAlg::Sha1));
+        assert_eq!(HashAlg::from_name_strict("x-sha256"), None);
+        assert_eq!(HashAlg::from_name("sha256"), None);
+    }
+
+    #[test]
+    #[cfg(all(feature = "md5", feature = "sha1"))]
+    fn calculates_otps_under_multiple_algorithms () {
+        let otps = calculate_otp_multi(&[ HashAlg::Md5, HashAlg::Sha1 ], "OTP's are good", "correct", 99).unwrap();
+        assert_eq!(otps.get(HashAlg::Md5), Some(test_vectors::MD5[8].otp));
+        assert_eq!(otps.get(HashAlg::Sha1), Some(test_vectors::SHA1[8].otp));
+        assert_eq!(otps.get(HashAlg::Md4), None);
+        assert_eq!(otps.iter().count(), 2);
+        assert_eq!(
+            calculate_otp_multi(&[ HashAlg::Md5 ], "OTP's are good", "correct", DEFAULT_MAX_COUNT + 1).err(),
+            Some(CalculateError::CountTooLarge(DEFAULT_MAX_COUNT)),
+        );
+        assert_eq!(
+            calculate_otp_multi(&[ HashAlg::Md5 ], "OTP's are good", "abcdefghijklmnopq", 99).err(),
+            Some(CalculateError::SeedTooLong),
+        );
+        assert_eq!(calculate_otp_with_alg(HashAlg::Md5, "OTP's are good", "CORRECT", 99), Some(test_vectors::MD5[8].otp));
+    }
+
+    #[test]
+    #[cfg(feature = "sha1")]
+    fn rejects_excessive_hash_counts () {
+        #[cfg(feature = "dyndig")]
+        assert_eq!(calculate_otp("sha1", "This is a test.", "TeSt", 4_000_000_000, None), None);
+        assert_eq!(
+            calculate_otp_with_options("sha1", "This is a test.", "TeSt", 4_000_000_000, &CalculateOptions::default()),
+            Err(CalculateError::CountTooLarge(DEFAULT_MAX_COUNT)),
+        );
+        let options = CalculateOptions { max_count: 100, ..Default::default() };
+        assert_eq!(
+            calculate_otp_with_options("sha1", "This is a test.", "TeSt", 101, &options),
+            Err(CalculateError::CountTooLarge(100)),
+        );
+        assert_eq!(
+            calculate_otp_with_options("sha1", "This is a test.", "TeSt", 99, &options),
+            Ok(test_vectors::SHA1[2].otp),
+        );
+        assert_eq!(
+            calculate_otp_with_options("sha3", "This is a test.", "TeSt", 99, &options),
+            Err(CalculateError::UnsupportedAlgorithm),
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "md5")]
+    fn lowercases_long_seeds () {
+        let options = CalculateOptions { max_seed_len: usize::MAX, ..Default::default() };
+        let upper = "A".repeat(MAX_SEED_LEN);
+        let lower = "a".repeat(MAX_SEED_LEN);
+        assert_eq!(
+            calculate_otp_with_options("md5", "This is a test.", &upper, 3, &options),
+            calculate_otp_with_options("md5", "This is a test.", &lower, 3, &options),
+        );
+        // Seeds too long to be lowercased on the stack are lowercased on the
+        // heap, if they can be.
+        let too_long = "Ab".repeat(MAX_SEED_LEN);
+        let lower = calculate_otp_with_options("md5", "This is a test.", &too_long.to_ascii_lowercase(), 3, &options);
+        assert!(lower.is_ok());
+        #[cfg(feature = "alloc")]
+        assert_eq!(calculate_otp_with_options("md5", "This is a test.", &too_long, 3, &options), lower);
+        #[cfg(not(feature = "alloc"))]
+        assert_eq!(
+            calculate_otp_with_options("md5", "This is a test.", &too_long, 3, &options),
+            Err(CalculateError::SeedTooLong),
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "md5")]
+    fn rejects_overlong_seeds () {
+        let seed = "a".repeat(MAX_SEED_LEN + 1);
+        assert_eq!(
+            calculate_otp_with_options("md5", "This is a test.", &seed, 0, &CalculateOptions::default()),
+            Err(CalculateError::SeedTooLong),
+        );
+        assert_eq!(calculate_otp_with_alg(HashAlg::Md5, "This is a test.", &seed, 0), None);
+        assert!(calculate_otp_with_alg(HashAlg::Md5, "This is a test.", &seed[..MAX_SEED_LEN], 0).is_some());
+    }
+
+    #[test]
+    fn folds_digests () {
+        let mut md: [u8; 16] = core::array::from_fn(|i| i as u8);
+        fold_md(&mut md);
+        assert_eq!(md[0..8], [ 8; 8 ]);
+
+        let mut sha1: [u8; 20] = core::array::from_fn(|i| i as u8);
+        fold_sha1(&mut sha1);
+        assert_eq!(sha1[0..8], [ 27, 26, 25, 24, 8, 8, 8, 8 ]);
+
+        let mut sha1: [u8; 20] = core::array::from_fn(|i| i as u8);
+        fold_to_64_bits(HashAlg::Sha1, &mut sha1);
+        assert_eq!(sha1[0..8], [ 27, 26, 25, 24, 8, 8, 8, 8 ]);
+
+        let mut sha256: [u8; 32] = core::array::from_fn(|i| i as u8);
+        fold_to_64_bits(HashAlg::Sha256, &mut sha256);
+        assert_eq!(sha256[0..8], [ 0; 8 ]);
+    }
+
+    #[test]
+    #[cfg(all(feature = "nfkc", feature = "sha1"))]
+    fn normalizes_passphrases () {
+        assert!(matches!(normalize_passphrase("This is a test."), Cow::Borrowed(_)));
+        assert_eq!(normalize_passphrase("\u{FB01}ne cr\u{E8}me br\u{FB}l\u{E9}e"), "fine cr\u{E8}me br\u{FB}l\u{E9}e");
+        let composed = calculate_otp_with_options("sha1", "cr\u{E8}me br\u{FB}l\u{E9}e", "TeSt", 5, &CalculateOptions::default());
+        let decomposed = calculate_otp_with_options("sha1", "cre\u{300}me bru\u{302}le\u{301}e", "TeSt", 5, &CalculateOptions::default());
+        assert!(composed.is_ok());
+        assert_eq!(composed, decomposed);
+        #[cfg(feature = "dyndig")]
+        {
+            let composed = calculate_otp("sha1", "cr\u{E8}me br\u{FB}l\u{E9}e", "TeSt", 5, None);
+            let decomposed = calculate_otp("sha1", "cre\u{300}me bru\u{302}le\u{301}e", "TeSt", 5, None);
+            assert!(composed.is_some());
+            assert_eq!(composed, decomposed);
         }
     }
 
     #[test]
-    #[cfg(feature = "parsing")]
+    fn checks_passphrase_quality () {
+        let good = check_passphrase("This is a test.", "TeSt");
+        assert!(good.is_acceptable());
+        assert_eq!(good.length, 15);
+        assert_eq!(good.char_classes, 3);
+        assert!(check_passphrase("banana", "ke1234").too_short);
+        assert!(check_passphrase(&"x".repeat(64), "ke1234").too_long);
+        assert!(check_passphrase("abababababab", "ke1234").low_diversity);
+        assert!(check_passphrase("Alpha1Alpha1", "alpha1alpha1").matches_seed);
+        assert!(!check_passphrase("Alpha1Alpha1", "alpha1alpha1").is_acceptable());
+    }
+
+    #[test]
+    #[cfg(feature = "parsing-challenge")]
     fn parses_otp_challenge() {
         let challenge = "otp-md5 487 dog2";
         let challenge = parse_otp_challenge(challenge).unwrap();
         assert_eq!(challenge.hash_alg, "md5");
         assert_eq!(challenge.hash_count, 487);
         assert_eq!(challenge.seed, "dog2");
+        assert!(challenge.capabilities.is_empty());
+    }
+
+    #[test]
+    #[cfg(feature = "parsing-challenge")]
+    fn parses_otp_challenge_capabilities () {
+        let challenge = parse_otp_challenge("otp-md5 499 ke1234 ext").unwrap();
+        assert_eq!(challenge.seed, "ke1234");
+        assert!(challenge.is_extended());
+        let challenge = parse_otp_challenge("otp-md5 499 EXT ke1234 foo bar").unwrap();
+        assert_eq!(challenge.seed, "ke1234");
+        assert!(challenge.is_extended());
+        assert!(challenge.capabilities.iter().eq(["EXT", "foo", "bar"]));
+        let challenge = parse_otp_challenge("otp-md5 499 ext").unwrap();
+        assert_eq!(challenge.seed, "ext");
+        assert!(!challenge.is_extended());
+        assert_eq!(parse_otp_challenge("otp-md5 499 "), None);
     }
 
     #[test]
-    #[cfg(feature = "parsing")]
+    #[cfg(feature = "parsing-challenge")]
+    fn parses_otp_challenge_strictly () {
+        assert_eq!(parse_otp_challenge_strict("otp-md5 487 dog2").unwrap().seed, "dog2");
+        assert!(parse_otp_challenge("otp-md5 487 do:g2").is_some());
+        assert_eq!(parse_otp_challenge_strict("otp-md5 487 do:g2"), Err(ChallengeParseError::BadSeed));
+        assert_eq!(parse_otp_challenge_strict("otp-md5 487 d\u{f6}g2"), Err(ChallengeParseError::BadSeed));
+        assert_eq!(parse_otp_challenge_strict("otp-md5 487 dog2dog2dog2dog2d"), Err(ChallengeParseError::BadSeed));
+        assert_eq!(parse_otp_challenge_strict("otp-md5 dog2"), Err(ChallengeParseError::Malformed));
+    }
+
+    #[test]
+    #[cfg(feature = "parsing-challenge")]
+    fn parses_challenges_with_implied_algorithms () {
+        for s in [ "s/key 99 th91334", "S/Key  99 th91334 ", "99 th91334" ] {
+            let challenge = parse_otp_challenge_with_default_alg(s, HashAlg::Md4).unwrap();
+            assert_eq!((challenge.hash_alg, challenge.hash_count, challenge.seed), ("md4", 99, "th91334"));
+        }
+        assert_eq!(parse_otp_challenge_with_default_alg("otp-md5 99 th91334", HashAlg::Md4).unwrap().hash_alg, "md5");
+        assert_eq!(parse_otp_challenge_with_default_alg("s/key th91334", HashAlg::Md4), None);
+        assert_eq!(parse_otp_challenge_with_default_alg("s/key 99", HashAlg::Md4), None);
+        assert_eq!(parse_otp_challenge_with_default_alg("otp-md5 99", HashAlg::Md4), None);
+
+        let challenge = OTPChallenge { hash_alg: "", hash_count: 99, seed: "th91334", capabilities: Default::default() };
+        assert_eq!(challenge.with_default_alg(HashAlg::Md4).hash_alg, "md4");
+        let challenge = parse_otp_challenge("otp-sha1 99 th91334").unwrap();
+        assert_eq!(challenge.with_default_alg(HashAlg::Md4).hash_alg, "sha1");
+    }
+
+    #[test]
+    #[cfg(all(feature = "parsing-challenge", feature = "words", feature = "alloc", feature = "md5"))]
+    fn responds_in_the_chosen_format () {
+        let challenge = parse_otp_challenge("otp-md5 0 TeSt").unwrap();
+        let words = "word:INCH SEA ANNE LONG AHEM TOUR";
+        let hex = "hex:9E87 6134 D904 99DD";
+        assert_eq!(challenge.respond("This is a test.", ResponseFormat::Words).unwrap(), words);
+        assert_eq!(challenge.respond("This is a test.", ResponseFormat::Hex).unwrap(), hex);
+        assert_eq!(challenge.respond("This is a test.", ResponseFormat::Auto).unwrap(), words);
+        let custom: &[&str] = &STANDARD_DICTIONARY;
+        assert_eq!(challenge.respond_with_dictionary("This is a test.", ResponseFormat::Auto, custom).unwrap(), hex);
+        assert_eq!(challenge.respond_with_dictionary("This is a test.", ResponseFormat::Words, custom).unwrap(), words);
+        let short: &[&str] = &[ "A" ];
+        assert_eq!(challenge.respond_with_dictionary("This is a test.", ResponseFormat::Words, short).unwrap(), hex);
+    }
+
+    #[test]
+    #[cfg(feature = "parsing-response")]
     fn parses_otp_response_hex () {
         let otp_response = "hex:5Bf0 75d9 959d 036f";
-        let r = parse_otp_response(&otp_response).unwrap();
-        if let OTPResponse::Current(x) = r {
-            if let HexOrWords::Hex(h) = x {
-                assert_eq!(h, [ 0x5B, 0xf0, 0x75, 0xd9, 0x95, 0x9d, 0x03, 0x6f ]);
-            } else {
-                panic!()
-            }
+        let r = parse_otp_response(otp_response).unwrap();
+        if let OTPResponse::Current(HexOrWords::Hex(h)) = r {
+            assert_eq!(h, [ 0x5B, 0xf0, 0x75, 0xd9, 0x95, 0x9d, 0x03, 0x6f ]);
         } else {
             panic!()
         }
     }
 
     #[test]
-    #[cfg(feature = "parsing")]
+    #[cfg(feature = "parsing-response")]
     fn parses_otp_response_word () {
         let otp_response = "word:BOND FOGY DRAB NE RISE MART";
-        let r = parse_otp_response(&otp_response).unwrap();
-        if let OTPResponse::Current(x) = r {
-            if let HexOrWords::Words(w) = x {
-                assert_eq!(w, "BOND FOGY DRAB NE RISE MART");
-            } else {
-                panic!()
-            }
+        let r = parse_otp_response(otp_response).unwrap();
+        if let OTPResponse::Current(HexOrWords::Words(w)) = r {
+            assert_eq!(w, "BOND FOGY DRAB NE RISE MART");
         } else {
             panic!()
         }
     }
 
     #[test]
-    #[cfg(feature = "parsing")]
+    #[cfg(feature = "parsing-response")]
+    fn parses_otp_response_bytes () {
+        let response = b"hex:5Bf0 75d9 959d 036f";
+        assert_eq!(parse_otp_response_bytes(response, 100), parse_otp_response("hex:5Bf0 75d9 959d 036f"));
+        assert!(parse_otp_response_bytes(response, response.len()).is_some());
+        assert_eq!(parse_otp_response_bytes(response, response.len() - 1), None);
+        assert!(parse_otp_response_bytes(b"hex:5Bf0\t75d9 959d 036f", 100).is_some());
+        assert_eq!(parse_otp_response_bytes(b"hex:5Bf0 75d9 959d 036f\0", 100), None);
+        assert_eq!(parse_otp_response_bytes(b"word:\xC3\x89 SEA ANNE LONG AHEM TOUR", 100), None);
+        assert_eq!(parse_otp_response_bytes(&[ b'a'; 101 ], usize::MAX), None);
+    }
+
+    #[test]
+    #[cfg(feature = "parsing-response")]
+    fn parses_bare_otp_responses () {
+        let lenient = ParseResponseOptions { accept_bare: true };
+        let words = " BOND FOGY DRAB NE RISE MART\n";
+        assert_eq!(parse_otp_response(words), None);
+        assert_eq!(
+            parse_otp_response_with_options(words, &lenient),
+            Some(OTPResponse::Current(HexOrWords::Words("BOND FOGY DRAB NE RISE MART"))),
+        );
+        assert_eq!(
+            parse_otp_response_with_options("5bf075d9959d036f", &lenient),
+            Some(OTPResponse::Current(HexOrWords::Hex([ 0x5B, 0xf0, 0x75, 0xd9, 0x95, 0x9d, 0x03, 0x6f ]))),
+        );
+        // Prefixed responses are parsed as usual.
+        assert_eq!(
+            parse_otp_response_with_options("word:BOND FOGY DRAB NE RISE MART", &lenient),
+            parse_otp_response("word:BOND FOGY DRAB NE RISE MART"),
+        );
+        assert_eq!(parse_otp_response_with_options("BOND FOGY DRAB NE RISE", &lenient), None);
+        assert_eq!(parse_otp_response_with_options("BOND FOGY DRAB NE RISE MARTS", &lenient), None);
+        assert_eq!(parse_otp_response_with_options("5bf075d9959d036", &lenient), None);
+        assert_eq!(parse_otp_response_with_options("foo:BOND FOGY DRAB NE RISE MART", &lenient), None);
+    }
+
+    #[test]
+    #[cfg(all(feature = "parsing-response", feature = "alloc"))]
+    fn sanitizes_user_otp_input () {
+        let cases = [
+            ("  bond fogy\tdrab ne  rise mart ", "word:BOND FOGY DRAB NE RISE MART"),
+            ("'Bond Fogy Drab Ne Rise Mart'", "word:BOND FOGY DRAB NE RISE MART"),
+            ("\u{201C}word: bond fogy drab ne rise mart\u{201D}", "word:BOND FOGY DRAB NE RISE MART"),
+            ("5bf075d9959d036f", "hex:5BF0 75D9 959D 036F"),
+            ("`5B F0 75 D9 95 9D 03 6F`", "hex:5BF0 75D9 959D 036F"),
+            ("Hex : 5bf0 75d9 959d 036f", "hex:5BF0 75D9 959D 036F"),
+        ];
+        for (input, expected) in cases {
+            let sanitized = sanitize_user_otp_input(input);
+            assert_eq!(sanitized, expected);
+            assert!(parse_otp_response(&sanitized).is_some());
+        }
+        let init = "init-hex:5bf0 75d9 959d 036f:md5 499 ke1235:3712 dcb4 aa53 16c1";
+        let quoted = " \"init-hex:5bf0 75d9 959d 036f:md5 499 ke1235:3712 dcb4 aa53 16c1\" ";
+        assert_eq!(sanitize_user_otp_input(quoted), init);
+        assert_eq!(sanitize_user_otp_input("hello  world"), "hello world");
+    }
+
+    #[test]
+    #[cfg(feature = "parsing-response")]
     fn parses_otp_response_init_hex () {
         let otp_response = "init-hex:5bf0 75d9 959d 036f:md5 499 ke1235:3712 dcb4 aa53 16c1";
-        let r = parse_otp_response(&otp_response).unwrap();
+        let r = parse_otp_response(otp_response).unwrap();
         if let OTPResponse::Init(x) = r {
             assert_eq!(x.current_otp, HexOrWords::Hex([ 0x5B, 0xf0, 0x75, 0xd9, 0x95, 0x9d, 0x03, 0x6f ]));
             assert_eq!(x.new_otp, HexOrWords::Hex([ 0x37, 0x12, 0xdc, 0xb4, 0xaa, 0x53, 0x16, 0xc1 ]));
             assert_eq!(x.new_alg, "md5");
             assert_eq!(x.new_seq_num, 499);
             assert_eq!(x.new_seed, "ke1235");
+            assert_eq!(x.extra, None);
         } else {
             panic!()
         }
     }
 
     #[test]
-    #[cfg(feature = "parsing")]
+    #[cfg(all(feature = "parsing-response", feature = "words", feature = "md5"))]
+    fn validates_init_responses () {
+        let parse = |s| match parse_otp_response(s) {
+            Some(OTPResponse::Init(init)) => init,
+            _ => panic!(),
+        };
+        let init = parse("init-hex:5bf0 75d9 959d 036f:md5 499 ke1235:3712 dcb4 aa53 16c1");
+        assert_eq!(init.current_bytes(), Ok([ 0x5B, 0xf0, 0x75, 0xd9, 0x95, 0x9d, 0x03, 0x6f ]));
+        assert_eq!(init.validate(), Ok(ValidatedInit {
+            current_otp: [ 0x5B, 0xf0, 0x75, 0xd9, 0x95, 0x9d, 0x03, 0x6f ],
+            new_otp: [ 0x37, 0x12, 0xdc, 0xb4, 0xaa, 0x53, 0x16, 0xc1 ],
+            new_alg: HashAlg::Md5,
+            new_seq_num: 499,
+            new_seed: Seed::new("ke1235").unwrap(),
+        }));
+        let init = parse("init-word:INCH SEA ANNE LONG AHEM TOUR:md5 499 ke1235:INCH SEA ANNE LONG AHEM TOUT");
+        assert_eq!(init.validate(), Err(InitValidationError::NewOtp(WordDecodeError::BadChecksum)));
+        let init = parse("init-hex:5bf0 75d9 959d 036f:md6 499 ke1235:3712 dcb4 aa53 16c1");
+        assert_eq!(init.validate(), Err(InitValidationError::UnsupportedAlgorithm));
+        let init = parse("init-hex:5bf0 75d9 959d 036f:md5 0 ke1235:3712 dcb4 aa53 16c1");
+        assert_eq!(init.validate(), Err(InitValidationError::BadCount));
+        let init = parse("init-hex:5bf0 75d9 959d 036f:md5 499 ke_1235:3712 dcb4 aa53 16c1");
+        assert_eq!(init.validate(), Err(InitValidationError::BadSeed));
+    }
+
+    #[test]
+    #[cfg(all(feature = "parsing-response", feature = "words", feature = "alloc"))]
+    fn round_trips_init_response_extensions () {
+        let otp_response = "init-hex:5bf0 75d9 959d 036f:md5 499 ke1235:3712 dcb4 aa53 16c1:x-foo 1::";
+        let r = parse_otp_response(otp_response).unwrap();
+        let OTPResponse::Init(init) = &r else {
+            panic!()
+        };
+        assert_eq!(init.new_otp, HexOrWords::Hex([ 0x37, 0x12, 0xdc, 0xb4, 0xaa, 0x53, 0x16, 0xc1 ]));
+        assert_eq!(init.extra, Some("x-foo 1::"));
+        assert!(init.extra_fields().eq([ "x-foo 1", "", "" ]));
+        let mut s = String::new();
+        write_otp_response(&mut s, &r).unwrap();
+        assert_eq!(s, "init-hex:5BF0 75D9 959D 036F:md5 499 ke1235:3712 DCB4 AA53 16C1:x-foo 1::");
+        assert_eq!(parse_otp_response(&s), Some(r));
+
+        let r = parse_otp_response("word:INCH SEA ANNE LONG AHEM TOUR").unwrap();
+        let mut s = String::new();
+        write_otp_response(&mut s, &r).unwrap();
+        assert_eq!(s, "word:INCH SEA ANNE LONG AHEM TOUR");
+    }
+
+    #[test]
+    #[cfg(feature = "parsing-response")]
+    fn parses_maximal_init_responses_with_extensions () {
+        use alloc::format;
+        let words = "BURG BURG BURG BURG BURG BURG";
+        let init = format!("init-word:{}:x-sha512 {:020} abcdefghijklmnop:{}", words, 499, words);
+        assert_eq!(init.len(), MAX_INIT_RESPONSE_LEN);
+        let extra = "x".repeat(MAX_EXTENSION_LEN - 1);
+        let response = format!("{}:{}", init, extra);
+        assert_eq!(response.len(), MAX_RESPONSE_LEN);
+        let Some(OTPResponse::Init(parsed)) = parse_otp_response(&response) else {
+            panic!()
+        };
+        assert_eq!((parsed.new_alg, parsed.new_seq_num, parsed.new_seed), ("x-sha512", 499, "abcdefghijklmnop"));
+        assert_eq!(parsed.extra, Some(extra.as_str()));
+        assert!(parse_otp_init(&response).is_some());
+        assert!(parse_otp_response(&format!("{}x", response)).is_none());
+    }
+
+    #[test]
+    #[cfg(feature = "parsing-response")]
     fn parses_otp_response_init_word () {
         let otp_response = "init-word:BOND FOGY DRAB NE RISE MART:md5 499 ke1235:RED HERD NOW BEAN PA BURG";
-        let r = parse_otp_response(&otp_response).unwrap();
+        let r = parse_otp_response(otp_response).unwrap();
         if let OTPResponse::Init(x) = r {
             assert_eq!(x.current_otp, HexOrWords::Words("BOND FOGY DRAB NE RISE MART"));
             assert_eq!(x.new_otp, HexOrWords::Words("RED HERD NOW BEAN PA BURG"));
@@ -1039,4 +3934,128 @@ mod tests {
             panic!()
         }
     }
+
+    #[test]
+    fn decodes_hex_with_embedded_whitespace () {
+        let otp = [ 0x5B, 0xF0, 0x75, 0xD9, 0x95, 0x9D, 0x03, 0x6F ];
+        assert_eq!(decode_hex_otp("5Bf0 75d9 959d 036f"), Some(otp));
+        assert_eq!(decode_hex_otp("5bf075d9959d036f"), Some(otp));
+        assert_eq!(decode_hex_otp(" 5B F0\t75 D9 95 9D 03 6F "), Some(otp));
+        assert_eq!(decode_hex_otp("5Bf0 75d9 959d 036"), None);
+        assert_eq!(decode_hex_otp("5Bf0 75d9 959d 036f 00"), None);
+        assert_eq!(decode_hex_otp("5Bf0 75d9 959d 036g"), None);
+        assert_eq!(decode_hex_otp("5Bf0\n75d9 959d 036f"), None);
+    }
+
+    #[cfg(feature = "parsing-response")]
+    #[test]
+    fn parses_ungrouped_hex_in_init_responses () {
+        let otp = [ 0x5B, 0xF0, 0x75, 0xD9, 0x95, 0x9D, 0x03, 0x6F ];
+        // The shortest possible init response, with groups distinguished only by case.
+        let s = "init-hex:5BF075d9959D036f:md4 1 a:5bf075D9959d036F";
+        assert_eq!(s.len(), 50);
+        let expected = OTPInit {
+            current_otp: HexOrWords::Hex(otp),
+            new_otp: HexOrWords::Hex(otp),
+            new_alg: "md4",
+            new_seq_num: 1,
+            new_seed: "a",
+            extra: None,
+        };
+        assert!(parse_otp_init(s) == Some(expected));
+        assert!(parse_otp_response(s).is_some_and(|r| matches!(r, OTPResponse::Init(_))));
+        assert!(parse_otp_response("hex:5bf075D9959d036F") == Some(OTPResponse::Current(HexOrWords::Hex(otp))));
+    }
+
+
+    #[test]
+    #[cfg(feature = "words")]
+    fn applies_checksums_to_last_words () {
+        let otp = [ 0x9E, 0x87, 0x61, 0x34, 0xD9, 0x04, 0x99, 0xDD ];
+        let last = word_index(convert_to_word_format(&otp)[5]).unwrap();
+        let checksum = checksum_2bit(&otp);
+        assert_eq!(u64::from(checksum), calculate_checksum(&otp));
+        assert_eq!(extract_checksum(last), checksum);
+        for c in 0..4 {
+            let index = apply_checksum_to_last_word(last, c);
+            assert_eq!(extract_checksum(index), c);
+            assert_eq!(index >> 2, last >> 2);
+        }
+        assert_eq!(checksum_2bit(&[ 0xFF; 8 ]), 0);
+        assert_eq!(checksum_2bit(&[ 0, 0, 0, 0, 0, 0, 0, 0b10 ]), 2);
+        assert_eq!(checksum_2bit_slice(&[]), 0);
+        assert_eq!(checksum_2bit_slice(&[ 0xFF; 3 ]), 0);
+        assert_eq!(checksum_2bit_slice(&[ 0b1110_0111; 100 ]), 0);
+        assert_eq!(checksum_2bit_slice(&[ 0b1110_0111; 101 ]), 1);
+    }
+
+    #[test]
+    #[cfg(all(feature = "parsing", feature = "words", feature = "alloc"))]
+    fn canonicalizes_responses () {
+        assert_eq!(canonicalize_response("hex:5Bf0 75d9\t959D036f").unwrap(), "hex:5BF0 75D9 959D 036F");
+        assert_eq!(
+            canonicalize_response("init-word:inch sea anne long ahem tour:MD5 499 KE1235:rome  mug fred scan live lace:ext").unwrap(),
+            "init-word:INCH SEA ANNE LONG AHEM TOUR:md5 499 ke1235:ROME MUG FRED SCAN LIVE LACE:ext",
+        );
+        assert_eq!(
+            canonicalize_response("init-hex:5bf075d9959d036f:md5 499 ke1235:3712dcb4aa5316c1"),
+            Ok(String::from("init-hex:5BF0 75D9 959D 036F:md5 499 ke1235:3712 DCB4 AA53 16C1")),
+        );
+        assert_eq!(canonicalize_response("otp-md5 499 ke1234"), Err(CanonicalizeError::Malformed));
+        assert_eq!(
+            canonicalize_response("word:INCH SEA ANNE LONG AHEM"),
+            Err(CanonicalizeError::Undecodable(WordDecodeError::WrongWordCount(5))),
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "words")]
+    fn converts_between_otps_and_word_indices () {
+        const OTP: [u8; 8] = [ 0x9E, 0x87, 0x61, 0x34, 0xD9, 0x04, 0x99, 0xDD ];
+        const INDICES: [u16; 6] = otp_to_word_indices(&OTP);
+        const DECODED: ([u8; 8], bool) = word_indices_to_otp(&INDICES);
+        assert_eq!(DECODED, (OTP, true));
+        for (index, word) in INDICES.iter().zip(convert_to_word_format(&OTP)) {
+            assert_eq!(word_index(word), Some(*index));
+        }
+        let mut bad = INDICES;
+        bad[5] ^= 1;
+        assert_eq!(word_indices_to_otp(&bad), (OTP, false));
+        for (i, word) in STANDARD_DICTIONARY.iter().enumerate() {
+            assert_eq!(word_index(word), Some(i as u16));
+            assert_eq!(word_index(&word.to_lowercase()), Some(i as u16));
+        }
+        assert_eq!(word_index("ABCD"), None);
+        assert_eq!(word_index("AB"), None);
+        assert_eq!(word_index(""), None);
+        assert_eq!(word_index("ABCDE"), None);
+    }
+
+    #[test]
+    #[cfg(feature = "md5")]
+    fn advances_otps () {
+        let otp_0 = calculate_otp_with_alg(HashAlg::Md5, "This is a test.", "TeSt", 0).unwrap();
+        let otp_1 = calculate_otp_with_alg(HashAlg::Md5, "This is a test.", "TeSt", 1).unwrap();
+        let otp_99 = calculate_otp_with_alg(HashAlg::Md5, "This is a test.", "TeSt", 99).unwrap();
+        assert_eq!(advance_otp(HashAlg::Md5, &otp_0, 0), Some(otp_0));
+        assert_eq!(advance_otp(HashAlg::Md5, &otp_0, 1), Some(otp_1));
+        assert_eq!(advance_otp(HashAlg::Md5, &otp_1, 98), Some(otp_99));
+        assert_ne!(advance_otp(HashAlg::Md4, &otp_1, 98), Some(otp_99));
+    }
+
+
+    #[test]
+    #[cfg(feature = "sha1")]
+    fn checks_chain_consistency () {
+        let otp_5 = calculate_otp_with_alg(HashAlg::Sha1, "This is a test.", "TeSt", 5).unwrap();
+        let otp_9 = calculate_otp_with_alg(HashAlg::Sha1, "This is a test.", "TeSt", 9).unwrap();
+        let other_9 = calculate_otp_with_alg(HashAlg::Sha1, "This is a test.", "TeSt2", 9).unwrap();
+        assert_eq!(same_chain(HashAlg::Sha1, &otp_5, 5, &otp_9, 9), Some(true));
+        assert_eq!(same_chain(HashAlg::Sha1, &otp_9, 9, &otp_5, 5), Some(true));
+        assert_eq!(same_chain(HashAlg::Sha1, &otp_9, 9, &otp_9, 9), Some(true));
+        assert_eq!(same_chain(HashAlg::Sha1, &otp_5, 5, &other_9, 9), Some(false));
+        assert_eq!(same_chain(HashAlg::Sha1, &otp_5, 6, &otp_9, 9), Some(false));
+        assert_eq!(same_chain(HashAlg::Sha1, &otp_5, 0, &otp_9, DEFAULT_MAX_COUNT + 1), None);
+    }
+
 }
@@ -0,0 +1,47 @@
+//! Kotlin and Swift bindings, via UniFFI, for mobile authenticator apps.
+//! Everything goes in and out as strings, and OTP values are passed around as
+//! contiguous hexadecimal, as in the `wasm` module.
+//!
+//! To generate the bindings, build this crate as a dynamic library with the
+//! `uniffi` feature, then run `uniffi-bindgen` on it, for instance:
+//!
+//! ```text
+//! cargo rustc --release --features uniffi --crate-type cdylib
+//! uniffi-bindgen generate --library target/release/librfc2289_otp.so --language kotlin --out-dir out
+//! ```
+use alloc::string::String;
+use crate::bindings;
+pub use crate::bindings::{BindingError, Challenge};
+
+/// Parse an OTP challenge string.
+#[uniffi::export]
+pub fn parse_challenge (challenge: &str) -> Result<Challenge, BindingError> {
+    bindings::parse_challenge(challenge)
+}
+
+/// Calculate an OTP value, returned as contiguous lowercase hexadecimal.
+#[uniffi::export]
+pub fn calculate_otp (alg: &str, passphrase: &str, seed: &str, count: u32) -> Result<String, BindingError> {
+    bindings::calculate_otp(alg, passphrase, seed, count)
+}
+
+/// Calculate the response to an OTP challenge string, such as
+/// `otp-md5 499 ke1234`, as a complete `word:` or `hex:` response.
+#[uniffi::export]
+pub fn respond_to_challenge (challenge: &str, passphrase: &str, words: bool) -> Result<String, BindingError> {
+    bindings::respond_to_challenge(challenge, passphrase, words)
+}
+
+/// Convert an OTP value in hexadecimal to six words from the standard
+/// dictionary.
+#[uniffi::export]
+pub fn hex_to_words (hex: &str) -> Result<String, BindingError> {
+    bindings::hex_to_words(hex)
+}
+
+/// Convert six words from the standard dictionary to an OTP value in
+/// contiguous lowercase hexadecimal.
+#[uniffi::export]
+pub fn words_to_hex (words: &str) -> Result<String, BindingError> {
+    bindings::words_to_hex(words)
+}
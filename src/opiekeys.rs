@@ -0,0 +1,224 @@
+//! Reading and writing entries of the `/etc/opiekeys` file used by OPIE, which
+//! stores, for each user, the hash count, seed, and OTP value that the next
+//! response will be checked against, one user per line:
+//!
+//! ```text
+//! jdoe 0499 ke1234           8e8f4a71bbb6ec6c  Apr 01,2024 12:00:00
+//! ```
+//!
+//! OPIE always uses MD5, so the file does not record the hash algorithm.
+use core::fmt;
+use hex::FromHex;
+#[cfg(all(feature = "parsing-response", feature = "words"))]
+use crate::{server::{import_state, OtpStore}, ReportIssue, Seed};
+
+/// The default location of the OPIE key file.
+pub const DEFAULT_OPIEKEYS_PATH: &str = "/etc/opiekeys";
+
+/// One line of an OPIE key file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OpieKeyEntry <'a> {
+    /// The user name.
+    pub user: &'a str,
+    /// The hash count of the stored OTP value. The next challenge will have a
+    /// hash count of one less than this.
+    pub count: usize,
+    /// The seed.
+    pub seed: &'a str,
+    /// The OTP value that the next response will be checked against.
+    pub otp: [u8; 8],
+    /// When the entry was last changed, in the format `Apr 01,2024 12:00:00`.
+    /// This is informational only, and is not parsed.
+    pub date: &'a str,
+}
+
+/// Parse a line of an OPIE key file, returning `None` if it is malformed.
+pub fn parse_opiekeys_line <'a> (line: &'a str) -> Option<OpieKeyEntry<'a>> {
+    let mut rest = line.trim_end_matches(['\r', '\n']);
+    let mut next_token = || {
+        rest = rest.trim_start_matches(' ');
+        let end = rest.find(' ').unwrap_or(rest.len());
+        let (token, r) = rest.split_at(end);
+        rest = r;
+        Some(token).filter(|t| !t.is_empty())
+    };
+    let user = next_token()?;
+    let count = next_token()?.parse::<usize>().ok()?;
+    let seed = next_token()?;
+    let otp = <[u8; 8]>::from_hex(next_token()?).ok()?;
+    let date = rest.trim();
+    Some(OpieKeyEntry { user, count, seed, otp, date })
+}
+
+impl fmt::Display for OpieKeyEntry<'_> {
+
+    /// Format the entry as OPIE does, without a trailing newline.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {:04} {:<16} ", self.user, self.count, self.seed)?;
+        for b in self.otp {
+            write!(f, "{:02x}", b)?;
+        }
+        write!(f, "  {}", self.date)
+    }
+
+}
+
+/// How many lines of a key file had a particular problem, and the line number,
+/// counting from one, of the first of them.
+#[cfg(all(feature = "parsing-response", feature = "words"))]
+pub type MigrationIssue = ReportIssue<usize>;
+
+/// The result of [migrate_opiekeys].
+#[cfg(all(feature = "parsing-response", feature = "words"))]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MigrationReport {
+    /// The number of entries written to the store.
+    pub migrated: usize,
+    /// Lines that could not be parsed, and were skipped.
+    pub malformed: MigrationIssue,
+    /// Entries whose seed is not one to sixteen alphanumeric characters, as
+    /// [Seed] requires, and were skipped.
+    pub invalid_seed: MigrationIssue,
+    /// Entries whose hash algorithm's feature flag is not enabled, so that
+    /// their responses could not be verified, and were skipped.
+    pub unsupported_alg: MigrationIssue,
+    /// Entries for users who were already in the store, or who appeared
+    /// earlier in the file, and were skipped rather than overwriting them.
+    pub duplicate_user: MigrationIssue,
+    /// Entries that were migrated with a count of zero, so that no challenge
+    /// can be issued until the user re-initializes.
+    pub exhausted: MigrationIssue,
+    /// Entries that were migrated using a deprecated hash algorithm, such as
+    /// the MD5 that OPIE always uses. These users should re-initialize to
+    /// SHA1.
+    pub deprecated_alg: MigrationIssue,
+}
+
+/// Migrate the entries of an OPIE key file, one per item of `lines`, into
+/// `store`, so that an OPIE server can be replaced by an
+/// [OtpServer](crate::server::OtpServer). Blank lines and lines starting with
+/// `#` are ignored. Entries that cannot be migrated are skipped and reported,
+/// rather than stopping the migration.
+///
+/// Each line is imported by [import_state], so lines in logdaemon's layout,
+/// which records the hash algorithm, are migrated using that algorithm, and
+/// lines in OPIE's layout are migrated using MD5.
+///
+/// `lines` are the lines of a file that has already been read. To migrate
+/// from a `std::io::BufRead`, collect its `lines()` into a
+/// `Result<Vec<String>, _>` first, so that a read error is not mistaken for
+/// the end of the file.
+///
+/// ```rust
+/// use rfc2289_otp::{opiekeys::migrate_opiekeys, server::{MemoryStore, OtpStore}};
+/// let file = "jdoe 0499 ke1234 8e8f4a71bbb6ec6c  Apr 01,2024 12:00:00\nbroken\n";
+/// let mut store = MemoryStore::new();
+/// let report = migrate_opiekeys(file.lines(), &mut store);
+/// assert_eq!(report.migrated, 1);
+/// assert_eq!(report.malformed.first, Some(2));
+/// assert_eq!(store.load("jdoe").unwrap().count, 499);
+/// ```
+#[cfg(all(feature = "parsing-response", feature = "words"))]
+pub fn migrate_opiekeys <L, S> (lines: impl IntoIterator<Item = L>, store: &mut S) -> MigrationReport
+    where
+        L: AsRef<str>,
+        S: OtpStore + ?Sized,
+{
+    let mut report = MigrationReport::default();
+    for (i, line) in lines.into_iter().enumerate() {
+        let line_number = i + 1;
+        let line = line.as_ref();
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let Some(imported) = import_state(line) else {
+            if parse_opiekeys_line(line).is_some_and(|entry| Seed::new(entry.seed).is_none()) {
+                report.invalid_seed.record(line_number);
+            } else {
+                report.malformed.record(line_number);
+            }
+            continue;
+        };
+        let state = imported.state;
+        if !state.alg.is_enabled() {
+            report.unsupported_alg.record(line_number);
+            continue;
+        }
+        if store.load(imported.user).is_some() {
+            report.duplicate_user.record(line_number);
+            continue;
+        }
+        store.save(imported.user, state);
+        report.migrated += 1;
+        if state.count == 0 {
+            report.exhausted.record(line_number);
+        }
+        if state.alg.is_deprecated() {
+            report.deprecated_alg.record(line_number);
+        }
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+
+    #[test]
+    fn parses_and_formats_entries () {
+        let line = "jdoe 0499 ke1234           8e8f4a71bbb6ec6c  Apr 01,2024 12:00:00";
+        let entry = parse_opiekeys_line(line).unwrap();
+        assert_eq!(entry, OpieKeyEntry {
+            user: "jdoe",
+            count: 499,
+            seed: "ke1234",
+            otp: [ 0x8e, 0x8f, 0x4a, 0x71, 0xbb, 0xb6, 0xec, 0x6c ],
+            date: "Apr 01,2024 12:00:00",
+        });
+        assert_eq!(entry.to_string(), line);
+        assert!(parse_opiekeys_line("jdoe 0499 ke1234").is_none());
+        assert!(parse_opiekeys_line("jdoe abc ke1234 8e8f4a71bbb6ec6c").is_none());
+        assert!(parse_opiekeys_line("").is_none());
+    }
+
+    #[test]
+    #[cfg(all(feature = "parsing-response", feature = "words", feature = "md5"))]
+    fn migrates_opiekeys () {
+        use crate::server::{MemoryStore, OtpStore};
+        use crate::HashAlg;
+        let file = [
+            "# migrated from host1",
+            "jdoe 0499 ke1234           8e8f4a71bbb6ec6c  Apr 01,2024 12:00:00",
+            "",
+            "asmith 0000 ab5678           8e8f4a71bbb6ec6c  Apr 01,2024 12:00:00",
+            "jdoe 0099 ke9999           8e8f4a71bbb6ec6c  Apr 01,2024 12:00:00",
+            "bjones 0099 ke_99           8e8f4a71bbb6ec6c  Apr 01,2024 12:00:00",
+            "cwu 0099",
+            "dlee sha1 0099 ke4321 8e8f4a71bbb6ec6c  Apr 01,2024 12:00:00",
+        ];
+        let mut store = MemoryStore::new();
+        let report = migrate_opiekeys(file, &mut store);
+        let unsupported_alg = if HashAlg::Sha1.is_enabled() {
+            MigrationIssue::default()
+        } else {
+            MigrationIssue { count: 1, first: Some(8) }
+        };
+        assert_eq!(report, MigrationReport {
+            migrated: 2 + usize::from(unsupported_alg.is_empty()),
+            malformed: MigrationIssue { count: 1, first: Some(7) },
+            invalid_seed: MigrationIssue { count: 1, first: Some(6) },
+            unsupported_alg,
+            duplicate_user: MigrationIssue { count: 1, first: Some(5) },
+            exhausted: MigrationIssue { count: 1, first: Some(4) },
+            deprecated_alg: MigrationIssue { count: 2, first: Some(2) },
+        });
+        let jdoe = store.load("jdoe").unwrap();
+        assert_eq!((jdoe.alg, jdoe.count, jdoe.seed.as_str()), (HashAlg::Md5, 499, "ke1234"));
+        assert!(store.load("bjones").is_none());
+        if HashAlg::Sha1.is_enabled() {
+            assert_eq!(store.load("dlee").unwrap().alg, HashAlg::Sha1);
+        }
+    }
+
+}
@@ -0,0 +1,136 @@
+//! Owned, eagerly decoded responses, for servers that want both the original
+//! text of an OTP value, such as for audit logs, and the 64 bits it decodes
+//! to, without parsing the response again or keeping it borrowed.
+use alloc::string::String;
+use crate::{parse_otp_response, HexOrWords, OTPResponse};
+
+/// An OTP value from a response, with its original text and the 64 bits it
+/// decodes to.
+#[derive(Clone, PartialEq, Eq)]
+pub struct HexOrWordsOwned {
+    /// The value as it appeared in the response, without any prefix.
+    pub text: String,
+    /// The decoded value, or `None` if it is words that could not be decoded
+    /// using the standard dictionary, or if the `words` feature is disabled.
+    pub bytes: Option<[u8; 8]>,
+    /// Whether the value was hexadecimal, rather than words.
+    pub is_hex: bool,
+}
+
+impl HexOrWordsOwned {
+
+    /// Decode `value`, which was parsed from `text`.
+    fn new (text: &str, value: &HexOrWords<'_>) -> Self {
+        let bytes = match value {
+            HexOrWords::Hex(h) => Some(*h),
+            #[cfg(feature = "words")]
+            HexOrWords::Words(w) => crate::decode_word_str(w).ok(),
+            #[cfg(not(feature = "words"))]
+            HexOrWords::Words(_) => None,
+        };
+        HexOrWordsOwned { text: String::from(text), bytes, is_hex: matches!(value, HexOrWords::Hex(_)) }
+    }
+
+}
+
+/// A parsed OTP response that owns its contents, produced by
+/// [parse_otp_response_owned].
+#[derive(Clone, PartialEq, Eq)]
+pub enum OTPResponseOwned {
+    /// A `hex:` or `word:` response.
+    Current(HexOrWordsOwned),
+    /// An `init-hex:` or `init-word:` response.
+    Init {
+        /// The OTP value for the current sequence.
+        current_otp: HexOrWordsOwned,
+        /// The first OTP value of the new sequence.
+        new_otp: HexOrWordsOwned,
+        /// The hash algorithm of the new sequence.
+        new_alg: String,
+        /// The count of the new sequence.
+        new_seq_num: usize,
+        /// The seed of the new sequence.
+        new_seed: String,
+        /// Any fields after the new OTP, as in [crate::OTPInit::extra].
+        extra: Option<String>,
+    },
+}
+
+impl OTPResponseOwned {
+
+    /// The OTP value for the current sequence, which is what the server
+    /// verifies.
+    pub fn current_otp (&self) -> &HexOrWordsOwned {
+        match self {
+            OTPResponseOwned::Current(otp) => otp,
+            OTPResponseOwned::Init { current_otp, .. } => current_otp,
+        }
+    }
+
+}
+
+/// Parse an OTP response as [parse_otp_response] does, eagerly decoding its
+/// OTP values and keeping their original text.
+///
+/// ```rust
+/// use rfc2289_otp::{parse_otp_response_owned, OTPResponseOwned};
+/// let response = parse_otp_response_owned("word:inch sea anne long ahem tour").unwrap();
+/// let otp = response.current_otp();
+/// assert_eq!(otp.text, "inch sea anne long ahem tour");
+/// assert_eq!(otp.bytes, Some([ 0x9E, 0x87, 0x61, 0x34, 0xD9, 0x04, 0x99, 0xDD ]));
+/// ```
+pub fn parse_otp_response_owned (s: &str) -> Option<OTPResponseOwned> {
+    let response = parse_otp_response(s)?;
+    let (_, rest) = s.split_once(':')?;
+    match response {
+        OTPResponse::Current(otp) => Some(OTPResponseOwned::Current(HexOrWordsOwned::new(rest, &otp))),
+        OTPResponse::Init(init) => {
+            let mut sections = rest.split(':');
+            let current_otp = sections.next()?;
+            sections.next()?;
+            let new_otp = sections.next()?;
+            Some(OTPResponseOwned::Init {
+                current_otp: HexOrWordsOwned::new(current_otp, &init.current_otp),
+                new_otp: HexOrWordsOwned::new(new_otp, &init.new_otp),
+                new_alg: String::from(init.new_alg),
+                new_seq_num: init.new_seq_num,
+                new_seed: String::from(init.new_seed),
+                extra: init.extra.map(String::from),
+            })
+        },
+    }
+}
+
+#[cfg(all(test, feature = "words"))]
+mod tests {
+
+    use super::*;
+
+    const OTP: [u8; 8] = [ 0x9E, 0x87, 0x61, 0x34, 0xD9, 0x04, 0x99, 0xDD ];
+
+    #[test]
+    fn parses_responses_eagerly () {
+        let response = parse_otp_response_owned("hex:9e87 6134 d904 99dd").unwrap();
+        assert_eq!(response, OTPResponseOwned::Current(HexOrWordsOwned {
+            text: String::from("9e87 6134 d904 99dd"),
+            bytes: Some(OTP),
+            is_hex: true,
+        }));
+
+        let response = parse_otp_response_owned("init-word:INCH SEA ANNE LONG AHEM TOUT:md5 499 ke1235:INCH SEA ANNE LONG AHEM TOUR").unwrap();
+        let OTPResponseOwned::Init { current_otp, new_otp, new_alg, new_seq_num, new_seed, extra } = &response else {
+            panic!("expected an init response");
+        };
+        assert_eq!(current_otp.text, "INCH SEA ANNE LONG AHEM TOUT");
+        // The checksum is wrong, but the text is kept for the audit log.
+        assert_eq!(current_otp.bytes, None);
+        assert!(!current_otp.is_hex);
+        assert_eq!(new_otp.text, "INCH SEA ANNE LONG AHEM TOUR");
+        assert_eq!(new_otp.bytes, Some(OTP));
+        assert_eq!((new_alg.as_str(), *new_seq_num, new_seed.as_str()), ("md5", 499, "ke1235"));
+        assert_eq!(extra, &None);
+        assert_eq!(response.current_otp(), current_otp);
+        assert_eq!(parse_otp_response_owned("hex:9e87"), None);
+    }
+
+}
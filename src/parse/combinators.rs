@@ -0,0 +1,133 @@
+//! Small parsers for the pieces of the OTP challenge and response grammars of
+//! [IETF RFC 2289](https://www.rfc-editor.org/rfc/rfc2289.html) and
+//! [IETF RFC 2243](https://www.rfc-editor.org/rfc/rfc2243), for protocols that
+//! embed OTP fields in larger messages.
+//!
+//! In the style of `nom`, each parser takes the input, and returns the rest of
+//! the input along with what it parsed, or `None` if the input does not start
+//! with what it parses. Parsers of whitespace-delimited fields skip any ASCII
+//! whitespace before them, but not after.
+//!
+//! ```rust
+//! use rfc2289_otp::parse::combinators::{challenge_alg, count, seed};
+//! let banner = "331 Response to otp-md5 499 ke1234 required for jdoe.";
+//! let start = banner.find("otp-").unwrap();
+//! let (rest, alg) = challenge_alg(&banner[start..]).unwrap();
+//! let (rest, count) = count(rest).unwrap();
+//! let (rest, seed) = seed(rest).unwrap();
+//! assert_eq!((alg, count, seed, rest), ("md5", 499, "ke1234", " required for jdoe."));
+//! ```
+use crate::{decode_hex_otp, is_valid_seed};
+
+/// Parse a token: the next run of characters other than ASCII whitespace.
+pub fn token (input: &str) -> Option<(&str, &str)> {
+    let input = input.trim_start_matches(|c: char| c.is_ascii_whitespace());
+    if input.is_empty() {
+        return None;
+    }
+    let end = input.find(|c: char| c.is_ascii_whitespace()).unwrap_or(input.len());
+    let (token, rest) = input.split_at(end);
+    Some((rest, token))
+}
+
+/// Parse the algorithm of a challenge, such as `otp-md5`, returning the name
+/// after the `otp-`, such as `md5`.
+pub fn challenge_alg (input: &str) -> Option<(&str, &str)> {
+    let input = input.trim_start_matches(|c: char| c.is_ascii_whitespace());
+    token(input.strip_prefix("otp-")?)
+}
+
+/// Parse a token that is a decimal count, such as the sequence number of a
+/// challenge.
+pub fn count (input: &str) -> Option<(&str, usize)> {
+    let (rest, count) = token(input)?;
+    Some((rest, count.parse::<usize>().ok()?))
+}
+
+/// Parse a token that is a valid seed per [is_valid_seed].
+pub fn seed (input: &str) -> Option<(&str, &str)> {
+    token(input).filter(|(_, seed)| is_valid_seed(seed))
+}
+
+/// Parse sixteen hexadecimal digits, in either case, with any spaces and tabs
+/// between them, as [decode_hex_otp] does. Parsing stops after the sixteenth
+/// digit, but fails if a seventeenth immediately follows it.
+pub fn hex_group (input: &str) -> Option<(&str, [u8; 8])> {
+    let input = input.trim_start_matches([' ', '\t']);
+    let mut digits = 0;
+    let mut end = input.len();
+    for (i, b) in input.bytes().enumerate() {
+        if b.is_ascii_hexdigit() {
+            digits += 1;
+            if digits == 16 {
+                end = i + 1;
+                break;
+            }
+        }
+        else if b != b' ' && b != b'\t' {
+            end = i;
+            break;
+        }
+    }
+    let (group, rest) = input.split_at(end);
+    if rest.as_bytes().first().is_some_and(u8::is_ascii_hexdigit) {
+        return None;
+    }
+    Some((rest, decode_hex_otp(group)?))
+}
+
+/// Parse six words of one to four ASCII letters, separated by ASCII
+/// whitespace, returning them as they appear in the input. The words are not
+/// looked up in the dictionary.
+pub fn six_words (input: &str) -> Option<(&str, &str)> {
+    let input = input.trim_start_matches(|c: char| c.is_ascii_whitespace());
+    let mut rest = input;
+    for _ in 0..6 {
+        let (after, word) = token(rest)?;
+        if !(1..=4).contains(&word.len()) || !word.bytes().all(|b| b.is_ascii_alphabetic()) {
+            return None;
+        }
+        rest = after;
+    }
+    let (words, rest) = input.split_at(input.len() - rest.len());
+    Some((rest, words))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_tokens () {
+        assert_eq!(token("  md5 499"), Some((" 499", "md5")));
+        assert_eq!(token("ke1234"), Some(("", "ke1234")));
+        assert_eq!(token(" \t"), None);
+        assert_eq!(challenge_alg("otp-sha1 99 seed"), Some((" 99 seed", "sha1")));
+        assert_eq!(challenge_alg("s/key 99 seed"), None);
+        assert_eq!(count(" 499 ke1234"), Some((" ke1234", 499)));
+        assert_eq!(count(" -1 ke1234"), None);
+        assert_eq!(seed(" ke1234 ext"), Some((" ext", "ke1234")));
+        assert_eq!(seed(" ke:1234"), None);
+    }
+
+    #[test]
+    fn parses_hex_groups () {
+        let otp = [0x9e, 0x87, 0x61, 0x34, 0xd9, 0x04, 0x99, 0xdd];
+        assert_eq!(hex_group("9E87 6134 D904 99DD"), Some(("", otp)));
+        assert_eq!(hex_group(" 9e876134d90499dd:md5 499"), Some((":md5 499", otp)));
+        assert_eq!(hex_group("9E87 6134 D904 99DD 12"), Some((" 12", otp)));
+        assert_eq!(hex_group("9E876134D90499DD12"), None);
+        assert_eq!(hex_group("9E87 6134 D904"), None);
+    }
+
+    #[test]
+    fn parses_six_words () {
+        let words = "INCH SEA ANNE LONG AHEM TOUR";
+        assert_eq!(six_words(words), Some(("", words)));
+        assert_eq!(six_words(" inch  sea anne long ahem tour:md5"), None);
+        assert_eq!(six_words(" inch  sea anne long ahem tour :md5"), Some((" :md5", "inch  sea anne long ahem tour")));
+        assert_eq!(six_words("INCH SEA ANNE LONG AHEM"), None);
+        assert_eq!(six_words("INCH SEA ANNE LONG AHEM TOURS"), None);
+    }
+
+}
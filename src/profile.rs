@@ -0,0 +1,124 @@
+//! Profiles selecting the formatting and parsing conventions of particular
+//! implementations, so that output can be compared byte-for-byte with theirs.
+use core::fmt;
+#[cfg(feature = "alloc")]
+use alloc::string::String;
+#[cfg(feature = "words")]
+use crate::{decode_word_str, decode_word_str_with, word_index, WordDecodeError};
+use crate::{decode_hex_otp, write_hex, HexStyle};
+
+/// A set of conventions for formatting and parsing OTP values.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Profile {
+    /// The conventions of [IETF RFC 2289](https://www.rfc-editor.org/rfc/rfc2289.html):
+    /// hexadecimal is printed in four groups of four uppercase digits, and
+    /// parsed as in [decode_hex_otp] and [crate::decode_word_str].
+    #[default]
+    Rfc2289,
+    /// The conventions of OpenBSD's `skey(1)`: hexadecimal is printed as
+    /// contiguous lowercase digits. When parsing, any ASCII whitespace,
+    /// including line breaks, may separate hex digits or words, and, within
+    /// words, `0`, `1`, and `5` are read as `O`, `L`, and `S`.
+    OpenBsdSkey,
+}
+
+impl Profile {
+
+    /// The style in which this profile prints hexadecimal.
+    pub const fn hex_style (self) -> HexStyle {
+        match self {
+            Profile::Rfc2289 => HexStyle::CANONICAL,
+            Profile::OpenBsdSkey => HexStyle::CONTIGUOUS_LOWERCASE,
+        }
+    }
+
+    /// Write a 64-bit value as hexadecimal in this profile's style, without a
+    /// prefix.
+    pub fn write_hex <W: fmt::Write> (self, w: &mut W, otp: &[u8; 8]) -> fmt::Result {
+        write_hex(w, otp, self.hex_style())
+    }
+
+    /// Format a 64-bit value as hexadecimal in this profile's style, without a
+    /// prefix.
+    #[cfg(feature = "alloc")]
+    pub fn format_hex (self, otp: &[u8; 8]) -> String {
+        crate::format_hex(otp, self.hex_style())
+    }
+
+    /// Decode 64 bits of hexadecimal, as typed by a user.
+    pub fn decode_hex (self, s: &str) -> Option<[u8; 8]> {
+        match self {
+            Profile::Rfc2289 => decode_hex_otp(s),
+            Profile::OpenBsdSkey => {
+                let mut buf = [0u8; 16];
+                let mut digits = s.bytes().filter(|b| !b.is_ascii_whitespace());
+                for b in buf.iter_mut() {
+                    *b = digits.next()?;
+                }
+                if digits.next().is_some() {
+                    return None;
+                }
+                decode_hex_otp(core::str::from_utf8(&buf).ok()?)
+            },
+        }
+    }
+
+    /// Decode six words from the standard dictionary, as typed by a user,
+    /// validating the checksum.
+    #[cfg(feature = "words")]
+    pub fn decode_words (self, s: &str) -> Result<[u8; 8], WordDecodeError> {
+        match self {
+            Profile::Rfc2289 => decode_word_str(s),
+            Profile::OpenBsdSkey => decode_word_str_with(s, skey_word_index),
+        }
+    }
+
+}
+
+/// Look up a word as OpenBSD's `skey(1)` does, reading the digits `0`, `1`,
+/// and `5` as the letters they resemble.
+#[cfg(feature = "words")]
+fn skey_word_index (word: &str) -> Option<u16> {
+    let mut buf = [0u8; 4];
+    let buf = buf.get_mut(..word.len())?;
+    for (b, c) in buf.iter_mut().zip(word.bytes()) {
+        *b = match c {
+            b'0' => b'O',
+            b'1' => b'L',
+            b'5' => b'S',
+            c => c,
+        };
+    }
+    word_index(core::str::from_utf8(buf).ok()?)
+}
+
+#[cfg(all(test, feature = "alloc", feature = "words"))]
+mod tests {
+
+    use super::*;
+
+    const OTP: [u8; 8] = [ 0x9E, 0x87, 0x61, 0x34, 0xD9, 0x04, 0x99, 0xDD ];
+
+    #[test]
+    fn formats_per_profile () {
+        assert_eq!(Profile::Rfc2289.format_hex(&OTP), "9E87 6134 D904 99DD");
+        assert_eq!(Profile::OpenBsdSkey.format_hex(&OTP), "9e876134d90499dd");
+    }
+
+    #[test]
+    fn parses_per_profile () {
+        let spaced = "9e87\n6134 d904\r\n99dd";
+        assert_eq!(Profile::Rfc2289.decode_hex(spaced), None);
+        assert_eq!(Profile::OpenBsdSkey.decode_hex(spaced), Some(OTP));
+        assert_eq!(Profile::OpenBsdSkey.decode_hex("9e876134d90499d"), None);
+        assert_eq!(Profile::OpenBsdSkey.decode_hex("9e876134d90499ddd"), None);
+
+        let words = "inch  sea\tanne\nl0ng ahem t0ur";
+        assert_eq!(Profile::Rfc2289.decode_words(words), Err(WordDecodeError::UnknownWord { index: 3 }));
+        assert_eq!(Profile::OpenBsdSkey.decode_words(words), Ok(OTP));
+        assert_eq!(Profile::OpenBsdSkey.decode_words("INCH SEA ANNE LONG AHEM"), Err(WordDecodeError::WrongWordCount(5)));
+        assert_eq!(Profile::OpenBsdSkey.decode_words("INCH SEA ANNE LONG AHEM TOUT"), Err(WordDecodeError::BadChecksum));
+    }
+
+}
@@ -0,0 +1,478 @@
+//! Provisioning of new OTP sequences: generating seeds, and calculating the
+//! initial OTP value that a server stores.
+//!
+//! Randomness comes from a [SeedRng], which embedded users can implement for
+//! their hardware random number generator. With the `rand` feature, it is
+//! implemented for every `rand_core::TryRngCore`, and with the `getrandom`
+//! feature, [GetrandomRng] draws from the operating system. If the random
+//! number generator fails, an error is returned, rather than a panic.
+//!
+//! A [SeedPolicy] and a [CountPolicy] control how the seed and initial count
+//! of a new sequence are chosen, so that operators can avoid giving every
+//! user the same initial count, which makes precomputation marginally easier.
+//!
+//! New sequences are returned as a [ProvisioningOutput], which never holds the
+//! passphrase, and only reveals the OTP value when asked, so that provisioning
+//! scripts do not print or log secrets by accident.
+use core::fmt;
+use crate::{
+    calculate_otp_with_options,
+    is_valid_seed,
+    CalculateError,
+    CalculateOptions,
+    HashAlg,
+    Seed,
+    MAX_SEED_LEN,
+};
+use crate::redact::{redacted_debug, RevealDebug, REDACTED};
+
+/// The length of the seeds generated by [generate_seed].
+pub const GENERATED_SEED_LEN: usize = 10;
+
+/// The characters from which generated seeds are drawn. Seeds are
+/// case-insensitive, so only lowercase letters are used.
+const SEED_ALPHABET: &[u8; 36] = b"abcdefghijklmnopqrstuvwxyz0123456789";
+
+/// The hash count of new sequences by default, as in `opiepasswd`.
+pub const DEFAULT_INITIAL_COUNT: usize = 499;
+
+/// An error obtaining random bytes from a [SeedRng].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RngError;
+
+impl fmt::Display for RngError {
+
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("failed to obtain random bytes")
+    }
+
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for RngError {}
+
+/// A source of random bytes for generating seeds. This must be
+/// cryptographically secure: predictable seeds let an attacker precompute
+/// OTP sequences.
+pub trait SeedRng {
+
+    /// Fill `dst` with random bytes, or return an error if none could be
+    /// obtained.
+    fn fill_bytes (&mut self, dst: &mut [u8]) -> Result<(), RngError>;
+
+}
+
+#[cfg(feature = "rand")]
+impl <R: rand_core::TryRngCore + ?Sized> SeedRng for R {
+
+    fn fill_bytes (&mut self, dst: &mut [u8]) -> Result<(), RngError> {
+        self.try_fill_bytes(dst).map_err(|_| RngError)
+    }
+
+}
+
+/// A [SeedRng] that draws from the operating system via `getrandom`.
+#[cfg(feature = "getrandom")]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct GetrandomRng;
+
+#[cfg(feature = "getrandom")]
+impl SeedRng for GetrandomRng {
+
+    fn fill_bytes (&mut self, dst: &mut [u8]) -> Result<(), RngError> {
+        getrandom::fill(dst).map_err(|_| RngError)
+    }
+
+}
+
+/// An error provisioning a new OTP sequence.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ProvisioningError {
+    /// The random number generator failed.
+    Rng(RngError),
+    /// The [SeedPolicy] cannot produce a valid seed.
+    InvalidSeedPolicy,
+    /// The initial OTP value could not be calculated, such as because the
+    /// feature flag for the algorithm is not enabled.
+    Calculate(CalculateError),
+}
+
+impl fmt::Display for ProvisioningError {
+
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProvisioningError::Rng(e) => e.fmt(f),
+            ProvisioningError::InvalidSeedPolicy => f.write_str("seed policy cannot produce a valid seed"),
+            ProvisioningError::Calculate(e) => e.fmt(f),
+        }
+    }
+
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ProvisioningError {}
+
+impl From<RngError> for ProvisioningError {
+
+    fn from(e: RngError) -> Self {
+        ProvisioningError::Rng(e)
+    }
+
+}
+
+impl From<CalculateError> for ProvisioningError {
+
+    fn from(e: CalculateError) -> Self {
+        ProvisioningError::Calculate(e)
+    }
+
+}
+
+/// Generate a random seed of [GENERATED_SEED_LEN] lowercase alphanumeric
+/// characters.
+pub fn generate_seed <R: SeedRng + ?Sized> (rng: &mut R) -> Result<Seed, RngError> {
+    let mut bytes = [0u8; MAX_SEED_LEN];
+    fill_from_alphabet(rng, &mut bytes[..GENERATED_SEED_LEN], SEED_ALPHABET)?;
+    Ok(Seed { bytes, len: GENERATED_SEED_LEN as u8 })
+}
+
+/// Fill `out` with characters chosen uniformly at random from `alphabet`.
+fn fill_from_alphabet <R: SeedRng + ?Sized> (rng: &mut R, out: &mut [u8], alphabet: &[u8]) -> Result<(), RngError> {
+    // Reject values that would bias the choice of character.
+    let limit = 256 - 256 % alphabet.len();
+    let mut i = 0;
+    while i < out.len() {
+        let mut random = [0u8; MAX_SEED_LEN];
+        let random = &mut random[..out.len()];
+        rng.fill_bytes(random)?;
+        for &r in random.iter() {
+            if r as usize >= limit || i >= out.len() {
+                continue;
+            }
+            out[i] = alphabet[r as usize % alphabet.len()];
+            i += 1;
+        }
+    }
+    Ok(())
+}
+
+/// How the seed of a new sequence is chosen.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum SeedPolicy <'a> {
+    /// [GENERATED_SEED_LEN] random lowercase alphanumeric characters, as
+    /// generated by [generate_seed].
+    #[default]
+    Random,
+    /// A fixed prefix, such as two letters of the host name, followed by
+    /// random digits, to make up `len` characters in total, as in `ke1234`.
+    Prefixed {
+        /// The alphanumeric prefix.
+        prefix: &'a str,
+        /// The total length of the seed, which must be more than the length
+        /// of `prefix` and no more than [MAX_SEED_LEN].
+        len: usize,
+    },
+}
+
+impl SeedPolicy<'_> {
+
+    /// Generate a seed according to this policy, returning
+    /// [ProvisioningError::InvalidSeedPolicy] if the policy cannot produce a
+    /// valid seed.
+    pub fn generate <R: SeedRng + ?Sized> (&self, rng: &mut R) -> Result<Seed, ProvisioningError> {
+        match *self {
+            SeedPolicy::Random => Ok(generate_seed(rng)?),
+            SeedPolicy::Prefixed { prefix, len } => {
+                if len > MAX_SEED_LEN || len <= prefix.len() || !(prefix.is_empty() || is_valid_seed(prefix)) {
+                    return Err(ProvisioningError::InvalidSeedPolicy);
+                }
+                let mut bytes = [0u8; MAX_SEED_LEN];
+                bytes[..prefix.len()].copy_from_slice(prefix.as_bytes());
+                fill_from_alphabet(rng, &mut bytes[prefix.len()..len], b"0123456789")?;
+                Ok(Seed { bytes, len: len as u8 })
+            },
+        }
+    }
+
+}
+
+/// How the initial hash count of a new sequence is chosen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum CountPolicy {
+    /// Always the same count.
+    Fixed(usize),
+    /// A count chosen uniformly at random from `min` to `max`, inclusive.
+    Random {
+        /// The lowest count.
+        min: usize,
+        /// The highest count.
+        max: usize,
+    },
+}
+
+impl Default for CountPolicy {
+
+    fn default () -> Self {
+        CountPolicy::Fixed(DEFAULT_INITIAL_COUNT)
+    }
+
+}
+
+impl CountPolicy {
+
+    /// Choose an initial count according to this policy. If `max` is less
+    /// than `min`, `min` is always chosen.
+    pub fn initial_count <R: SeedRng + ?Sized> (&self, rng: &mut R) -> Result<usize, RngError> {
+        match *self {
+            CountPolicy::Fixed(count) => Ok(count),
+            CountPolicy::Random { min, max } if max <= min => Ok(min),
+            CountPolicy::Random { min, max } => {
+                let mut random = [0u8; 8];
+                let Some(span) = ((max - min) as u64).checked_add(1) else {
+                    rng.fill_bytes(&mut random)?;
+                    return Ok(min.wrapping_add(u64::from_le_bytes(random) as usize));
+                };
+                // Reject values that would bias the choice of count.
+                let limit = u64::MAX - u64::MAX % span;
+                loop {
+                    rng.fill_bytes(&mut random)?;
+                    let r = u64::from_le_bytes(random);
+                    if r < limit {
+                        return Ok(min + (r % span) as usize);
+                    }
+                }
+            },
+        }
+    }
+
+}
+
+/// A newly provisioned OTP sequence, as returned by [generate_new_chain].
+///
+/// Its `Display` output is the parameters of the sequence, in the form used by
+/// init responses, such as `sha1 499 abcdefghij`, which is safe to show to
+/// the user. Its `Debug` output redacts the OTP value, unless it is wrapped
+/// in [Reveal](crate::Reveal) or the `unredacted-debug` feature is enabled.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct ProvisioningOutput {
+    alg: HashAlg,
+    seed: Seed,
+    count: usize,
+    otp: [u8; 8],
+}
+
+impl ProvisioningOutput {
+
+    /// The hash algorithm of the sequence.
+    pub fn alg (&self) -> HashAlg {
+        self.alg
+    }
+
+    /// The generated seed.
+    pub fn seed (&self) -> &Seed {
+        &self.seed
+    }
+
+    /// The count of the OTP value. The server's first challenge will be for
+    /// one less than this.
+    pub fn count (&self) -> usize {
+        self.count
+    }
+
+    /// The OTP value for [ProvisioningOutput::count], which the server
+    /// stores. Anyone who knows it can respond to the first challenge, so it
+    /// should only be written to the server's store.
+    pub fn reveal_otp (&self) -> [u8; 8] {
+        self.otp
+    }
+
+}
+
+impl fmt::Display for ProvisioningOutput {
+
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} {}", self.alg, self.count, self.seed)
+    }
+
+}
+
+impl ProvisioningOutput {
+
+    fn fmt_redacted (&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ProvisioningOutput")
+            .field("alg", &self.alg)
+            .field("seed", &self.seed)
+            .field("count", &self.count)
+            .field("otp", &format_args!("{}", REDACTED))
+            .finish()
+    }
+
+}
+
+impl RevealDebug for ProvisioningOutput {
+
+    fn fmt_revealed (&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ProvisioningOutput")
+            .field("alg", &self.alg)
+            .field("seed", &self.seed)
+            .field("count", &self.count)
+            .field("otp", &self.otp)
+            .finish()
+    }
+
+}
+
+redacted_debug!(ProvisioningOutput);
+
+/// Set up a new OTP sequence: generate a random seed, and calculate the OTP
+/// value for `passphrase` at `count`, which the server stores so that it can
+/// verify the response to its first challenge, which will have a hash count of
+/// `count - 1`.
+///
+/// Returns an error if the random number generator fails, if the feature flag
+/// for `alg` is not enabled, or if `count` exceeds
+/// [DEFAULT_MAX_COUNT](crate::DEFAULT_MAX_COUNT).
+pub fn generate_new_chain <R: SeedRng + ?Sized> (
+    rng: &mut R,
+    alg: HashAlg,
+    passphrase: &str,
+    count: usize,
+) -> Result<ProvisioningOutput, ProvisioningError> {
+    let seed = generate_seed(rng)?;
+    provision(alg, passphrase, seed, count)
+}
+
+/// Like [generate_new_chain], but the seed and count are chosen according to
+/// the given policies.
+///
+/// Returns an error if the random number generator fails, if `seed_policy`
+/// cannot produce a valid seed, if the feature flag for `alg` is not enabled,
+/// or if the chosen count exceeds [DEFAULT_MAX_COUNT](crate::DEFAULT_MAX_COUNT).
+pub fn generate_new_chain_with_policies <R: SeedRng + ?Sized> (
+    rng: &mut R,
+    alg: HashAlg,
+    passphrase: &str,
+    seed_policy: SeedPolicy<'_>,
+    count_policy: CountPolicy,
+) -> Result<ProvisioningOutput, ProvisioningError> {
+    let seed = seed_policy.generate(rng)?;
+    let count = count_policy.initial_count(rng)?;
+    provision(alg, passphrase, seed, count)
+}
+
+/// Calculate the OTP value of a new sequence with the chosen seed and count.
+fn provision (alg: HashAlg, passphrase: &str, seed: Seed, count: usize) -> Result<ProvisioningOutput, ProvisioningError> {
+    let otp = calculate_otp_with_options(alg.name(), passphrase, seed.as_str(), count, &CalculateOptions::default())?;
+    crate::otp_event!(info, alg = alg.name(), count, seed = seed.as_str(), "OTP sequence provisioned");
+    Ok(ProvisioningOutput { alg, seed, count, otp })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A predictable RNG, for testing.
+    pub(crate) struct CountingRng(pub u8);
+
+    impl SeedRng for CountingRng {
+
+        fn fill_bytes (&mut self, dst: &mut [u8]) -> Result<(), RngError> {
+            for b in dst {
+                *b = self.0;
+                self.0 = self.0.wrapping_add(1);
+            }
+            Ok(())
+        }
+
+    }
+
+    /// An RNG that always fails, for testing.
+    struct FailingRng;
+
+    impl SeedRng for FailingRng {
+
+        fn fill_bytes (&mut self, _: &mut [u8]) -> Result<(), RngError> {
+            Err(RngError)
+        }
+
+    }
+
+    #[test]
+    fn generates_seeds () {
+        let seed = generate_seed(&mut CountingRng(0)).unwrap();
+        assert_eq!(seed.as_str(), "abcdefghij");
+        // 250 and 251 are used; 252 through 255 are rejected.
+        let seed = generate_seed(&mut CountingRng(250)).unwrap();
+        assert_eq!(seed.as_str(), "89abcdefgh");
+        assert_eq!(Seed::new("ke1234").unwrap().as_str(), "ke1234");
+        assert_eq!(Seed::new(""), None);
+        assert_eq!(Seed::new("ke 1234"), None);
+        assert_eq!(Seed::new("abcdefghijklmnopq"), None);
+    }
+
+    #[test]
+    fn generates_seeds_and_counts_per_policy () {
+        let prefixed = SeedPolicy::Prefixed { prefix: "ke", len: 6 };
+        // 250 through 255 are rejected for digits.
+        assert_eq!(prefixed.generate(&mut CountingRng(248)).unwrap().as_str(), "ke8901");
+        assert_eq!(SeedPolicy::Prefixed { prefix: "", len: 4 }.generate(&mut CountingRng(0)).unwrap().as_str(), "0123");
+        assert_eq!(SeedPolicy::Prefixed { prefix: "ke", len: 2 }.generate(&mut CountingRng(0)), Err(ProvisioningError::InvalidSeedPolicy));
+        assert_eq!(SeedPolicy::Prefixed { prefix: "k:", len: 6 }.generate(&mut CountingRng(0)), Err(ProvisioningError::InvalidSeedPolicy));
+        assert_eq!(SeedPolicy::Prefixed { prefix: "ke", len: 17 }.generate(&mut CountingRng(0)), Err(ProvisioningError::InvalidSeedPolicy));
+        assert_eq!(SeedPolicy::Random.generate(&mut CountingRng(0)).unwrap().as_str(), "abcdefghij");
+        assert_eq!(SeedPolicy::Random.generate(&mut FailingRng), Err(ProvisioningError::Rng(RngError)));
+
+        assert_eq!(CountPolicy::default().initial_count(&mut CountingRng(0)).unwrap(), DEFAULT_INITIAL_COUNT);
+        assert_eq!(CountPolicy::Random { min: 400, max: 300 }.initial_count(&mut CountingRng(0)).unwrap(), 400);
+        let random = CountPolicy::Random { min: 300, max: 599 };
+        // 0x0706050403020100 % 300 == 28
+        assert_eq!(random.initial_count(&mut CountingRng(0)).unwrap(), 328);
+        let mut rng = CountingRng(0);
+        assert!((0..100).all(|_| (300..=599).contains(&random.initial_count(&mut rng).unwrap())));
+        assert_eq!(CountPolicy::Random { min: 0, max: usize::MAX }.initial_count(&mut CountingRng(1)).unwrap(), 0x0807_0605_0403_0201);
+        assert_eq!(random.initial_count(&mut FailingRng), Err(RngError));
+    }
+
+    #[test]
+    #[cfg(feature = "getrandom")]
+    fn generates_seeds_from_the_os () {
+        let seed = generate_seed(&mut GetrandomRng).unwrap();
+        assert_eq!(seed.as_str().len(), GENERATED_SEED_LEN);
+        assert!(seed.as_str().bytes().all(|b| SEED_ALPHABET.contains(&b)));
+    }
+
+    #[test]
+    #[cfg(feature = "sha1")]
+    fn generates_new_chains () {
+        let output = generate_new_chain(&mut CountingRng(0), HashAlg::Sha1, "This is a test.", 499).unwrap();
+        assert_eq!(output.alg(), HashAlg::Sha1);
+        assert_eq!(output.seed().as_str(), "abcdefghij");
+        assert_eq!(output.count(), 499);
+        assert_eq!(Some(output.reveal_otp()), crate::calculate_otp_with_alg(HashAlg::Sha1, "This is a test.", "abcdefghij", 499));
+        assert_eq!(
+            generate_new_chain(&mut FailingRng, HashAlg::Sha1, "This is a test.", 499),
+            Err(ProvisioningError::Rng(RngError)),
+        );
+        assert_eq!(
+            generate_new_chain(&mut CountingRng(0), HashAlg::Sha1, "This is a test.", usize::MAX),
+            Err(ProvisioningError::Calculate(CalculateError::CountTooLarge(crate::DEFAULT_MAX_COUNT))),
+        );
+    }
+
+    #[test]
+    #[cfg(all(feature = "sha1", feature = "alloc", not(feature = "unredacted-debug")))]
+    fn does_not_print_secrets () {
+        use alloc::format;
+        let output = generate_new_chain(&mut CountingRng(0), HashAlg::Sha1, "This is a test.", 499).unwrap();
+        assert_eq!(format!("{}", output), "sha1 499 abcdefghij");
+        let debug = format!("{:?}", output);
+        assert!(debug.contains("otp: ********"));
+        assert!(!debug.contains(&format!("{:?}", output.reveal_otp())));
+        assert!(!debug.contains("This is a test."));
+        assert!(format!("{:?}", output.reveal()).contains(&format!("{:?}", output.reveal_otp())));
+    }
+
+}
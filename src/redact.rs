@@ -0,0 +1,402 @@
+//! Redaction of OTP values from `Debug` output, so that they do not end up in
+//! logs by accident. OTP values are printed as `hex:********` or
+//! `word:********`, unless they are wrapped in [Reveal], or the
+//! `unredacted-debug` feature is enabled.
+use core::fmt;
+#[cfg(feature = "parsing-response")]
+use crate::{HexOrWords, OTPInit, OTPResponse};
+#[cfg(all(feature = "parsing-response", feature = "words"))]
+use crate::{ValidatedInit, VerifyResult};
+#[cfg(all(feature = "parsing-response", feature = "alloc"))]
+use crate::{HexOrWordsOwned, OTPResponseOwned};
+#[cfg(all(feature = "serde", feature = "alloc", feature = "parsing-challenge", feature = "parsing-response"))]
+use crate::dto::{InitDto, ResponseDto};
+
+/// What is printed in place of an OTP value.
+pub(crate) const REDACTED: &str = "********";
+
+/// Types whose `Debug` output is redacted, but which can be printed in full
+/// using [Reveal].
+pub trait RevealDebug {
+
+    /// Format this value, including any OTP values, as `Debug` would.
+    fn fmt_revealed (&self, f: &mut fmt::Formatter<'_>) -> fmt::Result;
+
+    /// Wrap this value so that its `Debug` output includes its OTP values.
+    fn reveal (&self) -> Reveal<'_, Self> {
+        Reveal(self)
+    }
+
+}
+
+/// A wrapper whose `Debug` output includes OTP values, for use in tests and
+/// debugging. Created by [RevealDebug::reveal].
+pub struct Reveal <'a, T: RevealDebug + ?Sized> (pub &'a T);
+
+impl <T: RevealDebug + ?Sized> fmt::Debug for Reveal<'_, T> {
+
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt_revealed(f)
+    }
+
+}
+
+/// Implement `Debug` using either `fmt_revealed` or `fmt_redacted`, depending
+/// on the `unredacted-debug` feature.
+macro_rules! redacted_debug {
+    ($t:ty) => {
+        impl core::fmt::Debug for $t {
+
+            fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+                if cfg!(feature = "unredacted-debug") {
+                    self.fmt_revealed(f)
+                } else {
+                    self.fmt_redacted(f)
+                }
+            }
+
+        }
+    };
+}
+
+pub(crate) use redacted_debug;
+
+#[cfg(feature = "parsing-response")]
+impl HexOrWords<'_> {
+
+    fn fmt_redacted (&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HexOrWords::Hex(_) => write!(f, "hex:{}", REDACTED),
+            HexOrWords::Words(_) => write!(f, "word:{}", REDACTED),
+        }
+    }
+
+}
+
+#[cfg(feature = "parsing-response")]
+impl RevealDebug for HexOrWords<'_> {
+
+    fn fmt_revealed (&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HexOrWords::Hex(h) => f.debug_tuple("Hex").field(h).finish(),
+            HexOrWords::Words(w) => f.debug_tuple("Words").field(w).finish(),
+        }
+    }
+
+}
+
+#[cfg(feature = "parsing-response")]
+redacted_debug!(HexOrWords<'_>);
+
+#[cfg(feature = "parsing-response")]
+impl OTPInit<'_> {
+
+    fn fmt_redacted (&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OTPInit")
+            .field("current_otp", &format_args!("{:?}", self.current_otp))
+            .field("new_otp", &format_args!("{:?}", self.new_otp))
+            .field("new_alg", &self.new_alg)
+            .field("new_seq_num", &self.new_seq_num)
+            .field("new_seed", &self.new_seed)
+            .field("extra", &self.extra)
+            .finish()
+    }
+
+}
+
+#[cfg(feature = "parsing-response")]
+impl RevealDebug for OTPInit<'_> {
+
+    fn fmt_revealed (&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OTPInit")
+            .field("current_otp", &self.current_otp.reveal())
+            .field("new_otp", &self.new_otp.reveal())
+            .field("new_alg", &self.new_alg)
+            .field("new_seq_num", &self.new_seq_num)
+            .field("new_seed", &self.new_seed)
+            .field("extra", &self.extra)
+            .finish()
+    }
+
+}
+
+#[cfg(feature = "parsing-response")]
+redacted_debug!(OTPInit<'_>);
+
+#[cfg(feature = "parsing-response")]
+impl OTPResponse<'_> {
+
+    fn fmt_redacted (&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OTPResponse::Init(init) => f.debug_tuple("Init").field(init).finish(),
+            OTPResponse::Current(otp) => f.debug_tuple("Current").field(otp).finish(),
+        }
+    }
+
+}
+
+#[cfg(feature = "parsing-response")]
+impl RevealDebug for OTPResponse<'_> {
+
+    fn fmt_revealed (&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OTPResponse::Init(init) => f.debug_tuple("Init").field(&init.reveal()).finish(),
+            OTPResponse::Current(otp) => f.debug_tuple("Current").field(&otp.reveal()).finish(),
+        }
+    }
+
+}
+
+#[cfg(feature = "parsing-response")]
+redacted_debug!(OTPResponse<'_>);
+
+#[cfg(all(feature = "parsing-response", feature = "words"))]
+impl VerifyResult<'_> {
+
+    fn fmt_redacted (&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VerifyResult::Match => f.write_str("Match"),
+            VerifyResult::Mismatch => f.write_str("Mismatch"),
+            VerifyResult::Reinit { init, .. } => f.debug_struct("Reinit")
+                .field("init", init)
+                .field("new_otp", &format_args!("{}", REDACTED))
+                .finish(),
+        }
+    }
+
+}
+
+#[cfg(all(feature = "parsing-response", feature = "words"))]
+impl RevealDebug for VerifyResult<'_> {
+
+    fn fmt_revealed (&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VerifyResult::Match => f.write_str("Match"),
+            VerifyResult::Mismatch => f.write_str("Mismatch"),
+            VerifyResult::Reinit { init, new_otp } => f.debug_struct("Reinit")
+                .field("init", &init.reveal())
+                .field("new_otp", new_otp)
+                .finish(),
+        }
+    }
+
+}
+
+#[cfg(all(feature = "parsing-response", feature = "words"))]
+redacted_debug!(VerifyResult<'_>);
+
+#[cfg(all(feature = "parsing-response", feature = "words"))]
+impl ValidatedInit {
+
+    fn fmt_redacted (&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ValidatedInit")
+            .field("current_otp", &format_args!("{}", REDACTED))
+            .field("new_otp", &format_args!("{}", REDACTED))
+            .field("new_alg", &self.new_alg)
+            .field("new_seq_num", &self.new_seq_num)
+            .field("new_seed", &self.new_seed)
+            .finish()
+    }
+
+}
+
+#[cfg(all(feature = "parsing-response", feature = "words"))]
+impl RevealDebug for ValidatedInit {
+
+    fn fmt_revealed (&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ValidatedInit")
+            .field("current_otp", &self.current_otp)
+            .field("new_otp", &self.new_otp)
+            .field("new_alg", &self.new_alg)
+            .field("new_seq_num", &self.new_seq_num)
+            .field("new_seed", &self.new_seed)
+            .finish()
+    }
+
+}
+
+#[cfg(all(feature = "parsing-response", feature = "words"))]
+redacted_debug!(ValidatedInit);
+
+#[cfg(all(feature = "parsing-response", feature = "alloc"))]
+impl HexOrWordsOwned {
+
+    fn fmt_redacted (&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let prefix = if self.is_hex { "hex" } else { "word" };
+        write!(f, "{}:{}", prefix, REDACTED)
+    }
+
+}
+
+#[cfg(all(feature = "parsing-response", feature = "alloc"))]
+impl RevealDebug for HexOrWordsOwned {
+
+    fn fmt_revealed (&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HexOrWordsOwned")
+            .field("text", &self.text)
+            .field("bytes", &self.bytes)
+            .field("is_hex", &self.is_hex)
+            .finish()
+    }
+
+}
+
+#[cfg(all(feature = "parsing-response", feature = "alloc"))]
+redacted_debug!(HexOrWordsOwned);
+
+#[cfg(all(feature = "parsing-response", feature = "alloc"))]
+impl OTPResponseOwned {
+
+    fn fmt_redacted (&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OTPResponseOwned::Current(otp) => f.debug_tuple("Current").field(otp).finish(),
+            OTPResponseOwned::Init { current_otp, new_otp, new_alg, new_seq_num, new_seed, extra } => f.debug_struct("Init")
+                .field("current_otp", current_otp)
+                .field("new_otp", new_otp)
+                .field("new_alg", new_alg)
+                .field("new_seq_num", new_seq_num)
+                .field("new_seed", new_seed)
+                .field("extra", extra)
+                .finish(),
+        }
+    }
+
+}
+
+#[cfg(all(feature = "parsing-response", feature = "alloc"))]
+impl RevealDebug for OTPResponseOwned {
+
+    fn fmt_revealed (&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OTPResponseOwned::Current(otp) => f.debug_tuple("Current").field(&otp.reveal()).finish(),
+            OTPResponseOwned::Init { current_otp, new_otp, new_alg, new_seq_num, new_seed, extra } => f.debug_struct("Init")
+                .field("current_otp", &current_otp.reveal())
+                .field("new_otp", &new_otp.reveal())
+                .field("new_alg", new_alg)
+                .field("new_seq_num", new_seq_num)
+                .field("new_seed", new_seed)
+                .field("extra", extra)
+                .finish(),
+        }
+    }
+
+}
+
+#[cfg(all(feature = "parsing-response", feature = "alloc"))]
+redacted_debug!(OTPResponseOwned);
+
+#[cfg(all(feature = "serde", feature = "alloc", feature = "parsing-challenge", feature = "parsing-response"))]
+impl InitDto {
+
+    fn fmt_redacted (&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("InitDto")
+            .field("current_otp", &format_args!("{}", REDACTED))
+            .field("new_alg", &self.new_alg)
+            .field("new_count", &self.new_count)
+            .field("new_seed", &self.new_seed)
+            .field("new_otp", &format_args!("{}", REDACTED))
+            .field("extra", &self.extra)
+            .finish()
+    }
+
+}
+
+#[cfg(all(feature = "serde", feature = "alloc", feature = "parsing-challenge", feature = "parsing-response"))]
+impl RevealDebug for InitDto {
+
+    fn fmt_revealed (&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("InitDto")
+            .field("current_otp", &self.current_otp)
+            .field("new_alg", &self.new_alg)
+            .field("new_count", &self.new_count)
+            .field("new_seed", &self.new_seed)
+            .field("new_otp", &self.new_otp)
+            .field("extra", &self.extra)
+            .finish()
+    }
+
+}
+
+#[cfg(all(feature = "serde", feature = "alloc", feature = "parsing-challenge", feature = "parsing-response"))]
+redacted_debug!(InitDto);
+
+#[cfg(all(feature = "serde", feature = "alloc", feature = "parsing-challenge", feature = "parsing-response"))]
+impl ResponseDto {
+
+    fn fmt_redacted (&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ResponseDto::Hex { .. } => f.debug_struct("Hex").field("otp", &format_args!("{}", REDACTED)).finish(),
+            ResponseDto::Word { .. } => f.debug_struct("Word").field("otp", &format_args!("{}", REDACTED)).finish(),
+            ResponseDto::InitHex(init) => f.debug_tuple("InitHex").field(init).finish(),
+            ResponseDto::InitWord(init) => f.debug_tuple("InitWord").field(init).finish(),
+        }
+    }
+
+}
+
+#[cfg(all(feature = "serde", feature = "alloc", feature = "parsing-challenge", feature = "parsing-response"))]
+impl RevealDebug for ResponseDto {
+
+    fn fmt_revealed (&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ResponseDto::Hex { otp } => f.debug_struct("Hex").field("otp", otp).finish(),
+            ResponseDto::Word { otp } => f.debug_struct("Word").field("otp", otp).finish(),
+            ResponseDto::InitHex(init) => f.debug_tuple("InitHex").field(&init.reveal()).finish(),
+            ResponseDto::InitWord(init) => f.debug_tuple("InitWord").field(&init.reveal()).finish(),
+        }
+    }
+
+}
+
+#[cfg(all(feature = "serde", feature = "alloc", feature = "parsing-challenge", feature = "parsing-response"))]
+redacted_debug!(ResponseDto);
+
+#[cfg(all(test, feature = "parsing-response", feature = "alloc", not(feature = "unredacted-debug")))]
+mod tests {
+    use super::*;
+    use alloc::format;
+
+    #[test]
+    fn redacts_otp_values () {
+        let init = OTPInit {
+            current_otp: HexOrWords::Hex([ 0x5B, 0xF0, 0x75, 0xD9, 0x95, 0x9D, 0x03, 0x6F ]),
+            new_otp: HexOrWords::Words("ROME MUG FRED SCAN LIVE LACE"),
+            new_alg: "md5",
+            new_seq_num: 499,
+            new_seed: "ke1235",
+            extra: None,
+        };
+        let response = OTPResponse::Init(init);
+        assert_eq!(
+            format!("{:?}", response),
+            "Init(OTPInit { current_otp: hex:********, new_otp: word:********, \
+            new_alg: \"md5\", new_seq_num: 499, new_seed: \"ke1235\", extra: None })",
+        );
+        let revealed = format!("{:?}", response.reveal());
+        assert!(revealed.contains("Hex([91, 240, 117, 217, 149, 157, 3, 111])"));
+        assert!(revealed.contains("Words(\"ROME MUG FRED SCAN LIVE LACE\")"));
+
+        let owned = crate::parse_otp_response_owned("hex:5Bf0 75d9 959d 036f").unwrap();
+        assert_eq!(format!("{:?}", owned), "Current(hex:********)");
+        assert!(format!("{:?}", owned.reveal()).contains("text: \"5Bf0 75d9 959d 036f\""));
+    }
+
+    #[test]
+    #[cfg(all(feature = "serde", feature = "parsing-challenge"))]
+    fn redacts_dto_otp_values () {
+        use crate::dto::ResponseDto;
+        let dto = ResponseDto::parse("word:INCH SEA ANNE LONG AHEM TOUR").unwrap();
+        assert_eq!(format!("{:?}", dto), "Word { otp: ******** }");
+        assert!(format!("{:?}", dto.reveal()).contains("INCH SEA ANNE LONG AHEM TOUR"));
+        let dto = ResponseDto::parse("init-hex:9E87 6134 D904 99DD:sha1 499 ke1235:1111 2222 3333 4444").unwrap();
+        assert_eq!(
+            format!("{:?}", dto),
+            "InitHex(InitDto { current_otp: ********, new_alg: \"sha1\", new_count: 499, \
+            new_seed: \"ke1235\", new_otp: ********, extra: None })",
+        );
+        let revealed = format!("{:?}", dto.reveal());
+        assert!(revealed.contains("current_otp: \"9E87 6134 D904 99DD\""));
+        assert!(revealed.contains("new_otp: \"1111 2222 3333 4444\""));
+    }
+
+}
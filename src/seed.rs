@@ -0,0 +1,58 @@
+//! Seeds, which are combined with the passphrase so that the same passphrase
+//! can be used for many OTP sequences.
+
+/// The maximum length of a seed, per Section 6.0 of
+/// [IETF RFC 2289](https://www.rfc-editor.org/rfc/rfc2289.html).
+pub const MAX_SEED_LEN: usize = 16;
+
+/// Whether `s` is a valid seed: one to sixteen alphanumeric characters, per
+/// Section 6.0 of [IETF RFC 2289](https://www.rfc-editor.org/rfc/rfc2289.html).
+pub fn is_valid_seed (s: &str) -> bool {
+    (1..=MAX_SEED_LEN).contains(&s.len()) && s.bytes().all(|b| b.is_ascii_alphanumeric())
+}
+
+/// A seed of one to sixteen alphanumeric characters, per Section 6.0 of
+/// [IETF RFC 2289](https://www.rfc-editor.org/rfc/rfc2289.html), stored
+/// inline so that it can be generated without allocating.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Seed {
+    pub(crate) bytes: [u8; MAX_SEED_LEN],
+    pub(crate) len: u8,
+}
+
+impl Seed {
+
+    /// Create a seed from a string, returning `None` if it is empty, longer
+    /// than sixteen characters, or not entirely alphanumeric.
+    pub fn new (s: &str) -> Option<Seed> {
+        if !is_valid_seed(s) {
+            return None;
+        }
+        let mut bytes = [0u8; MAX_SEED_LEN];
+        bytes[..s.len()].copy_from_slice(s.as_bytes());
+        Some(Seed { bytes, len: s.len() as u8 })
+    }
+
+    /// The seed as a string.
+    pub fn as_str (&self) -> &str {
+        // Seeds can only be constructed from ASCII alphanumerics.
+        core::str::from_utf8(&self.bytes[..self.len as usize]).unwrap_or_default()
+    }
+
+}
+
+impl AsRef<str> for Seed {
+
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+
+}
+
+impl core::fmt::Display for Seed {
+
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(self.as_str())
+    }
+
+}
@@ -0,0 +1,1685 @@
+//! A server-side state machine for OTP authentication: issuing challenges,
+//! verifying responses, and handling re-initialization, on top of any storage
+//! backend that implements [OtpStore].
+//!
+//! For each user, the server stores the OTP value for some count `n`. It
+//! challenges the user for count `n - 1`, hashes the response once, and
+//! compares it to the stored value. If they match, the response becomes the
+//! stored value for count `n - 1`, per Section 7.0 of
+//! [IETF RFC 2289](https://www.rfc-editor.org/rfc/rfc2289.html).
+use core::fmt;
+use core::net::IpAddr;
+#[cfg(feature = "alloc")]
+use alloc::{collections::BTreeMap, string::String};
+use crate::{
+    clock::Clock,
+    decode_hex_otp,
+    next_otp,
+    parse_otp_response,
+    DEFAULT_MAX_COUNT,
+    verify_otp_eq,
+    HashAlg,
+    InitValidationError,
+    OTPInit,
+    OTPResponse,
+    Seed,
+    WordDecodeError,
+};
+
+/// The most seeds that a [SeedHistory] can hold.
+pub const SEED_HISTORY_LEN: usize = 8;
+
+/// The seeds of a user's previous sequences, most recent first, so that the
+/// server can refuse to re-initialize to one of them. Reusing a seed with the
+/// same passphrase repeats the OTP values of the old sequence, which may have
+/// been disclosed, per Section 6.0 of
+/// [IETF RFC 2289](https://www.rfc-editor.org/rfc/rfc2289.html).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SeedHistory {
+    seeds: [Option<Seed>; SEED_HISTORY_LEN],
+}
+
+impl SeedHistory {
+
+    /// Create an empty history.
+    pub const fn new () -> Self {
+        SeedHistory { seeds: [None; SEED_HISTORY_LEN] }
+    }
+
+    /// Record `seed` as the most recent, keeping at most `limit` seeds, and
+    /// never more than [SEED_HISTORY_LEN].
+    pub fn push (&mut self, seed: Seed, limit: usize) {
+        self.seeds.copy_within(..SEED_HISTORY_LEN - 1, 1);
+        self.seeds[0] = Some(seed);
+        for s in self.seeds.iter_mut().skip(limit) {
+            *s = None;
+        }
+    }
+
+    /// Whether `seed` is in the history, ignoring case, since seeds are
+    /// lowercased before hashing.
+    pub fn contains (&self, seed: &str) -> bool {
+        self.iter().any(|s| s.as_str().eq_ignore_ascii_case(seed))
+    }
+
+    /// The seeds in the history, most recent first.
+    pub fn iter (&self) -> impl Iterator<Item = &Seed> {
+        self.seeds.iter().flatten()
+    }
+
+}
+
+/// What the server stores for each user.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OtpState {
+    /// The hash algorithm of the user's sequence.
+    pub alg: HashAlg,
+    /// The count of the stored OTP value. The next challenge will be for one
+    /// less than this.
+    pub count: usize,
+    /// The seed of the user's sequence.
+    pub seed: Seed,
+    /// The OTP value for `count`, against which the next response is checked.
+    pub otp: [u8; 8],
+    /// When the outstanding challenge was issued, per the server's [Clock],
+    /// or `None` if there is no outstanding challenge.
+    pub challenge_issued_at: Option<u64>,
+    /// The seeds of the user's previous sequences.
+    pub seen_seeds: SeedHistory,
+    /// When the sequence was initialized, per the server's [Clock], or `None`
+    /// if this is not known, as for imported sequences.
+    pub created_at: Option<u64>,
+    /// When a response was last accepted, per the server's [Clock], or `None`
+    /// if none has been.
+    pub last_used: Option<u64>,
+    /// Whether only init responses will be accepted, as set by
+    /// [OtpServer::force_reinit].
+    pub reinit_required: bool,
+}
+
+impl OtpState {
+
+    /// Create the state for a new sequence, with no outstanding challenge, no
+    /// previous seeds, and no known creation time.
+    pub const fn new (alg: HashAlg, count: usize, seed: Seed, otp: [u8; 8]) -> Self {
+        OtpState {
+            alg,
+            count,
+            seed,
+            otp,
+            challenge_issued_at: None,
+            seen_seeds: SeedHistory::new(),
+            created_at: None,
+            last_used: None,
+            reinit_required: false,
+        }
+    }
+
+    /// Set when the sequence was initialized, per the server's [Clock].
+    pub const fn with_created_at (mut self, secs: u64) -> Self {
+        self.created_at = Some(secs);
+        self
+    }
+
+    /// Whether the sequence was initialized more than `max_age` seconds before
+    /// `now`, or at an unknown time, and so should be re-initialized.
+    pub fn is_stale (&self, now: u64, max_age: u64) -> bool {
+        self.created_at.is_none_or(|created_at| now.saturating_sub(created_at) > max_age)
+    }
+
+    /// Whether `seed` is the seed of this sequence or one of its
+    /// [OtpState::seen_seeds], ignoring case, and so may not be used for a new
+    /// sequence.
+    pub fn has_used_seed (&self, seed: &str) -> bool {
+        self.seed.as_str().eq_ignore_ascii_case(seed) || self.seen_seeds.contains(seed)
+    }
+
+}
+
+/// The length, in bytes, of an [OtpState] encoded by [OtpState::to_bytes].
+#[cfg(feature = "binary-state")]
+pub const OTP_STATE_ENCODED_LEN: usize = 60;
+
+/// The first byte of an encoded [OtpState], which changes whenever its layout
+/// does.
+#[cfg(feature = "binary-state")]
+const OTP_STATE_FORMAT_VERSION: u8 = 1;
+
+#[cfg(feature = "binary-state")]
+const HAS_CHALLENGE: u8 = 1 << 0;
+#[cfg(feature = "binary-state")]
+const HAS_CREATED_AT: u8 = 1 << 1;
+#[cfg(feature = "binary-state")]
+const HAS_LAST_USED: u8 = 1 << 2;
+#[cfg(feature = "binary-state")]
+const REINIT_REQUIRED: u8 = 1 << 3;
+
+#[cfg(feature = "binary-state")]
+fn read_u64 (bytes: &[u8; OTP_STATE_ENCODED_LEN], offset: usize) -> u64 {
+    let mut buf = [0u8; 8];
+    buf.copy_from_slice(&bytes[offset..offset + 8]);
+    u64::from_be_bytes(buf)
+}
+
+#[cfg(feature = "binary-state")]
+impl OtpState {
+
+    /// Encode the state into a fixed number of bytes, such as for storing it
+    /// in a page of EEPROM on an embedded gateway. Integers are big-endian,
+    /// and absent times are written as zero:
+    ///
+    /// | Offset | Length | Field |
+    /// | ------ | ------ | ----- |
+    /// | 0      | 1      | Format version, currently 1 |
+    /// | 1      | 1      | Flags: set if there is a challenge (bit 0), `created_at` (bit 1), or `last_used` (bit 2), and if `reinit_required` (bit 3) |
+    /// | 2      | 1      | Hash algorithm: 1 for MD4, 2 for MD5, 3 for SHA1, 4 for SHA-256, and 5 for SHA-512 |
+    /// | 3      | 8      | Count |
+    /// | 11     | 1      | Seed length |
+    /// | 12     | 16     | Seed, padded with zeroes |
+    /// | 28     | 8      | OTP value |
+    /// | 36     | 8      | `challenge_issued_at` |
+    /// | 44     | 8      | `created_at` |
+    /// | 52     | 8      | `last_used` |
+    ///
+    /// The [SeedHistory] does not fit, so it is not encoded, and states
+    /// decoded by [OtpState::from_bytes] have an empty one.
+    pub fn to_bytes (&self) -> [u8; OTP_STATE_ENCODED_LEN] {
+        let mut out = [0u8; OTP_STATE_ENCODED_LEN];
+        out[0] = OTP_STATE_FORMAT_VERSION;
+        let times = [
+            (HAS_CHALLENGE, self.challenge_issued_at),
+            (HAS_CREATED_AT, self.created_at),
+            (HAS_LAST_USED, self.last_used),
+        ];
+        for (i, (flag, time)) in times.into_iter().enumerate() {
+            if let Some(time) = time {
+                out[1] |= flag;
+                out[36 + 8 * i..44 + 8 * i].copy_from_slice(&time.to_be_bytes());
+            }
+        }
+        if self.reinit_required {
+            out[1] |= REINIT_REQUIRED;
+        }
+        out[2] = match self.alg {
+            HashAlg::Md4 => 1,
+            HashAlg::Md5 => 2,
+            HashAlg::Sha1 => 3,
+            HashAlg::Sha256 => 4,
+            HashAlg::Sha512 => 5,
+        };
+        out[3..11].copy_from_slice(&(self.count as u64).to_be_bytes());
+        out[11] = self.seed.len;
+        out[12..28].copy_from_slice(&self.seed.bytes);
+        out[28..36].copy_from_slice(&self.otp);
+        out
+    }
+
+    /// Decode a state encoded by [OtpState::to_bytes], returning `None` if it
+    /// is from an unknown format version, or is otherwise invalid.
+    pub fn from_bytes (bytes: &[u8; OTP_STATE_ENCODED_LEN]) -> Option<OtpState> {
+        if bytes[0] != OTP_STATE_FORMAT_VERSION {
+            return None;
+        }
+        let flags = bytes[1];
+        if flags & !(HAS_CHALLENGE | HAS_CREATED_AT | HAS_LAST_USED | REINIT_REQUIRED) != 0 {
+            return None;
+        }
+        let alg = match bytes[2] {
+            1 => HashAlg::Md4,
+            2 => HashAlg::Md5,
+            3 => HashAlg::Sha1,
+            4 => HashAlg::Sha256,
+            5 => HashAlg::Sha512,
+            _ => return None,
+        };
+        let count = usize::try_from(read_u64(bytes, 3)).ok()?;
+        let seed = bytes[12..28].get(..bytes[11] as usize)?;
+        let seed = Seed::new(core::str::from_utf8(seed).ok()?)?;
+        let mut otp = [0u8; 8];
+        otp.copy_from_slice(&bytes[28..36]);
+        let time = |flag: u8, offset: usize| (flags & flag != 0).then(|| read_u64(bytes, offset));
+        let mut state = OtpState::new(alg, count, seed, otp);
+        state.challenge_issued_at = time(HAS_CHALLENGE, 36);
+        state.created_at = time(HAS_CREATED_AT, 44);
+        state.last_used = time(HAS_LAST_USED, 52);
+        state.reinit_required = flags & REINIT_REQUIRED != 0;
+        Some(state)
+    }
+
+}
+
+/// The layout of a state record in a legacy key file, as detected by
+/// [import_state].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum StateDialect {
+    /// `user count seed otp [date]`, as in OPIE's `/etc/opiekeys` and the
+    /// original Bellcore S/KEY's `/etc/skeykeys`, which do not record the hash
+    /// algorithm.
+    Opie,
+    /// `user alg count seed otp [date]`, as in logdaemon's `/etc/skeykeys`.
+    Logdaemon,
+    /// The user, algorithm, count, seed, and OTP value on five separate lines,
+    /// as in the files under OpenBSD's `/etc/skey` directory.
+    OpenBsd,
+}
+
+/// A user's state, imported from a legacy key file by [import_state].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ImportedState <'a> {
+    /// The user name.
+    pub user: &'a str,
+    /// The layout that the record was detected to be in.
+    pub dialect: StateDialect,
+    /// The imported state, with no outstanding challenge and no previous
+    /// seeds.
+    pub state: OtpState,
+}
+
+/// Look up a hash algorithm by name, ignoring case and any `otp-` prefix, since
+/// legacy key files write names such as `MD5` and `otp-md5`.
+fn alg_from_legacy_name (name: &str) -> Option<HashAlg> {
+    let name = name.get(..4)
+        .filter(|p| p.eq_ignore_ascii_case("otp-"))
+        .map_or(name, |_| &name[4..]);
+    HashAlg::ALL.into_iter().find(|alg| alg.name().eq_ignore_ascii_case(name))
+}
+
+/// Import a user's state from a record of a legacy key file, detecting which
+/// [StateDialect] it is in, so that mixed fleets of OPIE and S/KEY servers can
+/// be migrated at once. Records that do not say which hash algorithm they use
+/// are assumed to use MD5, as OPIE does. Returns `None` if the record is
+/// malformed.
+///
+/// ```rust
+/// use rfc2289_otp::{server::{import_state, StateDialect}, HashAlg};
+/// let imported = import_state("jdoe md4 0099 alpha1 8f33f8c5de5c6b0a  Apr 04,1999 11:39:57").unwrap();
+/// assert_eq!(imported.user, "jdoe");
+/// assert_eq!(imported.dialect, StateDialect::Logdaemon);
+/// assert_eq!(imported.state.alg, HashAlg::Md4);
+/// assert_eq!(imported.state.count, 99);
+/// ```
+pub fn import_state (record: &str) -> Option<ImportedState<'_>> {
+    import_state_with_default_alg(record, HashAlg::Md5)
+}
+
+/// Import a user's state as [import_state] does, but assume that records that
+/// do not say which hash algorithm they use use `default_alg`, such as MD4 for
+/// key files written by the original Bellcore S/KEY.
+pub fn import_state_with_default_alg (record: &str, default_alg: HashAlg) -> Option<ImportedState<'_>> {
+    let record = record.trim_end_matches(['\r', '\n']);
+    let (dialect, user, alg, count, seed, otp) = if record.contains('\n') {
+        let mut lines = record.lines().map(str::trim);
+        let [user, alg, count, seed, otp] = [lines.next()?, lines.next()?, lines.next()?, lines.next()?, lines.next()?];
+        if lines.next().is_some() {
+            return None;
+        }
+        (StateDialect::OpenBsd, user, alg_from_legacy_name(alg)?, count, seed, otp)
+    } else {
+        let mut tokens = record.split_ascii_whitespace();
+        let user = tokens.next()?;
+        let second = tokens.next()?;
+        match alg_from_legacy_name(second) {
+            Some(alg) => (StateDialect::Logdaemon, user, alg, tokens.next()?, tokens.next()?, tokens.next()?),
+            None => (StateDialect::Opie, user, default_alg, second, tokens.next()?, tokens.next()?),
+        }
+    };
+    let count = count.parse::<usize>().ok()?;
+    let seed = Seed::new(seed)?;
+    let otp = decode_hex_otp(otp)?;
+    Some(ImportedState { user, dialect, state: OtpState::new(alg, count, seed, otp) })
+}
+
+/// Storage for the [OtpState] of each user.
+pub trait OtpStore {
+
+    /// Get the state of `user`, if there is any.
+    fn load (&self, user: &str) -> Option<OtpState>;
+
+    /// Replace the state of `user`.
+    fn save (&mut self, user: &str, state: OtpState);
+
+    /// Call `f` with each user and their state, such as for finding accounts
+    /// that need attention.
+    fn for_each (&self, f: &mut dyn FnMut(&str, &OtpState));
+
+    /// Replace the state of `user` with `new`, but only if its stored OTP
+    /// value is still `expected_otp`, returning whether it was replaced. The
+    /// server uses this to store the result of a verification, so that, of two
+    /// concurrent logins with the same OTP, only one succeeds.
+    ///
+    /// Stores shared by several processes must do this atomically, such as
+    /// with a conditional `UPDATE`. The default implementation loads and then
+    /// saves, which is only sufficient if nothing else can modify the store in
+    /// between, as when it is only accessed through `&mut self`.
+    fn compare_and_swap (&mut self, user: &str, expected_otp: &[u8; 8], new: OtpState) -> bool {
+        match self.load(user) {
+            Some(current) if verify_otp_eq(&current.otp, expected_otp) => {
+                self.save(user, new);
+                true
+            },
+            _ => false,
+        }
+    }
+
+}
+
+/// An [OtpStore] that keeps everything in memory.
+#[cfg(feature = "alloc")]
+#[derive(Debug, Clone, Default)]
+pub struct MemoryStore {
+    states: BTreeMap<String, OtpState>,
+}
+
+#[cfg(feature = "alloc")]
+impl MemoryStore {
+
+    /// Create an empty store.
+    pub fn new () -> Self {
+        MemoryStore::default()
+    }
+
+}
+
+#[cfg(feature = "alloc")]
+impl OtpStore for MemoryStore {
+
+    fn load (&self, user: &str) -> Option<OtpState> {
+        self.states.get(user).copied()
+    }
+
+    fn save (&mut self, user: &str, state: OtpState) {
+        self.states.insert(String::from(user), state);
+    }
+
+    fn for_each (&self, f: &mut dyn FnMut(&str, &OtpState)) {
+        for (user, state) in self.states.iter() {
+            f(user, state);
+        }
+    }
+
+}
+
+/// An error issuing a challenge or verifying a response.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ServerError {
+    /// There is no state for the user.
+    UnknownUser,
+    /// The user's sequence is used up, and must be re-initialized.
+    SequenceExhausted,
+    /// No challenge was issued for the response being verified.
+    NoChallenge,
+    /// The challenge was issued longer ago than the challenge lifetime.
+    ChallengeExpired,
+    /// The response could not be parsed.
+    InvalidResponse,
+    /// The words in the response could not be decoded.
+    Undecodable(WordDecodeError),
+    /// The hash algorithm of the user's sequence, or the new algorithm in an
+    /// init response, is not supported.
+    UnsupportedAlgorithm,
+    /// The new seed in an init response is not one to sixteen alphanumeric
+    /// characters.
+    BadSeed,
+    /// The new algorithm in an init response is supported, but not allowed by
+    /// the server's policy.
+    AlgorithmNotAllowed,
+    /// The new seed in an init response is the seed of the user's current
+    /// sequence, or one in its [SeedHistory].
+    SeedReused,
+    /// The user's state changed while the response was being verified, as
+    /// when another login for the same user completed first. The response was
+    /// not accepted, and may be retried after issuing a new challenge.
+    Conflict,
+    /// The server's [PreVerifyHook] denied the request before the response
+    /// was verified.
+    Denied,
+    /// The user must re-initialize, because of [OtpServer::force_reinit], but
+    /// the response was not an init response. If it was otherwise correct, it
+    /// was still used up, so that it cannot be replayed.
+    ReinitRequired,
+    /// The new count in an init response is outside the range allowed by the
+    /// server's policy.
+    CountNotAllowed,
+    /// The realm is not one of those given to [OtpServer::with_realms].
+    UnknownRealm,
+}
+
+impl fmt::Display for ServerError {
+
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ServerError::UnknownUser => f.write_str("unknown user"),
+            ServerError::SequenceExhausted => f.write_str("OTP sequence exhausted"),
+            ServerError::NoChallenge => f.write_str("no challenge outstanding"),
+            ServerError::ChallengeExpired => f.write_str("challenge expired"),
+            ServerError::InvalidResponse => f.write_str("invalid OTP response"),
+            ServerError::Undecodable(e) => e.fmt(f),
+            ServerError::UnsupportedAlgorithm => f.write_str("unsupported hash algorithm"),
+            ServerError::BadSeed => f.write_str("invalid seed"),
+            ServerError::AlgorithmNotAllowed => f.write_str("hash algorithm not allowed"),
+            ServerError::SeedReused => f.write_str("seed reused"),
+            ServerError::Conflict => f.write_str("concurrent modification of OTP state"),
+            ServerError::Denied => f.write_str("request denied"),
+            ServerError::ReinitRequired => f.write_str("re-initialization required"),
+            ServerError::CountNotAllowed => f.write_str("hash count not allowed"),
+            ServerError::UnknownRealm => f.write_str("unknown realm"),
+        }
+    }
+
+}
+
+impl From<WordDecodeError> for ServerError {
+
+    fn from(e: WordDecodeError) -> Self {
+        ServerError::Undecodable(e)
+    }
+
+}
+
+/// A challenge issued by [OtpServer::issue_challenge]. Its `Display` output is
+/// the challenge string, such as `otp-md5 498 ke1234`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IssuedChallenge {
+    /// The hash algorithm.
+    pub alg: HashAlg,
+    /// The count for which the user must respond.
+    pub count: usize,
+    /// The seed.
+    pub seed: Seed,
+    /// Whether the user must respond with an init response, because of
+    /// [OtpServer::force_reinit]. Applications should tell the user so, since
+    /// the challenge string itself does not.
+    pub reinit_required: bool,
+}
+
+impl fmt::Display for IssuedChallenge {
+
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "otp-{} {} {}", self.alg, self.count, self.seed)
+    }
+
+}
+
+/// Conditions that do not prevent a response from being accepted, but which
+/// the user or operator should probably be told about.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct VerifyWarnings {
+    /// The user has no more than the low count threshold of OTPs left, and
+    /// should re-initialize soon.
+    pub low_count: bool,
+    /// The user's sequence uses a deprecated hash algorithm, per
+    /// [HashAlg::is_deprecated].
+    pub deprecated_alg: bool,
+}
+
+impl VerifyWarnings {
+
+    /// Returns `true` if any warning is set.
+    pub const fn any (&self) -> bool {
+        self.low_count || self.deprecated_alg
+    }
+
+}
+
+/// The outcome of verifying a response with [OtpServer::verify].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct VerifyOutcome {
+    /// Whether the response was accepted.
+    pub accepted: bool,
+    /// If the response was accepted, the count of the newly stored OTP value,
+    /// which is also the number of OTPs the user has left.
+    pub new_sequence: Option<usize>,
+    /// Whether the response was an init response that re-initialized the
+    /// user's sequence.
+    pub reinit_performed: bool,
+    /// How many counts below the challenged one the response was for, if it
+    /// was accepted per [OtpServer::with_max_skip]. The count that matched is
+    /// [VerifyOutcome::new_sequence].
+    pub skipped: usize,
+    /// Warnings about the user's sequence, if the response was accepted.
+    pub warnings: VerifyWarnings,
+}
+
+/// Counts of what an [OtpServer] has done since it was created, returned by
+/// [OtpServer::stats], for exporting as metrics without parsing logs. Since
+/// verifying takes `&mut self`, these are plain integers, rather than atomics
+/// that not every target has.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ServerStats {
+    /// Calls to [OtpServer::verify], [OtpServer::verify_with_metadata], and
+    /// [OtpServer::verify_dual].
+    pub verifications: u64,
+    /// Verifications that were accepted.
+    pub successes: u64,
+    /// Verifications that were not accepted, or failed with an error.
+    pub failures: u64,
+    /// Failures where the response was the OTP value that was last accepted,
+    /// as when a previous response is replayed.
+    pub replays: u64,
+    /// Successes that re-initialized the user's sequence.
+    pub reinits: u64,
+}
+
+impl ServerStats {
+
+    fn record (&mut self, result: &Result<VerifyOutcome, ServerError>) {
+        self.verifications += 1;
+        match result {
+            Ok(outcome) if outcome.accepted => {
+                self.successes += 1;
+                if outcome.reinit_performed {
+                    self.reinits += 1;
+                }
+            },
+            _ => self.failures += 1,
+        }
+    }
+
+}
+
+/// The default lifetime of a challenge, in seconds.
+pub const DEFAULT_CHALLENGE_TTL: u64 = 300;
+
+/// The default number of remaining OTPs at or below which
+/// [VerifyWarnings::low_count] is set.
+pub const DEFAULT_LOW_COUNT_THRESHOLD: usize = 10;
+
+/// The policies applied to init responses, either server-wide or for the users
+/// of one realm. See [OtpServer::with_realms].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RealmPolicy {
+    /// The hash algorithms that init responses may switch a sequence to.
+    pub allowed_algs: &'static [HashAlg],
+    /// The lowest count that init responses may start a sequence at.
+    pub min_count: usize,
+    /// The highest count that init responses may start a sequence at.
+    pub max_count: usize,
+}
+
+impl Default for RealmPolicy {
+
+    /// Allow any supported algorithm, and any count up to [DEFAULT_MAX_COUNT].
+    fn default () -> Self {
+        RealmPolicy {
+            allowed_algs: &HashAlg::ALL,
+            min_count: 1,
+            max_count: DEFAULT_MAX_COUNT,
+        }
+    }
+
+}
+
+/// The key under which [OtpServer::issue_challenge_in_realm] and
+/// [OtpServer::verify_in_realm] store the state of `user` in `realm`: the realm
+/// and the user, separated by a NUL character. Returns `None` if either
+/// contains a NUL character, so that keys from different realms can never
+/// collide.
+#[cfg(feature = "alloc")]
+pub fn realm_user_key (realm: &str, user: &str) -> Option<String> {
+    if realm.contains('\0') || user.contains('\0') {
+        return None;
+    }
+    Some([realm, "\0", user].concat())
+}
+
+/// Split a key made by [realm_user_key] into its realm and user, such as when
+/// iterating over a store with [OtpStore::for_each]. Returns `None` for keys
+/// stored without a realm.
+pub fn split_realm_user_key (key: &str) -> Option<(&str, &str)> {
+    key.split_once('\0')
+}
+
+/// Information about the request that a response arrived in, for
+/// [PreVerifyHook]s.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RequestMetadata <'a> {
+    /// The address that the request came from, if known.
+    pub remote_addr: Option<IpAddr>,
+    /// The service that the user is logging in to, such as `sshd`, if known.
+    pub service: Option<&'a str>,
+}
+
+/// Whether a [PreVerifyHook] allows a request to proceed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HookDecision {
+    /// Verify the response as usual.
+    Allow,
+    /// Refuse the request with [ServerError::Denied], without verifying the
+    /// response.
+    Deny,
+}
+
+/// A check run by [OtpServer::verify_with_metadata] before a response is
+/// parsed or any hashing is done, for integrating existing anti-abuse
+/// systems, such as IP throttling or geographic rules. Denied requests fail
+/// with [ServerError::Denied], and leave the user's state untouched.
+///
+/// This is implemented for `()`, which allows everything, and for closures:
+///
+/// ```rust
+/// use rfc2289_otp::server::{HookDecision, PreVerifyHook, RequestMetadata};
+///
+/// let hook = |user: &str, metadata: &RequestMetadata<'_>| match metadata.remote_addr {
+///     Some(addr) if addr.is_loopback() => HookDecision::Allow,
+///     _ if user == "root" => HookDecision::Deny,
+///     _ => HookDecision::Allow,
+/// };
+/// assert_eq!(hook.check("root", &RequestMetadata::default()), HookDecision::Deny);
+/// ```
+pub trait PreVerifyHook {
+
+    /// Decide whether to verify a response from `user`.
+    fn check (&self, user: &str, metadata: &RequestMetadata<'_>) -> HookDecision;
+
+}
+
+impl PreVerifyHook for () {
+
+    fn check (&self, _user: &str, _metadata: &RequestMetadata<'_>) -> HookDecision {
+        HookDecision::Allow
+    }
+
+}
+
+impl <F: Fn(&str, &RequestMetadata<'_>) -> HookDecision> PreVerifyHook for F {
+
+    fn check (&self, user: &str, metadata: &RequestMetadata<'_>) -> HookDecision {
+        self(user, metadata)
+    }
+
+}
+
+/// An OTP server, which issues challenges and verifies responses for the users
+/// in its store.
+#[derive(Debug)]
+pub struct OtpServer <S: OtpStore, C: Clock, H: PreVerifyHook = ()> {
+    store: S,
+    clock: C,
+    hook: H,
+    challenge_ttl: u64,
+    low_count_threshold: usize,
+    policy: RealmPolicy,
+    realms: &'static [(&'static str, RealmPolicy)],
+    seed_history: usize,
+    max_skip: usize,
+    uniform_timing: bool,
+    stats: ServerStats,
+}
+
+impl <S: OtpStore, C: Clock> OtpServer<S, C> {
+
+    /// Create a server using the given store and clock, whose challenges
+    /// expire after [DEFAULT_CHALLENGE_TTL] seconds.
+    pub fn new (store: S, clock: C) -> Self {
+        OtpServer {
+            store,
+            clock,
+            hook: (),
+            challenge_ttl: DEFAULT_CHALLENGE_TTL,
+            low_count_threshold: DEFAULT_LOW_COUNT_THRESHOLD,
+            policy: RealmPolicy::default(),
+            realms: &[],
+            seed_history: SEED_HISTORY_LEN,
+            max_skip: 0,
+            uniform_timing: false,
+            stats: ServerStats::default(),
+        }
+    }
+
+}
+
+impl <S: OtpStore, C: Clock, H: PreVerifyHook> OtpServer<S, C, H> {
+
+    /// Run `hook` before verifying each response. See [PreVerifyHook].
+    pub fn with_pre_verify_hook <H2: PreVerifyHook> (self, hook: H2) -> OtpServer<S, C, H2> {
+        OtpServer {
+            store: self.store,
+            clock: self.clock,
+            hook,
+            challenge_ttl: self.challenge_ttl,
+            low_count_threshold: self.low_count_threshold,
+            policy: self.policy,
+            realms: self.realms,
+            seed_history: self.seed_history,
+            max_skip: self.max_skip,
+            uniform_timing: self.uniform_timing,
+            stats: self.stats,
+        }
+    }
+
+    /// Set how many seconds a challenge remains valid after it is issued.
+    pub fn with_challenge_ttl (mut self, secs: u64) -> Self {
+        self.challenge_ttl = secs;
+        self
+    }
+
+    /// Set the number of remaining OTPs at or below which
+    /// [VerifyWarnings::low_count] is set.
+    pub fn with_low_count_threshold (mut self, threshold: usize) -> Self {
+        self.low_count_threshold = threshold;
+        self
+    }
+
+    /// Set the hash algorithms that init responses may switch a sequence to.
+    /// By default, any supported algorithm is allowed. This does not affect
+    /// sequences that already use a disallowed algorithm, which can still be
+    /// used, and re-initialized to an allowed one.
+    pub fn with_allowed_algorithms (mut self, algs: &'static [HashAlg]) -> Self {
+        self.policy.allowed_algs = algs;
+        self
+    }
+
+    /// Set the range of counts that init responses may start a new sequence
+    /// at. Others are refused with [ServerError::CountNotAllowed]. By default,
+    /// any count from one to [DEFAULT_MAX_COUNT] is allowed.
+    pub fn with_init_count_range (mut self, min_count: usize, max_count: usize) -> Self {
+        self.policy.min_count = min_count;
+        self.policy.max_count = max_count;
+        self
+    }
+
+    /// Set the realms that [OtpServer::issue_challenge_in_realm] and
+    /// [OtpServer::verify_in_realm] serve, and the policy for each, so that
+    /// one server can serve several tenants from one store. A realm's policy
+    /// replaces the server-wide one for its users.
+    pub fn with_realms (mut self, realms: &'static [(&'static str, RealmPolicy)]) -> Self {
+        self.realms = realms;
+        self
+    }
+
+    /// Set how many previous seeds to remember for each user, up to
+    /// [SEED_HISTORY_LEN], which is the default. Init responses whose new seed
+    /// is the current seed or a remembered one are refused with
+    /// [ServerError::SeedReused]. Zero disables this check.
+    pub fn with_seed_history (mut self, len: usize) -> Self {
+        self.seed_history = len.min(SEED_HISTORY_LEN);
+        self
+    }
+
+    /// Accept responses for up to `max_skip` counts below the challenged one,
+    /// as when a user has crossed off entries of a printed list without using
+    /// them. Such a response is hashed forward until it matches the stored
+    /// value, and the user's state moves down to its count. By default, only
+    /// responses for the challenged count are accepted.
+    ///
+    /// A `max_skip` of one also tolerates buggy clients that calculate the
+    /// OTP for one less than the challenged count. Either way,
+    /// [VerifyOutcome::skipped] and [VerifyOutcome::new_sequence] report which
+    /// count matched, and a distinct `tracing` event is emitted.
+    ///
+    /// Each failed verification then costs up to `max_skip + 1` hashes, so
+    /// this should be kept small.
+    pub fn with_max_skip (mut self, max_skip: usize) -> Self {
+        self.max_skip = max_skip;
+        self
+    }
+
+    /// Make responses that fail before they can be compared, such as those for
+    /// unknown users or without an outstanding challenge, do the same parsing
+    /// and hashing as a response that does not match, against a dummy chain.
+    /// This way, how long verification takes does not reveal which users
+    /// exist. The error returned is unchanged, so applications must also
+    /// report [ServerError::UnknownUser] to clients the same way as a
+    /// mismatch. Disabled by default.
+    pub fn with_uniform_timing (mut self, uniform_timing: bool) -> Self {
+        self.uniform_timing = uniform_timing;
+        self
+    }
+
+    /// The users whose sequences were initialized more than `max_age` seconds
+    /// ago, or at an unknown time, such as those imported from another server,
+    /// so that they can be disabled or asked to re-initialize. Section 6.0 of
+    /// [IETF RFC 2289](https://www.rfc-editor.org/rfc/rfc2289.html) advises
+    /// changing the passphrase and seed regularly.
+    #[cfg(feature = "alloc")]
+    pub fn stale_accounts (&self, max_age: u64) -> alloc::vec::Vec<String> {
+        let now = self.clock.now_secs();
+        let mut users = alloc::vec::Vec::new();
+        self.store.for_each(&mut |user, state| {
+            if state.is_stale(now, max_age) {
+                users.push(String::from(user));
+            }
+        });
+        users
+    }
+
+    /// Counts of verifications and their outcomes since the server was created.
+    pub fn stats (&self) -> ServerStats {
+        self.stats
+    }
+
+    /// The underlying store.
+    pub fn store (&self) -> &S {
+        &self.store
+    }
+
+    /// The underlying store, mutably, such as for provisioning users.
+    pub fn store_mut (&mut self) -> &mut S {
+        &mut self.store
+    }
+
+    /// The policy of `realm`, if it is one of the server's realms.
+    pub fn realm_policy (&self, realm: &str) -> Option<RealmPolicy> {
+        self.realms.iter().find(|(name, _)| *name == realm).map(|(_, policy)| *policy)
+    }
+
+    /// Like [OtpServer::issue_challenge], but for `user` in `realm`, whose
+    /// state is stored under [realm_user_key].
+    #[cfg(feature = "alloc")]
+    pub fn issue_challenge_in_realm (&mut self, realm: &str, user: &str) -> Result<IssuedChallenge, ServerError> {
+        self.realm_policy(realm).ok_or(ServerError::UnknownRealm)?;
+        let key = realm_user_key(realm, user).ok_or(ServerError::UnknownUser)?;
+        self.issue_challenge(&key)
+    }
+
+    /// Like [OtpServer::verify], but for `user` in `realm`, whose state is
+    /// stored under [realm_user_key], applying the realm's policy instead of
+    /// the server-wide one. The server's [PreVerifyHook] is given the key, not
+    /// the bare user name.
+    #[cfg(feature = "alloc")]
+    pub fn verify_in_realm (&mut self, realm: &str, user: &str, response: &str) -> Result<VerifyOutcome, ServerError> {
+        let policy = self.realm_policy(realm).ok_or(ServerError::UnknownRealm)?;
+        let key = realm_user_key(realm, user).ok_or(ServerError::UnknownUser)?;
+        let server_policy = core::mem::replace(&mut self.policy, policy);
+        let result = self.verify(&key, response);
+        self.policy = server_policy;
+        result
+    }
+
+    /// Issue a challenge to `user`, recording when it was issued. Issuing a
+    /// new challenge replaces any outstanding one.
+    pub fn issue_challenge (&mut self, user: &str) -> Result<IssuedChallenge, ServerError> {
+        let mut state = self.store.load(user).ok_or(ServerError::UnknownUser)?;
+        if state.count == 0 {
+            return Err(ServerError::SequenceExhausted);
+        }
+        state.challenge_issued_at = Some(self.clock.now_secs());
+        self.store.save(user, state);
+        let challenge = IssuedChallenge {
+            alg: state.alg,
+            count: state.count - 1,
+            seed: state.seed,
+            reinit_required: state.reinit_required,
+        };
+        otp_event!(info, alg = state.alg.name(), count = challenge.count, "OTP challenge issued");
+        Ok(challenge)
+    }
+
+    /// Require `user` to re-initialize, such as after their passphrase may have
+    /// been compromised. Their account is kept, but from now on, only init
+    /// responses are accepted, and other responses fail with
+    /// [ServerError::ReinitRequired].
+    pub fn force_reinit (&mut self, user: &str) -> Result<(), ServerError> {
+        let mut state = self.store.load(user).ok_or(ServerError::UnknownUser)?;
+        state.reinit_required = true;
+        self.store.save(user, state);
+        otp_event!(info, "OTP re-initialization required");
+        Ok(())
+    }
+
+    /// Verify `response` to the challenge outstanding for `user`. If it is
+    /// accepted, the user's state moves down one count, or, for an init
+    /// response, to the new sequence.
+    ///
+    /// Responses to challenges that were issued longer ago than the challenge
+    /// lifetime are rejected with [ServerError::ChallengeExpired], and the
+    /// expired challenge is discarded. Responses for lower counts than the
+    /// challenged one are rejected unless allowed by
+    /// [OtpServer::with_max_skip]. If the current OTP of an init response
+    /// matches, but its new sequence is refused, the current OTP is still
+    /// used up, so that it cannot be replayed.
+    ///
+    /// The new state is stored with [OtpStore::compare_and_swap], so if the
+    /// user's state changes during verification, nothing is stored, and
+    /// [ServerError::Conflict] is returned.
+    ///
+    /// The server's [PreVerifyHook] is given empty [RequestMetadata]. Use
+    /// [OtpServer::verify_with_metadata] to describe the request.
+    pub fn verify (&mut self, user: &str, response: &str) -> Result<VerifyOutcome, ServerError> {
+        self.verify_with_metadata(user, response, &RequestMetadata::default())
+    }
+
+    /// Like [OtpServer::verify], but first asks the server's [PreVerifyHook]
+    /// whether to proceed, given `metadata` about the request. If it denies
+    /// the request, [ServerError::Denied] is returned.
+    pub fn verify_with_metadata (
+        &mut self,
+        user: &str,
+        response: &str,
+        metadata: &RequestMetadata<'_>,
+    ) -> Result<VerifyOutcome, ServerError> {
+        let result = self.verify_unrecorded(user, response, metadata);
+        self.stats.record(&result);
+        result
+    }
+
+    /// Verify a response as [OtpServer::verify_with_metadata] does, without
+    /// recording the result in [OtpServer::stats].
+    fn verify_unrecorded (
+        &mut self,
+        user: &str,
+        response: &str,
+        metadata: &RequestMetadata<'_>,
+    ) -> Result<VerifyOutcome, ServerError> {
+        if self.hook.check(user, metadata) == HookDecision::Deny {
+            otp_event!(warn, outcome = "denied", "OTP verification failed");
+            return Err(ServerError::Denied);
+        }
+        let Some(mut state) = self.store.load(user) else {
+            let alg = self.policy.allowed_algs.iter().copied().find(HashAlg::is_enabled).unwrap_or(HashAlg::Md5);
+            self.dummy_verify(alg, response);
+            return Err(ServerError::UnknownUser);
+        };
+        let Some(issued_at) = state.challenge_issued_at else {
+            self.dummy_verify(state.alg, response);
+            return Err(ServerError::NoChallenge);
+        };
+        let now = self.clock.now_secs();
+        if now.saturating_sub(issued_at) > self.challenge_ttl {
+            self.dummy_verify(state.alg, response);
+            state.challenge_issued_at = None;
+            self.store.save(user, state);
+            otp_event!(warn, outcome = "expired", "OTP verification failed");
+            return Err(ServerError::ChallengeExpired);
+        }
+        let Some(parsed) = parse_otp_response(response) else {
+            self.dummy_verify(state.alg, response);
+            return Err(ServerError::InvalidResponse);
+        };
+        let decoded = match &parsed {
+            OTPResponse::Current(otp) => otp.decode(),
+            OTPResponse::Init(init) => init.current_otp.decode(),
+        };
+        let current = match decoded {
+            Ok(current) => current,
+            Err(e) => {
+                self.dummy_verify(state.alg, response);
+                return Err(ServerError::Undecodable(e));
+            },
+        };
+        let mut hashed = next_otp(state.alg, &current).ok_or(ServerError::UnsupportedAlgorithm)?;
+        let max_skip = self.max_skip.min(state.count.saturating_sub(1));
+        let mut skipped = 0;
+        while !verify_otp_eq(&hashed, &state.otp) {
+            if skipped == max_skip {
+                if verify_otp_eq(&current, &state.otp) {
+                    self.stats.replays += 1;
+                    otp_event!(warn, outcome = "replay", "OTP verification failed");
+                } else {
+                    otp_event!(warn, outcome = "mismatch", "OTP verification failed");
+                }
+                return Ok(VerifyOutcome::default());
+            }
+            hashed = next_otp(state.alg, &hashed).ok_or(ServerError::UnsupportedAlgorithm)?;
+            skipped += 1;
+        }
+        let used = OtpState {
+            count: state.count - 1 - skipped,
+            otp: current,
+            challenge_issued_at: None,
+            last_used: Some(now),
+            ..state
+        };
+        let new_state = match &parsed {
+            OTPResponse::Current(_) => used,
+            OTPResponse::Init(init) => match self.verify_init(&state, init) {
+                Ok(new_state) => OtpState {
+                    last_used: Some(now),
+                    ..new_state.with_created_at(now)
+                },
+                // The current OTP was correct, so it is used up even though the
+                // new sequence is refused, so that it cannot be replayed.
+                Err(e) => {
+                    if !self.store.compare_and_swap(user, &state.otp, used) {
+                        otp_event!(warn, outcome = "conflict", "OTP verification failed");
+                        return Err(ServerError::Conflict);
+                    }
+                    return Err(e);
+                },
+            },
+        };
+        if !self.store.compare_and_swap(user, &state.otp, new_state) {
+            otp_event!(warn, outcome = "conflict", "OTP verification failed");
+            return Err(ServerError::Conflict);
+        }
+        if new_state.reinit_required {
+            otp_event!(warn, outcome = "reinit required", "OTP verification failed");
+            return Err(ServerError::ReinitRequired);
+        }
+        if skipped > 0 {
+            otp_event!(
+                warn,
+                challenged = state.count - 1,
+                matched = state.count - 1 - skipped,
+                "OTP response was for a lower count than challenged",
+            );
+        }
+        otp_event!(info, outcome = "match", "OTP verification succeeded");
+        Ok(VerifyOutcome {
+            accepted: true,
+            new_sequence: Some(new_state.count),
+            reinit_performed: matches!(parsed, OTPResponse::Init(_)),
+            skipped,
+            warnings: VerifyWarnings {
+                low_count: new_state.count <= self.low_count_threshold,
+                deprecated_alg: new_state.alg.is_deprecated(),
+            },
+        })
+    }
+
+    /// Issue two challenges to `user` at once, for consecutive counts, for
+    /// operations that require two OTPs, such as high-value transactions.
+    /// The responses must be verified together with [OtpServer::verify_dual].
+    pub fn issue_dual_challenge (&mut self, user: &str) -> Result<[IssuedChallenge; 2], ServerError> {
+        let state = self.store.load(user).ok_or(ServerError::UnknownUser)?;
+        if state.count < 2 {
+            return Err(ServerError::SequenceExhausted);
+        }
+        let first = self.issue_challenge(user)?;
+        Ok([ first, IssuedChallenge { count: first.count - 1, ..first } ])
+    }
+
+    /// Verify the responses to both challenges issued by
+    /// [OtpServer::issue_dual_challenge], only accepting them if both match.
+    /// Neither may be an init response.
+    ///
+    /// The first response is verified as by [OtpServer::verify]. If it is
+    /// accepted, but the second is not, the first is still used up, so that it
+    /// cannot be replayed, and the user must start again with new challenges.
+    pub fn verify_dual (&mut self, user: &str, first: &str, second: &str) -> Result<VerifyOutcome, ServerError> {
+        let result = self.verify_dual_unrecorded(user, first, second);
+        self.stats.record(&result);
+        result
+    }
+
+    /// Verify two responses as [OtpServer::verify_dual] does, without
+    /// recording the result in [OtpServer::stats].
+    fn verify_dual_unrecorded (&mut self, user: &str, first: &str, second: &str) -> Result<VerifyOutcome, ServerError> {
+        if !matches!(parse_otp_response(first), Some(OTPResponse::Current(_))) {
+            return Err(ServerError::InvalidResponse);
+        }
+        let second = match parse_otp_response(second) {
+            Some(OTPResponse::Current(otp)) => otp.decode()?,
+            _ => return Err(ServerError::InvalidResponse),
+        };
+        let outcome = self.verify_unrecorded(user, first, &RequestMetadata::default())?;
+        if !outcome.accepted {
+            return Ok(outcome);
+        }
+        let state = self.store.load(user).ok_or(ServerError::UnknownUser)?;
+        if state.count == 0 {
+            return Err(ServerError::SequenceExhausted);
+        }
+        let hashed = next_otp(state.alg, &second).ok_or(ServerError::UnsupportedAlgorithm)?;
+        if !verify_otp_eq(&hashed, &state.otp) {
+            otp_event!(warn, outcome = "mismatch", "second OTP verification failed");
+            return Ok(VerifyOutcome::default());
+        }
+        let new_state = OtpState {
+            count: state.count - 1,
+            otp: second,
+            last_used: Some(self.clock.now_secs()),
+            ..state
+        };
+        if !self.store.compare_and_swap(user, &state.otp, new_state) {
+            otp_event!(warn, outcome = "conflict", "second OTP verification failed");
+            return Err(ServerError::Conflict);
+        }
+        otp_event!(info, outcome = "match", "second OTP verification succeeded");
+        Ok(VerifyOutcome {
+            new_sequence: Some(new_state.count),
+            warnings: VerifyWarnings {
+                low_count: new_state.count <= self.low_count_threshold,
+                ..outcome.warnings
+            },
+            ..outcome
+        })
+    }
+
+    /// If [OtpServer::with_uniform_timing] is enabled, parse `response` and
+    /// hash it as many times as a mismatched response would be, comparing it
+    /// against a dummy value, and discard the result.
+    fn dummy_verify (&self, alg: HashAlg, response: &str) {
+        if !self.uniform_timing {
+            return;
+        }
+        let current = match parse_otp_response(response) {
+            Some(OTPResponse::Current(otp)) => otp.try_into_bytes(),
+            Some(OTPResponse::Init(init)) => init.current_otp.try_into_bytes(),
+            None => None,
+        };
+        let mut hashed = current.unwrap_or_default();
+        for _ in 0..=self.max_skip.min(DEFAULT_MAX_COUNT) {
+            hashed = next_otp(alg, &hashed).unwrap_or(hashed);
+            core::hint::black_box(verify_otp_eq(&hashed, &[ 0; 8 ]));
+        }
+    }
+
+    /// Validate the new parameters of an init response whose current OTP has
+    /// already been verified using the sequence's existing algorithm, and
+    /// return the state for the new sequence. The new algorithm may differ
+    /// from the existing one, as when re-keying from MD5 to SHA1, but it must
+    /// be supported and allowed by the server's policy, and the new seed must
+    /// not have been used before.
+    fn verify_init (&self, state: &OtpState, init: &OTPInit<'_>) -> Result<OtpState, ServerError> {
+        let init = init.validate().map_err(|e| match e {
+            InitValidationError::CurrentOtp(e) | InitValidationError::NewOtp(e) => ServerError::Undecodable(e),
+            InitValidationError::UnsupportedAlgorithm => ServerError::UnsupportedAlgorithm,
+            InitValidationError::BadCount => ServerError::InvalidResponse,
+            InitValidationError::BadSeed => ServerError::BadSeed,
+        })?;
+        let (alg, seed) = (init.new_alg, init.new_seed);
+        if !self.policy.allowed_algs.contains(&alg) {
+            otp_event!(warn, outcome = "disallowed", new_alg = alg.name(), "OTP re-initialization refused");
+            return Err(ServerError::AlgorithmNotAllowed);
+        }
+        if !(self.policy.min_count..=self.policy.max_count).contains(&init.new_seq_num) {
+            otp_event!(warn, outcome = "disallowed", new_count = init.new_seq_num, "OTP re-initialization refused");
+            return Err(ServerError::CountNotAllowed);
+        }
+        if self.seed_history > 0 && state.has_used_seed(seed.as_str()) {
+            otp_event!(warn, outcome = "seed reused", "OTP re-initialization refused");
+            return Err(ServerError::SeedReused);
+        }
+        otp_event!(
+            info,
+            outcome = "reinit",
+            old_alg = state.alg.name(),
+            new_alg = alg.name(),
+            new_count = init.new_seq_num,
+            "OTP re-initialization accepted",
+        );
+        let mut new_state = OtpState::new(alg, init.new_seq_num, seed, init.new_otp);
+        new_state.seen_seeds = state.seen_seeds;
+        new_state.seen_seeds.push(state.seed, self.seed_history);
+        Ok(new_state)
+    }
+
+}
+
+#[cfg(all(test, feature = "alloc", feature = "md5", feature = "sha1"))]
+mod tests {
+    use super::*;
+    use alloc::string::{String, ToString};
+    use crate::{calculate_otp_with_alg, clock::ManualClock, format_hex, otp_to_word_string, HexStyle};
+
+    const PASSPHRASE: &str = "This is a test.";
+
+    fn server () -> OtpServer<MemoryStore, ManualClock> {
+        let mut store = MemoryStore::new();
+        let seed = Seed::new("TeSt").unwrap();
+        let otp = calculate_otp_with_alg(HashAlg::Md5, PASSPHRASE, "TeSt", 100).unwrap();
+        store.save("jdoe", OtpState::new(HashAlg::Md5, 100, seed, otp));
+        OtpServer::new(store, ManualClock::new(1000)).with_challenge_ttl(60)
+    }
+
+    #[test]
+    #[cfg(feature = "binary-state")]
+    fn encodes_states_as_bytes () {
+        let mut state = OtpState::new(HashAlg::Sha1, 499, Seed::new("ke1234").unwrap(), [ 0x8e; 8 ])
+            .with_created_at(1000);
+        state.last_used = Some(u64::MAX);
+        state.reinit_required = true;
+        let bytes = state.to_bytes();
+        assert_eq!(&bytes[..4], &[ 1, 0b1110, 3, 0 ]);
+        assert_eq!(OtpState::from_bytes(&bytes), Some(state));
+
+        state.challenge_issued_at = Some(5);
+        state.seen_seeds.push(Seed::new("old1").unwrap(), SEED_HISTORY_LEN);
+        let decoded = OtpState::from_bytes(&state.to_bytes()).unwrap();
+        assert_eq!(decoded.challenge_issued_at, Some(5));
+        assert_eq!(decoded.seen_seeds, SeedHistory::new());
+
+        let corrupt = |i: usize, b: u8| {
+            let mut bytes = bytes;
+            bytes[i] = b;
+            OtpState::from_bytes(&bytes)
+        };
+        assert_eq!(corrupt(0, 2), None);
+        assert_eq!(corrupt(1, 0x10), None);
+        assert_eq!(corrupt(2, 0), None);
+        assert_eq!(corrupt(11, 0), None);
+        assert_eq!(corrupt(11, 17), None);
+        assert_eq!(corrupt(12, b'_'), None);
+    }
+
+    #[test]
+    fn imports_legacy_state () {
+        let otp = [ 0x8e, 0x8f, 0x4a, 0x71, 0xbb, 0xb6, 0xec, 0x6c ];
+        let seed = Seed::new("ke1234").unwrap();
+        let opie = import_state("jdoe 0499 ke1234           8e8f4a71bbb6ec6c  Apr 01,2024 12:00:00\n").unwrap();
+        assert_eq!(opie, ImportedState {
+            user: "jdoe",
+            dialect: StateDialect::Opie,
+            state: OtpState::new(HashAlg::Md5, 499, seed, otp),
+        });
+        let bellcore = import_state_with_default_alg("jdoe 0499 ke1234 8e8f4a71bbb6ec6c", HashAlg::Md4).unwrap();
+        assert_eq!(bellcore.state.alg, HashAlg::Md4);
+        let logdaemon = import_state("jdoe MD5 0499 ke1234 8E8F4A71BBB6EC6C Apr 01,2024 12:00:00").unwrap();
+        assert_eq!(logdaemon.dialect, StateDialect::Logdaemon);
+        assert_eq!(logdaemon.state, opie.state);
+        let openbsd = import_state("jdoe\notp-sha1\n499\nke1234\n8e8f4a71bbb6ec6c\n").unwrap();
+        assert_eq!(openbsd.dialect, StateDialect::OpenBsd);
+        assert_eq!(openbsd.state, OtpState::new(HashAlg::Sha1, 499, seed, otp));
+
+        assert_eq!(import_state("jdoe 0499 ke1234"), None);
+        assert_eq!(import_state("jdoe md6 0499 ke1234 8e8f4a71bbb6ec6c"), None);
+        assert_eq!(import_state("jdoe 0499 ke_1234 8e8f4a71bbb6ec6c"), None);
+        assert_eq!(import_state("jdoe 0499 ke1234 8e8f4a71bbb6ec6"), None);
+        assert_eq!(import_state("jdoe\nmd5\n499\nke1234"), None);
+        assert_eq!(import_state("jdoe\nmd6\n499\nke1234\n8e8f4a71bbb6ec6c"), None);
+    }
+
+    fn respond (alg: HashAlg, count: usize) -> String {
+        let otp = calculate_otp_with_alg(alg, PASSPHRASE, "TeSt", count).unwrap();
+        otp_to_word_string(&otp, true)
+    }
+
+    /// An init response to the first MD5 challenge, switching to a new
+    /// sequence using `new_alg` with count 500 and seed `new1`.
+    fn init_response (new_alg: HashAlg, new_otp: &[u8; 8]) -> String {
+        init_response_with_seed(new_alg, "new1", new_otp)
+    }
+
+    fn init_response_with_seed (new_alg: HashAlg, new_seed: &str, new_otp: &[u8; 8]) -> String {
+        let current = calculate_otp_with_alg(HashAlg::Md5, PASSPHRASE, "TeSt", 99).unwrap();
+        [
+            format_hex(&current, HexStyle::CANONICAL.with_prefix()).replacen("hex:", "init-hex:", 1),
+            alloc::format!(":{} 500 {}:", new_alg, new_seed),
+            format_hex(new_otp, HexStyle::CANONICAL),
+        ].concat()
+    }
+
+    #[test]
+    fn verifies_responses_to_challenges () {
+        let mut server = server();
+        assert_eq!(server.verify("jdoe", &respond(HashAlg::Md5, 99)), Err(ServerError::NoChallenge));
+        let challenge = server.issue_challenge("jdoe").unwrap();
+        assert_eq!(challenge.to_string(), "otp-md5 99 TeSt");
+        assert_eq!(server.verify("jdoe", &respond(HashAlg::Md5, 98)), Ok(VerifyOutcome::default()));
+        assert_eq!(server.verify("jdoe", &respond(HashAlg::Md5, 99)), Ok(VerifyOutcome {
+            accepted: true,
+            new_sequence: Some(99),
+            reinit_performed: false,
+            skipped: 0,
+            warnings: VerifyWarnings { low_count: false, deprecated_alg: true },
+        }));
+        assert_eq!(server.store().load("jdoe").unwrap().count, 99);
+        assert_eq!(server.store().load("jdoe").unwrap().challenge_issued_at, None);
+        // The same response cannot be replayed.
+        assert_eq!(server.verify("jdoe", &respond(HashAlg::Md5, 99)), Err(ServerError::NoChallenge));
+        assert_eq!(server.issue_challenge("jdoe").unwrap().count, 98);
+        assert!(!server.verify("jdoe", &respond(HashAlg::Md5, 99)).unwrap().accepted);
+        assert_eq!(server.issue_challenge("jane"), Err(ServerError::UnknownUser));
+        assert_eq!(server.verify("jdoe", "word:JUNK"), Err(ServerError::InvalidResponse));
+    }
+
+    #[test]
+    fn runs_pre_verify_hooks () {
+        let blocked: IpAddr = [ 192, 0, 2, 1 ].into();
+        let mut server = server().with_pre_verify_hook(move |_: &str, metadata: &RequestMetadata<'_>| {
+            if metadata.remote_addr == Some(blocked) {
+                HookDecision::Deny
+            } else {
+                HookDecision::Allow
+            }
+        });
+        server.issue_challenge("jdoe").unwrap();
+        let metadata = RequestMetadata { remote_addr: Some(blocked), service: Some("sshd") };
+        let response = respond(HashAlg::Md5, 99);
+        assert_eq!(server.verify_with_metadata("jdoe", &response, &metadata), Err(ServerError::Denied));
+        assert_eq!(server.verify_with_metadata("jdoe", "junk", &metadata), Err(ServerError::Denied));
+        // A denied request does not consume the challenge.
+        assert!(server.store().load("jdoe").unwrap().challenge_issued_at.is_some());
+        let metadata = RequestMetadata { remote_addr: Some([ 198, 51, 100, 7 ].into()), ..metadata };
+        assert!(server.verify_with_metadata("jdoe", &response, &metadata).unwrap().accepted);
+    }
+
+    #[test]
+    fn fails_the_same_way_with_uniform_timing () {
+        let mut server = server().with_uniform_timing(true).with_max_skip(3);
+        let response = respond(HashAlg::Md5, 99);
+        assert_eq!(server.verify("jane", &response), Err(ServerError::UnknownUser));
+        assert_eq!(server.verify("jdoe", &response), Err(ServerError::NoChallenge));
+        server.issue_challenge("jdoe").unwrap();
+        assert_eq!(server.verify("jdoe", "word:JUNK"), Err(ServerError::InvalidResponse));
+        assert_eq!(
+            server.verify("jdoe", "word:INCH SEA ANNE LONG AHEM"),
+            Err(ServerError::Undecodable(WordDecodeError::WrongWordCount(5))),
+        );
+        assert!(!server.verify("jdoe", &respond(HashAlg::Md5, 50)).unwrap().accepted);
+        assert!(server.verify("jdoe", &response).unwrap().accepted);
+    }
+
+    #[test]
+    fn rejects_expired_challenges () {
+        let mut server = server();
+        server.issue_challenge("jdoe").unwrap();
+        server.clock.advance(61);
+        assert_eq!(server.verify("jdoe", &respond(HashAlg::Md5, 99)), Err(ServerError::ChallengeExpired));
+        assert_eq!(server.verify("jdoe", &respond(HashAlg::Md5, 99)), Err(ServerError::NoChallenge));
+        server.issue_challenge("jdoe").unwrap();
+        server.clock.advance(60);
+        assert!(server.verify("jdoe", &respond(HashAlg::Md5, 99)).unwrap().accepted);
+    }
+
+    #[test]
+    fn accepts_reinitialization () {
+        let mut server = server();
+        server.issue_challenge("jdoe").unwrap();
+        let new_otp = calculate_otp_with_alg(HashAlg::Sha1, "Another passphrase", "new1", 500).unwrap();
+        let response = init_response(HashAlg::Sha1, &new_otp);
+        assert_eq!(server.verify("jdoe", &response), Ok(VerifyOutcome {
+            accepted: true,
+            new_sequence: Some(500),
+            reinit_performed: true,
+            skipped: 0,
+            warnings: VerifyWarnings::default(),
+        }));
+        let state = server.store().load("jdoe").unwrap();
+        let mut expected = OtpState::new(HashAlg::Sha1, 500, Seed::new("new1").unwrap(), new_otp).with_created_at(1000);
+        expected.seen_seeds.push(Seed::new("TeSt").unwrap(), SEED_HISTORY_LEN);
+        expected.last_used = Some(1000);
+        assert_eq!(state, expected);
+    }
+
+    #[test]
+    fn refuses_disallowed_counts () {
+        let mut server = server().with_init_count_range(10, 400);
+        server.issue_challenge("jdoe").unwrap();
+        let new_otp = calculate_otp_with_alg(HashAlg::Md5, PASSPHRASE, "new1", 500).unwrap();
+        let response = init_response(HashAlg::Md5, &new_otp);
+        assert_eq!(server.verify("jdoe", &response), Err(ServerError::CountNotAllowed));
+
+        // The current OTP was used up, so the response cannot be replayed.
+        let state = server.store().load("jdoe").unwrap();
+        assert_eq!((state.count, state.challenge_issued_at), (99, None));
+        assert_eq!(server.issue_challenge("jdoe").unwrap().count, 98);
+        assert_eq!(server.verify("jdoe", &response), Ok(VerifyOutcome::default()));
+        assert_eq!(server.stats().replays, 1);
+    }
+
+    #[test]
+    fn isolates_realms () {
+        const REALMS: &[(&str, RealmPolicy)] = &[
+            ("acme", RealmPolicy { allowed_algs: &[HashAlg::Sha1], min_count: 1, max_count: 1000 }),
+            ("initech", RealmPolicy { allowed_algs: &[HashAlg::Md5, HashAlg::Sha1], min_count: 1, max_count: 400 }),
+        ];
+        let mut server = server().with_realms(REALMS);
+        let seed = Seed::new("TeSt").unwrap();
+        let otp = calculate_otp_with_alg(HashAlg::Md5, PASSPHRASE, "TeSt", 100).unwrap();
+        for realm in ["acme", "initech"] {
+            let key = realm_user_key(realm, "jdoe").unwrap();
+            server.store_mut().save(&key, OtpState::new(HashAlg::Md5, 100, seed, otp));
+        }
+        assert_eq!(split_realm_user_key("acme\0jdoe"), Some(("acme", "jdoe")));
+        assert_eq!(realm_user_key("acme", "jdoe\0x"), None);
+        assert_eq!(server.issue_challenge_in_realm("globex", "jdoe"), Err(ServerError::UnknownRealm));
+        assert_eq!(server.issue_challenge_in_realm("acme", "nobody"), Err(ServerError::UnknownUser));
+
+        // Each realm has its own state for the same user name.
+        server.issue_challenge_in_realm("acme", "jdoe").unwrap();
+        assert!(server.verify_in_realm("acme", "jdoe", &respond(HashAlg::Md5, 99)).unwrap().accepted);
+        assert_eq!(server.issue_challenge_in_realm("acme", "jdoe").unwrap().count, 98);
+        assert_eq!(server.issue_challenge_in_realm("initech", "jdoe").unwrap().count, 99);
+
+        // Each realm's policy applies to its own users.
+        let md5_otp = calculate_otp_with_alg(HashAlg::Md5, "Another passphrase", "new1", 500).unwrap();
+        assert_eq!(server.verify_in_realm("initech", "jdoe", &init_response(HashAlg::Md5, &md5_otp)), Err(ServerError::CountNotAllowed));
+        server.store_mut().save("initech\0jdoe", OtpState::new(HashAlg::Md5, 100, seed, otp));
+        server.issue_challenge_in_realm("initech", "jdoe").unwrap();
+        let sha1_otp = calculate_otp_with_alg(HashAlg::Sha1, "Another passphrase", "new1", 500).unwrap();
+        assert_eq!(server.verify_in_realm("initech", "jdoe", &init_response(HashAlg::Sha1, &sha1_otp)), Err(ServerError::CountNotAllowed));
+        assert!(server.store().load("jdoe").is_some());
+        assert_eq!(server.realm_policy("acme"), Some(REALMS[0].1));
+    }
+
+    #[test]
+    fn refuses_reused_seeds () {
+        let mut server = server();
+        let new_otp = calculate_otp_with_alg(HashAlg::Md5, PASSPHRASE, "test", 500).unwrap();
+        server.issue_challenge("jdoe").unwrap();
+        assert_eq!(server.verify("jdoe", &init_response_with_seed(HashAlg::Md5, "test", &new_otp)), Err(ServerError::SeedReused));
+
+        let mut server = self::server();
+        let mut state = server.store().load("jdoe").unwrap();
+        state.seen_seeds.push(Seed::new("old1").unwrap(), SEED_HISTORY_LEN);
+        server.store_mut().save("jdoe", state);
+        server.issue_challenge("jdoe").unwrap();
+        assert_eq!(server.verify("jdoe", &init_response_with_seed(HashAlg::Md5, "OLD1", &new_otp)), Err(ServerError::SeedReused));
+
+        let mut server = self::server().with_seed_history(0);
+        server.issue_challenge("jdoe").unwrap();
+        assert!(server.verify("jdoe", &init_response_with_seed(HashAlg::Md5, "test", &new_otp)).unwrap().accepted);
+    }
+
+    #[test]
+    fn bounds_seed_history () {
+        let mut history = SeedHistory::new();
+        for seed in [ "a", "b", "c" ] {
+            history.push(Seed::new(seed).unwrap(), 2);
+        }
+        assert!(history.iter().map(Seed::as_str).eq([ "c", "b" ]));
+        assert!(!history.contains("a"));
+        for i in 0..20 {
+            history.push(Seed::new(&alloc::format!("s{}", i)).unwrap(), usize::MAX);
+        }
+        assert_eq!(history.iter().count(), SEED_HISTORY_LEN);
+        assert!(history.contains("S19") && !history.contains("s11"));
+    }
+
+    #[test]
+    fn refuses_disallowed_algorithms () {
+        let mut server = server().with_allowed_algorithms(&[ HashAlg::Sha1 ]);
+        server.issue_challenge("jdoe").unwrap();
+        // The existing MD5 sequence may still be used...
+        let new_otp = calculate_otp_with_alg(HashAlg::Md5, "Another passphrase", "new1", 500).unwrap();
+        assert_eq!(server.verify("jdoe", &init_response(HashAlg::Md5, &new_otp)), Err(ServerError::AlgorithmNotAllowed));
+        assert_eq!(server.store().load("jdoe").unwrap().alg, HashAlg::Md5);
+        // ...and re-keyed to SHA1, once the OTP used up by the refused
+        // response is restored.
+        server.store_mut().save("jdoe", self::server().store().load("jdoe").unwrap());
+        server.issue_challenge("jdoe").unwrap();
+        let new_otp = calculate_otp_with_alg(HashAlg::Sha1, "Another passphrase", "new1", 500).unwrap();
+        assert!(server.verify("jdoe", &init_response(HashAlg::Sha1, &new_otp)).unwrap().reinit_performed);
+        assert_eq!(server.store().load("jdoe").unwrap().alg, HashAlg::Sha1);
+    }
+
+    #[test]
+    fn accepts_skipped_counts_up_to_the_limit () {
+        let mut server = server();
+        server.issue_challenge("jdoe").unwrap();
+        assert!(!server.verify("jdoe", &respond(HashAlg::Md5, 98)).unwrap().accepted);
+
+        let mut server = server.with_max_skip(2);
+        server.issue_challenge("jdoe").unwrap();
+        assert!(!server.verify("jdoe", &respond(HashAlg::Md5, 96)).unwrap().accepted);
+        let outcome = server.verify("jdoe", &respond(HashAlg::Md5, 97)).unwrap();
+        assert_eq!(outcome.new_sequence, Some(97));
+        assert_eq!(outcome.skipped, 2);
+        server.issue_challenge("jdoe").unwrap();
+        assert_eq!(server.verify("jdoe", &respond(HashAlg::Md5, 96)).unwrap().skipped, 0);
+    }
+
+    #[test]
+    fn finds_stale_accounts () {
+        const YEAR: u64 = 365 * 24 * 60 * 60;
+        let mut server = server();
+        let seed = Seed::new("ke1234").unwrap();
+        server.store_mut().save("fresh", OtpState::new(HashAlg::Sha1, 100, seed, [ 0; 8 ]).with_created_at(1000));
+        assert_eq!(server.stale_accounts(YEAR), ["jdoe"]);
+
+        server.issue_challenge("jdoe").unwrap();
+        let new_otp = calculate_otp_with_alg(HashAlg::Sha1, "Another passphrase", "new1", 500).unwrap();
+        assert!(server.verify("jdoe", &init_response(HashAlg::Sha1, &new_otp)).unwrap().accepted);
+        assert!(server.stale_accounts(YEAR).is_empty());
+
+        server.clock.advance(YEAR);
+        server.issue_challenge("jdoe").unwrap();
+        let outcome = server.verify("jdoe", &otp_to_word_string(
+            &calculate_otp_with_alg(HashAlg::Sha1, "Another passphrase", "new1", 499).unwrap(),
+            true,
+        ));
+        assert!(outcome.unwrap().accepted);
+        let jdoe = server.store().load("jdoe").unwrap();
+        assert_eq!((jdoe.created_at, jdoe.last_used), (Some(1000), Some(1000 + YEAR)));
+        assert!(server.stale_accounts(YEAR).is_empty());
+        server.clock.advance(1);
+        assert_eq!(server.stale_accounts(YEAR), ["fresh", "jdoe"]);
+    }
+
+    #[test]
+    fn verifies_dual_responses () {
+        let mut server = server();
+        let [first, second] = server.issue_dual_challenge("jdoe").unwrap();
+        assert_eq!((first.count, second.count), (99, 98));
+        let outcome = server.verify_dual("jdoe", &respond(HashAlg::Md5, 99), &respond(HashAlg::Md5, 98)).unwrap();
+        assert!(outcome.accepted);
+        assert_eq!(outcome.new_sequence, Some(98));
+        assert_eq!(server.store().load("jdoe").unwrap().count, 98);
+
+        // The first response is used up even if the second is wrong.
+        server.issue_dual_challenge("jdoe").unwrap();
+        let outcome = server.verify_dual("jdoe", &respond(HashAlg::Md5, 97), &respond(HashAlg::Md5, 97)).unwrap();
+        assert!(!outcome.accepted);
+        assert_eq!(server.store().load("jdoe").unwrap().count, 97);
+        assert_eq!(server.verify_dual("jdoe", &respond(HashAlg::Md5, 96), &respond(HashAlg::Md5, 95)), Err(ServerError::NoChallenge));
+
+        server.issue_dual_challenge("jdoe").unwrap();
+        let new_otp = calculate_otp_with_alg(HashAlg::Sha1, "Another passphrase", "new1", 500).unwrap();
+        let init = init_response(HashAlg::Sha1, &new_otp);
+        assert_eq!(server.verify_dual("jdoe", &init, &respond(HashAlg::Md5, 95)), Err(ServerError::InvalidResponse));
+        assert_eq!(server.verify_dual("jdoe", &respond(HashAlg::Md5, 96), "word:"), Err(ServerError::InvalidResponse));
+        assert_eq!(server.store().load("jdoe").unwrap().count, 97);
+
+        let seed = Seed::new("ke1234").unwrap();
+        server.store_mut().save("last", OtpState::new(HashAlg::Md5, 1, seed, [ 0; 8 ]));
+        assert_eq!(server.issue_dual_challenge("last"), Err(ServerError::SequenceExhausted));
+    }
+
+    #[test]
+    fn forces_reinitialization () {
+        let mut server = server();
+        assert_eq!(server.force_reinit("nobody"), Err(ServerError::UnknownUser));
+        server.force_reinit("jdoe").unwrap();
+        assert!(server.issue_challenge("jdoe").unwrap().reinit_required);
+        assert_eq!(server.verify("jdoe", &respond(HashAlg::Md5, 99)), Err(ServerError::ReinitRequired));
+        let state = server.store().load("jdoe").unwrap();
+        assert_eq!(state.count, 99);
+        assert!(state.reinit_required);
+
+        server.issue_challenge("jdoe").unwrap();
+        assert_eq!(server.verify("jdoe", &respond(HashAlg::Md5, 98)), Err(ServerError::ReinitRequired));
+        server.issue_challenge("jdoe").unwrap();
+        let new_otp = calculate_otp_with_alg(HashAlg::Sha1, "Another passphrase", "new1", 500).unwrap();
+        let current = calculate_otp_with_alg(HashAlg::Md5, PASSPHRASE, "TeSt", 97).unwrap();
+        let response = alloc::format!(
+            "init-hex:{}:sha1 500 new1:{}",
+            format_hex(&current, HexStyle::CANONICAL),
+            format_hex(&new_otp, HexStyle::CANONICAL),
+        );
+        assert!(server.verify("jdoe", &response).unwrap().reinit_performed);
+        assert!(!server.store().load("jdoe").unwrap().reinit_required);
+        assert!(!server.issue_challenge("jdoe").unwrap().reinit_required);
+    }
+
+    #[test]
+    fn counts_verifications () {
+        let mut server = server();
+        server.issue_challenge("jdoe").unwrap();
+        assert!(server.verify("jdoe", &respond(HashAlg::Md5, 99)).unwrap().accepted);
+        server.issue_challenge("jdoe").unwrap();
+        assert!(!server.verify("jdoe", &respond(HashAlg::Md5, 99)).unwrap().accepted);
+        assert!(!server.verify("jdoe", &respond(HashAlg::Md5, 97)).unwrap().accepted);
+        assert_eq!(server.verify("nobody", &respond(HashAlg::Md5, 97)), Err(ServerError::UnknownUser));
+        let new_otp = calculate_otp_with_alg(HashAlg::Sha1, "Another passphrase", "new1", 500).unwrap();
+        let current = calculate_otp_with_alg(HashAlg::Md5, PASSPHRASE, "TeSt", 98).unwrap();
+        let init = alloc::format!(
+            "init-hex:{}:sha1 500 new1:{}",
+            format_hex(&current, HexStyle::CANONICAL),
+            format_hex(&new_otp, HexStyle::CANONICAL),
+        );
+        assert!(server.verify("jdoe", &init).unwrap().reinit_performed);
+        server.issue_challenge("jdoe").unwrap();
+        assert_eq!(server.verify_dual("jdoe", "word:", "word:"), Err(ServerError::InvalidResponse));
+        assert_eq!(server.stats(), ServerStats {
+            verifications: 6,
+            successes: 2,
+            failures: 4,
+            replays: 1,
+            reinits: 1,
+        });
+    }
+
+    #[test]
+    fn tolerates_off_by_one_clients () {
+        let mut server = server();
+        server.issue_challenge("jdoe").unwrap();
+        assert!(!server.verify("jdoe", &respond(HashAlg::Md5, 98)).unwrap().accepted);
+        let mut server = server.with_max_skip(1);
+        server.issue_challenge("jdoe").unwrap();
+        let outcome = server.verify("jdoe", &respond(HashAlg::Md5, 98)).unwrap();
+        assert_eq!((outcome.accepted, outcome.skipped, outcome.new_sequence), (true, 1, Some(98)));
+    }
+
+    /// A store in which another login completes between each load and
+    /// compare-and-swap.
+    struct RacingStore(MemoryStore);
+
+    impl OtpStore for RacingStore {
+
+        fn load (&self, user: &str) -> Option<OtpState> {
+            self.0.load(user)
+        }
+
+        fn save (&mut self, user: &str, state: OtpState) {
+            self.0.save(user, state)
+        }
+
+        fn for_each (&self, f: &mut dyn FnMut(&str, &OtpState)) {
+            self.0.for_each(f)
+        }
+
+        fn compare_and_swap (&mut self, user: &str, expected_otp: &[u8; 8], new: OtpState) -> bool {
+            let mut state = self.0.load(user).unwrap();
+            state.otp = calculate_otp_with_alg(HashAlg::Md5, PASSPHRASE, "TeSt", state.count - 1).unwrap();
+            state.count -= 1;
+            self.0.save(user, state);
+            self.0.compare_and_swap(user, expected_otp, new)
+        }
+
+    }
+
+    #[test]
+    fn detects_concurrent_modification () {
+        let mut server = OtpServer::new(RacingStore(server().store), ManualClock::new(1000));
+        server.issue_challenge("jdoe").unwrap();
+        assert_eq!(server.verify("jdoe", &respond(HashAlg::Md5, 99)), Err(ServerError::Conflict));
+        assert_eq!(server.store().0.load("jdoe").unwrap().count, 99);
+
+        let mut store = MemoryStore::new();
+        let state = server.store().0.load("jdoe").unwrap();
+        assert!(!store.compare_and_swap("jdoe", &state.otp, state));
+        store.save("jdoe", state);
+        assert!(!store.compare_and_swap("jdoe", &[ 0; 8 ], state));
+        assert!(store.compare_and_swap("jdoe", &state.otp, OtpState { count: 1, ..state }));
+        assert_eq!(store.load("jdoe").unwrap().count, 1);
+    }
+
+    #[test]
+    fn warns_when_few_otps_remain () {
+        let mut server = server().with_low_count_threshold(98);
+        server.issue_challenge("jdoe").unwrap();
+        assert!(!server.verify("jdoe", &respond(HashAlg::Md5, 99)).unwrap().warnings.low_count);
+        server.issue_challenge("jdoe").unwrap();
+        let outcome = server.verify("jdoe", &respond(HashAlg::Md5, 98)).unwrap();
+        assert_eq!(outcome.new_sequence, Some(98));
+        assert!(outcome.warnings.low_count);
+    }
+
+}
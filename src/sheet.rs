@@ -0,0 +1,421 @@
+//! Printable OTP sheets: a range of OTP values, laid out in numbered columns,
+//! for users who carry their one-time passwords on paper.
+//!
+//! ```text
+//! otp-md5 ke1234
+//!
+//! 10: ROME MUG FRED SCAN LIVE LACE      7: INCH SEA ANNE LONG AHEM TOUR
+//!  9: ...                               6: ...
+//! ```
+//!
+//! Values are listed from the highest count to the lowest, since that is the
+//! order in which they are used, running down each column in turn. An
+//! [OtpList] keeps track of which of them have been used.
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+use core::ops::RangeInclusive;
+use crate::{
+    calculate_otp_with_alg,
+    convert_to_word_format,
+    next_otp,
+    write_hex,
+    CalculateError,
+    HashAlg,
+    HexStyle,
+    DEFAULT_MAX_COUNT,
+    MAX_HEX_STRING_LEN,
+    MAX_WORD_STRING_LEN,
+};
+use crate::redact::{redacted_debug, RevealDebug, REDACTED};
+#[cfg(feature = "parsing-challenge")]
+use crate::OTPChallenge;
+
+/// Options for [write_sheet] and [format_sheet].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SheetOptions {
+    /// The number of columns per page. Zero is treated as one.
+    pub columns: usize,
+    /// Print OTP values in hexadecimal instead of as six words.
+    pub hex: bool,
+    /// The number of rows per page, after which a form feed is written. Zero
+    /// means that the sheet is not broken into pages.
+    pub rows_per_page: usize,
+    /// Start each page with a line identifying the algorithm and seed, such as
+    /// `otp-md5 ke1234`.
+    pub header: bool,
+}
+
+impl Default for SheetOptions {
+
+    fn default() -> Self {
+        SheetOptions {
+            columns: 2,
+            hex: false,
+            rows_per_page: 0,
+            header: true,
+        }
+    }
+
+}
+
+/// An error generating an OTP sheet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SheetError {
+    /// An OTP value could not be calculated.
+    Calculate(CalculateError),
+    /// The underlying writer returned an error.
+    Fmt,
+    /// The number of values per page, `columns` times `rows_per_page`, does
+    /// not fit in a `usize`.
+    PageTooLarge,
+}
+
+impl fmt::Display for SheetError {
+
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SheetError::Calculate(e) => e.fmt(f),
+            SheetError::Fmt => f.write_str("could not write OTP sheet"),
+            SheetError::PageTooLarge => f.write_str("OTP sheet page size overflows"),
+        }
+    }
+
+}
+
+impl From<fmt::Error> for SheetError {
+
+    fn from(_: fmt::Error) -> Self {
+        SheetError::Fmt
+    }
+
+}
+
+/// Write one OTP value, padded to `width` characters unless `last` is set.
+fn write_cell <W: fmt::Write> (
+    w: &mut W,
+    count: usize,
+    count_width: usize,
+    otp: &[u8; 8],
+    hex: bool,
+    last: bool,
+) -> fmt::Result {
+    write!(w, "{:>width$}: ", count, width = count_width)?;
+    let mut len = 0;
+    if hex {
+        write_hex(w, otp, HexStyle::CANONICAL)?;
+        len = MAX_HEX_STRING_LEN - "hex:".len();
+    } else {
+        for (i, word) in convert_to_word_format(otp).iter().enumerate() {
+            if i > 0 {
+                w.write_char(' ')?;
+                len += 1;
+            }
+            w.write_str(word)?;
+            len += word.len();
+        }
+    }
+    if !last {
+        let width = if hex {
+            MAX_HEX_STRING_LEN - "hex:".len()
+        } else {
+            MAX_WORD_STRING_LEN - "word:".len()
+        };
+        // Four spaces between columns.
+        for _ in len..width + 4 {
+            w.write_char(' ')?;
+        }
+    }
+    Ok(())
+}
+
+/// Calculate the OTP values from `high` down to `low`, highest count first, by
+/// hashing the lowest count once and then hashing once more for each count
+/// after it.
+fn calculate_range (
+    alg: HashAlg,
+    passphrase: &str,
+    seed: &str,
+    low: usize,
+    high: usize,
+) -> Result<Vec<[u8; 8]>, CalculateError> {
+    if high > DEFAULT_MAX_COUNT {
+        return Err(CalculateError::CountTooLarge(DEFAULT_MAX_COUNT));
+    }
+    let mut otp = calculate_otp_with_alg(alg, passphrase, seed, low).ok_or(CalculateError::UnsupportedAlgorithm)?;
+    let mut otps: Vec<[u8; 8]> = Vec::with_capacity(high - low + 1);
+    otps.push(otp);
+    for _ in low..high {
+        otp = next_otp(alg, &otp).ok_or(CalculateError::UnsupportedAlgorithm)?;
+        otps.push(otp);
+    }
+    otps.reverse();
+    Ok(otps)
+}
+
+/// Write a printable sheet of the OTP values for each count in `counts`.
+///
+/// The values are calculated by hashing the lowest count once and then
+/// hashing once more for each count after it, so this is no slower than
+/// calculating the highest count alone.
+pub fn write_sheet <W: fmt::Write> (
+    w: &mut W,
+    alg: HashAlg,
+    passphrase: &str,
+    seed: &str,
+    counts: RangeInclusive<usize>,
+    options: &SheetOptions,
+) -> Result<(), SheetError> {
+    let (low, high) = (*counts.start(), *counts.end());
+    crate::otp_span!("write_sheet", alg = alg.name(), low, high);
+    if low > high {
+        return Ok(());
+    }
+    let otps = calculate_range(alg, passphrase, seed, low, high).map_err(SheetError::Calculate)?;
+
+    let columns = options.columns.max(1);
+    let total_rows = otps.len().div_ceil(columns);
+    let rows_per_page = if options.rows_per_page == 0 { total_rows } else { options.rows_per_page };
+    let page_size = rows_per_page.checked_mul(columns).ok_or(SheetError::PageTooLarge)?;
+    let count_width = high.checked_ilog10().unwrap_or(0) as usize + 1;
+    let page_starts = (0..otps.len()).step_by(page_size);
+    for (page_index, (start, page)) in page_starts.zip(otps.chunks(page_size)).enumerate() {
+        if page_index > 0 {
+            w.write_char('\x0C')?;
+        }
+        if options.header {
+            writeln!(w, "otp-{} {}", alg, seed)?;
+            w.write_char('\n')?;
+        }
+        let rows = page.len().div_ceil(columns);
+        let first_count = high - start;
+        for row in 0..rows {
+            let cells: Vec<usize> = (0..columns)
+                .map(|col| col * rows + row)
+                .take_while(|i| *i < page.len())
+                .collect();
+            for (j, i) in cells.iter().enumerate() {
+                write_cell(w, first_count - i, count_width, &page[*i], options.hex, j + 1 == cells.len())?;
+            }
+            w.write_char('\n')?;
+        }
+    }
+    Ok(())
+}
+
+/// Format a printable sheet of the OTP values for each count in `counts`. See
+/// [write_sheet].
+pub fn format_sheet (
+    alg: HashAlg,
+    passphrase: &str,
+    seed: &str,
+    counts: RangeInclusive<usize>,
+    options: &SheetOptions,
+) -> Result<String, SheetError> {
+    let mut s = String::new();
+    write_sheet(&mut s, alg, passphrase, seed, counts, options)?;
+    Ok(s)
+}
+
+/// One OTP value of an [OtpList]. Its `Debug` output does not include the
+/// OTP value, unless it is wrapped in [Reveal](crate::Reveal) or the
+/// `unredacted-debug` feature is enabled, since unused values are still valid.
+#[derive(Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct OtpListEntry {
+    /// The count of the OTP value.
+    pub count: usize,
+    /// The OTP value.
+    pub otp: [u8; 8],
+    /// Whether the value has been used, or can no longer be used, since a
+    /// lower count has been.
+    pub used: bool,
+}
+
+/// A pre-generated list of OTP values, as printed on a sheet, that keeps track
+/// of which have been used, so that a client can store it between logins and
+/// warn when few remain. With the `serde` feature, it can be serialized. Its
+/// `Debug` output does not include the OTP values, as with [OtpListEntry].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct OtpList {
+    alg: HashAlg,
+    seed: String,
+    /// Highest count first.
+    entries: Vec<OtpListEntry>,
+}
+
+impl OtpListEntry {
+
+    fn fmt_redacted (&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OtpListEntry")
+            .field("count", &self.count)
+            .field("otp", &format_args!("{}", REDACTED))
+            .field("used", &self.used)
+            .finish()
+    }
+
+}
+
+impl RevealDebug for OtpListEntry {
+
+    fn fmt_revealed (&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OtpListEntry")
+            .field("count", &self.count)
+            .field("otp", &self.otp)
+            .field("used", &self.used)
+            .finish()
+    }
+
+}
+
+redacted_debug!(OtpListEntry);
+
+impl OtpList {
+
+    /// Generate the OTP values for each count in `counts`, none of them used.
+    pub fn generate (
+        alg: HashAlg,
+        passphrase: &str,
+        seed: &str,
+        counts: RangeInclusive<usize>,
+    ) -> Result<OtpList, CalculateError> {
+        let (low, high) = (*counts.start(), *counts.end());
+        let otps = if low > high { Vec::new() } else { calculate_range(alg, passphrase, seed, low, high)? };
+        let entries = otps.into_iter()
+            .zip((low..=high).rev())
+            .map(|(otp, count)| OtpListEntry { count, otp, used: false })
+            .collect();
+        Ok(OtpList { alg, seed: String::from(seed), entries })
+    }
+
+    /// The hash algorithm of the list.
+    pub fn alg (&self) -> HashAlg {
+        self.alg
+    }
+
+    /// The seed of the list.
+    pub fn seed (&self) -> &str {
+        &self.seed
+    }
+
+    /// Every entry, highest count first.
+    pub fn entries (&self) -> &[OtpListEntry] {
+        &self.entries
+    }
+
+    /// The entry for `count`, if it is in the list.
+    pub fn get (&self, count: usize) -> Option<&OtpListEntry> {
+        self.entries.iter().find(|e| e.count == count)
+    }
+
+    /// Mark the entry for `count` as used, along with every entry for a higher
+    /// count, since the server will never challenge for those again. Returns
+    /// the OTP value, or `None` if `count` is not in the list or was already
+    /// used.
+    pub fn take (&mut self, count: usize) -> Option<[u8; 8]> {
+        let i = self.entries.iter().position(|e| e.count == count && !e.used)?;
+        for e in self.entries[..=i].iter_mut() {
+            e.used = true;
+        }
+        Some(self.entries[i].otp)
+    }
+
+    /// Take the OTP value for `challenge`, as [OtpList::take] does, if its
+    /// algorithm and seed are those of this list. Seeds are compared
+    /// case-insensitively, since they are lowercased before hashing.
+    #[cfg(feature = "parsing-challenge")]
+    pub fn respond (&mut self, challenge: &OTPChallenge<'_>) -> Option<[u8; 8]> {
+        if challenge.hash_alg != self.alg.name() || !challenge.seed.eq_ignore_ascii_case(&self.seed) {
+            return None;
+        }
+        self.take(challenge.hash_count)
+    }
+
+    /// The number of entries that have not been used.
+    pub fn remaining (&self) -> usize {
+        self.entries.iter().filter(|e| !e.used).count()
+    }
+
+    /// Whether no more than `threshold` entries remain unused, so that the
+    /// user should be told to generate a new list or re-initialize.
+    pub fn is_low (&self, threshold: usize) -> bool {
+        self.remaining() <= threshold
+    }
+
+}
+
+#[cfg(all(test, feature = "md5"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_sheets () {
+        let options = SheetOptions { columns: 2, hex: false, rows_per_page: 0, header: true };
+        let sheet = format_sheet(HashAlg::Md5, "This is a test.", "TeSt", 0..=2, &options).unwrap();
+        assert_eq!(sheet, "otp-md5 TeSt\n\
+            \n\
+            2: THY AVON NO NECK COKE MOLL       0: INCH SEA ANNE LONG AHEM TOUR\n\
+            1: EASE OIL FUM CURE AWRY AVIS\n");
+        let options = SheetOptions { columns: 1, hex: true, rows_per_page: 2, header: false };
+        let sheet = format_sheet(HashAlg::Md5, "This is a test.", "TeSt", 0..=2, &options).unwrap();
+        assert_eq!(sheet, "2: 4049 F8B1 6166 9B7B\n\
+            1: 7965 E054 36F5 029F\n\
+            \x0C0: 9E87 6134 D904 99DD\n");
+        assert_eq!(
+            format_sheet(HashAlg::Md5, "This is a test.", "TeSt", 0..=DEFAULT_MAX_COUNT + 1, &options),
+            Err(SheetError::Calculate(CalculateError::CountTooLarge(DEFAULT_MAX_COUNT))),
+        );
+        let options = SheetOptions { columns: usize::MAX, hex: false, rows_per_page: 2, header: false };
+        assert_eq!(
+            format_sheet(HashAlg::Md5, "This is a test.", "TeSt", 0..=2, &options),
+            Err(SheetError::PageTooLarge),
+        );
+        let options = SheetOptions { columns: usize::MAX, hex: false, rows_per_page: 0, header: false };
+        let sheet = format_sheet(HashAlg::Md5, "This is a test.", "TeSt", 0..=1, &options).unwrap();
+        assert_eq!(sheet, "1: EASE OIL FUM CURE AWRY AVIS      0: INCH SEA ANNE LONG AHEM TOUR\n");
+    }
+
+    #[test]
+    #[cfg(not(feature = "unredacted-debug"))]
+    fn does_not_print_secrets () {
+        use alloc::format;
+        let list = OtpList::generate(HashAlg::Md5, "This is a test.", "TeSt", 0..=1).unwrap();
+        let debug = format!("{:?}", list);
+        assert!(debug.contains("OtpListEntry { count: 0, otp: ********, used: false }"));
+        for entry in list.entries() {
+            assert!(!debug.contains(&format!("{:?}", entry.otp)));
+            assert!(format!("{:?}", entry.reveal()).contains(&format!("{:?}", entry.otp)));
+        }
+    }
+
+    #[test]
+    fn tracks_used_list_entries () {
+        let mut list = OtpList::generate(HashAlg::Md5, "This is a test.", "TeSt", 0..=2).unwrap();
+        assert_eq!(list.entries().iter().map(|e| e.count).collect::<Vec<_>>(), [ 2, 1, 0 ]);
+        assert_eq!(list.get(0).unwrap().otp, [ 0x9E, 0x87, 0x61, 0x34, 0xD9, 0x04, 0x99, 0xDD ]);
+        assert_eq!(list.remaining(), 3);
+        assert_eq!(list.take(1), Some([ 0x79, 0x65, 0xE0, 0x54, 0x36, 0xF5, 0x02, 0x9F ]));
+        assert!(list.get(2).unwrap().used);
+        assert_eq!(list.take(1), None);
+        assert_eq!(list.take(3), None);
+        assert_eq!(list.remaining(), 1);
+        assert!(list.is_low(1));
+        assert!(!list.is_low(0));
+        #[allow(clippy::reversed_empty_ranges)]
+        let empty = OtpList::generate(HashAlg::Md5, "This is a test.", "TeSt", 2..=1).unwrap();
+        assert!(empty.entries().is_empty());
+    }
+
+    #[test]
+    #[cfg(feature = "parsing-challenge")]
+    fn responds_to_challenges_from_lists () {
+        use crate::parse_otp_challenge;
+        let mut list = OtpList::generate(HashAlg::Md5, "This is a test.", "TeSt", 0..=2).unwrap();
+        assert_eq!(list.respond(&parse_otp_challenge("otp-sha1 0 test").unwrap()), None);
+        assert_eq!(list.respond(&parse_otp_challenge("otp-md5 0 other").unwrap()), None);
+        assert_eq!(list.respond(&parse_otp_challenge("otp-md5 0 test").unwrap()), list.get(0).map(|e| e.otp));
+        assert_eq!(list.remaining(), 0);
+    }
+
+}
@@ -0,0 +1,44 @@
+//! `proptest` strategies for generating valid OTP inputs, for property-testing
+//! code that uses this library.
+use alloc::string::String;
+use proptest::prelude::*;
+use crate::HashAlg;
+
+/// Generate seeds that are valid per Section 6.0 of
+/// [IETF RFC 2289](https://www.rfc-editor.org/rfc/rfc2289.html): one to
+/// sixteen alphanumeric characters.
+pub fn seed () -> impl Strategy<Value = String> {
+    "[A-Za-z0-9]{1,16}"
+}
+
+/// Generate passphrases that are valid per Section 6.0 of
+/// [IETF RFC 2289](https://www.rfc-editor.org/rfc/rfc2289.html): ten to
+/// sixty-three printable ASCII characters.
+pub fn passphrase () -> impl Strategy<Value = String> {
+    "[ -~]{10,63}"
+}
+
+/// Generate any hash algorithm.
+pub fn hash_alg () -> impl Strategy<Value = HashAlg> {
+    proptest::sample::select(&HashAlg::ALL[..])
+}
+
+/// Generate any 64-bit OTP value.
+pub fn otp () -> impl Strategy<Value = [u8; 8]> {
+    any::<[u8; 8]>()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    proptest! {
+        #[test]
+        fn generates_valid_seeds_and_passphrases (s in seed(), p in passphrase()) {
+            prop_assert!((1..=16).contains(&s.len()));
+            prop_assert!(s.bytes().all(|b| b.is_ascii_alphanumeric()));
+            prop_assert!((10..=63).contains(&p.len()));
+        }
+    }
+
+}
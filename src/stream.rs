@@ -0,0 +1,246 @@
+//! Incremental parsing of OTP responses, for protocols such as SMTP and IMAP
+//! in which a response may arrive split across several continuation lines.
+//! Feed each line to a [ResponseParser] as it arrives, and it will report when
+//! a complete response has been received.
+use core::fmt;
+use crate::{OTPResponse, parse_otp_response, HashAlg, MAX_SEED_LEN};
+
+/// The length of six words of four letters, separated by spaces.
+const MAX_WORDS_LEN: usize = 6 * 4 + 5;
+
+/// The length of the longest algorithm name that this crate knows.
+const MAX_ALG_NAME_LEN: usize = {
+    let mut len = 0;
+    let mut i = 0;
+    while i < HashAlg::ALL.len() {
+        let name_len = HashAlg::ALL[i].name().len();
+        if name_len > len {
+            len = name_len;
+        }
+        i += 1;
+    }
+    len
+};
+
+/// The longest init response, in bytes, allowed by the grammar of
+/// [IETF RFC 2243](https://www.rfc-editor.org/rfc/rfc2243), without any
+/// extension fields: `init-word:`, six words of four letters for each OTP
+/// value, the longest algorithm name that this crate knows, a count of up to
+/// twenty digits, and a seed of [MAX_SEED_LEN] characters, separated by single
+/// spaces and colons. Hexadecimal responses and responses for the current
+/// sequence are shorter.
+pub const MAX_INIT_RESPONSE_LEN: usize = "init-word:".len() + 2 * MAX_WORDS_LEN + MAX_ALG_NAME_LEN + 20 + MAX_SEED_LEN + 4;
+
+/// The most bytes of `:`-separated extension fields, including the first `:`,
+/// that may follow the new OTP value of an init response, as kept in
+/// [OTPInit::extra](crate::OTPInit::extra).
+pub const MAX_EXTENSION_LEN: usize = 64;
+
+/// The longest response, in bytes, that [ResponseParser] will buffer: an init
+/// response of [MAX_INIT_RESPONSE_LEN] bytes with [MAX_EXTENSION_LEN] bytes of
+/// extension fields. This is the same limit that [parse_otp_response]
+/// enforces.
+pub const MAX_RESPONSE_LEN: usize = MAX_INIT_RESPONSE_LEN + MAX_EXTENSION_LEN;
+
+const PREFIXES: [&str; 4] = [ "hex:", "word:", "init-hex:", "init-word:" ];
+
+/// An error parsing a response with a [ResponseParser].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum StreamError {
+    /// The response exceeded [MAX_RESPONSE_LEN] bytes.
+    TooLong,
+    /// The response is not syntactically valid.
+    Malformed,
+}
+
+impl fmt::Display for StreamError {
+
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StreamError::TooLong => f.write_str("OTP response too long"),
+            StreamError::Malformed => f.write_str("malformed OTP response"),
+        }
+    }
+
+}
+
+/// The result of feeding a line to a [ResponseParser].
+#[derive(Debug, PartialEq, Eq)]
+pub enum Status <'a> {
+    /// The response is incomplete so far.
+    NeedMore,
+    /// A complete response was received.
+    Done(OTPResponse<'a>),
+    /// The response is invalid. Feeding more input will not fix it.
+    Error(StreamError),
+}
+
+/// A push-based parser for OTP responses that arrive over several lines.
+///
+/// Each call to [ResponseParser::feed] takes one line, with or without its
+/// line ending. The lines are trimmed and joined with single spaces, except
+/// after a `:`, so words must not be split across lines. The response is
+/// complete once it contains sixteen hex digits or six words after its last
+/// `:`. After a response is done or an error occurs, call
+/// [ResponseParser::reset] before parsing another one.
+///
+/// This does not allocate: the response is buffered inline.
+#[derive(Clone)]
+pub struct ResponseParser {
+    buf: [u8; MAX_RESPONSE_LEN],
+    len: usize,
+    error: Option<StreamError>,
+}
+
+impl ResponseParser {
+
+    /// Create a new parser with nothing buffered.
+    pub const fn new () -> Self {
+        ResponseParser { buf: [0; MAX_RESPONSE_LEN], len: 0, error: None }
+    }
+
+    /// Discard anything buffered so that another response can be parsed.
+    pub fn reset (&mut self) {
+        self.len = 0;
+        self.error = None;
+    }
+
+    fn buffered (&self) -> &str {
+        // Only whole `&str`s and spaces are ever copied into the buffer.
+        core::str::from_utf8(&self.buf[..self.len]).unwrap_or_default()
+    }
+
+    fn push (&mut self, s: &str) -> Result<(), StreamError> {
+        let end = self.len + s.len();
+        if end > MAX_RESPONSE_LEN {
+            return Err(StreamError::TooLong);
+        }
+        self.buf[self.len..end].copy_from_slice(s.as_bytes());
+        self.len = end;
+        Ok(())
+    }
+
+    fn append (&mut self, line: &str) -> Result<(), StreamError> {
+        let line = line.trim_matches(|c: char| c.is_ascii_whitespace());
+        if line.is_empty() {
+            return Ok(());
+        }
+        if self.len > 0 && self.buf[self.len - 1] != b':' {
+            self.push(" ")?;
+        }
+        self.push(line)?;
+        let buffered = self.buffered();
+        if !PREFIXES.iter().any(|p| buffered.starts_with(p) || p.starts_with(buffered)) {
+            return Err(StreamError::Malformed);
+        }
+        Ok(())
+    }
+
+    fn is_complete (&self) -> bool {
+        let buffered = self.buffered();
+        let Some(prefix) = PREFIXES.iter().find(|p| buffered.starts_with(*p)) else {
+            return false;
+        };
+        let rest = &buffered[prefix.len()..];
+        let last = if prefix.starts_with("init-") {
+            let mut sections = rest.split(':');
+            match (sections.next(), sections.next(), sections.next()) {
+                (Some(_), Some(_), Some(new_otp)) => new_otp,
+                _ => return false,
+            }
+        } else {
+            rest
+        };
+        if prefix.ends_with("hex:") {
+            last.bytes().filter(u8::is_ascii_hexdigit).count() >= 16
+        } else {
+            last.split_ascii_whitespace().count() >= 6
+        }
+    }
+
+    /// Feed the next line of the response.
+    pub fn feed (&mut self, line: &str) -> Status<'_> {
+        if let Some(e) = self.error {
+            return Status::Error(e);
+        }
+        if let Err(e) = self.append(line) {
+            self.error = Some(e);
+            return Status::Error(e);
+        }
+        if !self.is_complete() {
+            return Status::NeedMore;
+        }
+        // Parsed twice, since the borrow checker cannot yet see that recording
+        // the error does not overlap with a returned response.
+        if parse_otp_response(self.buffered()).is_none() {
+            self.error = Some(StreamError::Malformed);
+            return Status::Error(StreamError::Malformed);
+        }
+        parse_otp_response(self.buffered()).map_or(Status::Error(StreamError::Malformed), Status::Done)
+    }
+
+}
+
+impl Default for ResponseParser {
+
+    fn default () -> Self {
+        ResponseParser::new()
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::{HexOrWords, OTPInit};
+
+    #[test]
+    fn parses_responses_split_across_lines () {
+        let mut parser = ResponseParser::new();
+        assert_eq!(parser.feed("hex:5Bf0 75d9\r\n"), Status::NeedMore);
+        assert_eq!(parser.feed("  959d 036f\r\n"), Status::Done(OTPResponse::Current(
+            HexOrWords::Hex([ 0x5B, 0xf0, 0x75, 0xd9, 0x95, 0x9d, 0x03, 0x6f ]),
+        )));
+
+        parser.reset();
+        assert_eq!(parser.feed("word:"), Status::NeedMore);
+        assert_eq!(parser.feed("INCH SEA ANNE"), Status::NeedMore);
+        assert_eq!(parser.feed(""), Status::NeedMore);
+        assert_eq!(parser.feed("LONG AHEM TOUR"), Status::Done(OTPResponse::Current(
+            HexOrWords::Words("INCH SEA ANNE LONG AHEM TOUR"),
+        )));
+
+        parser.reset();
+        assert_eq!(parser.feed("init-word:INCH SEA ANNE LONG AHEM TOUR:md5 499"), Status::NeedMore);
+        assert_eq!(parser.feed("ke1235:GLOB"), Status::NeedMore);
+        assert_eq!(parser.feed("SOFT CALL ARCH FLEW FREY"), Status::Done(OTPResponse::Init(OTPInit {
+            current_otp: HexOrWords::Words("INCH SEA ANNE LONG AHEM TOUR"),
+            new_otp: HexOrWords::Words("GLOB SOFT CALL ARCH FLEW FREY"),
+            new_alg: "md5",
+            new_seq_num: 499,
+            new_seed: "ke1235",
+            extra: None,
+        })));
+    }
+
+    #[test]
+    fn rejects_invalid_responses () {
+        let mut parser = ResponseParser::new();
+        assert_eq!(parser.feed("hax:5Bf0"), Status::Error(StreamError::Malformed));
+        assert_eq!(parser.feed("959d 036f"), Status::Error(StreamError::Malformed));
+
+        parser.reset();
+        assert_eq!(parser.feed("hex:5Bf0 75d9 959d 036g 0000"), Status::Error(StreamError::Malformed));
+
+        parser.reset();
+        assert_eq!(parser.feed("init-word:"), Status::NeedMore);
+        assert_eq!(parser.feed("HA"), Status::NeedMore);
+        while parser.len + " HA".len() <= MAX_RESPONSE_LEN {
+            assert_eq!(parser.feed("HA"), Status::NeedMore);
+        }
+        assert_eq!(parser.feed("HA"), Status::Error(StreamError::TooLong));
+    }
+
+}
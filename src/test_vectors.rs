@@ -0,0 +1,284 @@
+//! The official test vectors from Appendix C of
+//! [IETF RFC 2289](https://www.rfc-editor.org/rfc/rfc2289.html), for use in
+//! the tests of OTP clients and servers built using this library.
+use crate::HashAlg;
+
+/// A single test vector from Appendix C of
+/// [IETF RFC 2289](https://www.rfc-editor.org/rfc/rfc2289.html).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TestVector {
+    /// The hash algorithm.
+    pub alg: HashAlg,
+    /// The passphrase, or secret.
+    pub passphrase: &'static str,
+    /// The seed, in the case in which it appears in the RFC.
+    pub seed: &'static str,
+    /// The hash count.
+    pub count: usize,
+    /// The expected OTP value.
+    pub otp: [u8; 8],
+    /// The OTP in hex, as it appears in the RFC.
+    pub hex: &'static str,
+    /// The OTP as six words, as it appears in the RFC.
+    pub words: &'static str,
+}
+
+/// The MD4 test vectors.
+pub const MD4: [TestVector; 9] = [
+    TestVector {
+        alg: HashAlg::Md4,
+        passphrase: "This is a test.",
+        seed: "TeSt",
+        count: 0,
+        otp: [ 0xD1, 0x85, 0x42, 0x18, 0xEB, 0xBB, 0x0B, 0x51 ],
+        hex: "D185 4218 EBBB 0B51",
+        words: "ROME MUG FRED SCAN LIVE LACE",
+    },
+    TestVector {
+        alg: HashAlg::Md4,
+        passphrase: "This is a test.",
+        seed: "TeSt",
+        count: 1,
+        otp: [ 0x63, 0x47, 0x3E, 0xF0, 0x1C, 0xD0, 0xB4, 0x44 ],
+        hex: "6347 3EF0 1CD0 B444",
+        words: "CARD SAD MINI RYE COL KIN",
+    },
+    TestVector {
+        alg: HashAlg::Md4,
+        passphrase: "This is a test.",
+        seed: "TeSt",
+        count: 99,
+        otp: [ 0xC5, 0xE6, 0x12, 0x77, 0x6E, 0x6C, 0x23, 0x7A ],
+        hex: "C5E6 1277 6E6C 237A",
+        words: "NOTE OUT IBIS SINK NAVE MODE",
+    },
+    TestVector {
+        alg: HashAlg::Md4,
+        passphrase: "AbCdEfGhIjK",
+        seed: "alpha1",
+        count: 0,
+        otp: [ 0x50, 0x07, 0x6F, 0x47, 0xEB, 0x1A, 0xDE, 0x4E ],
+        hex: "5007 6F47 EB1A DE4E",
+        words: "AWAY SEN ROOK SALT LICE MAP",
+    },
+    TestVector {
+        alg: HashAlg::Md4,
+        passphrase: "AbCdEfGhIjK",
+        seed: "alpha1",
+        count: 1,
+        otp: [ 0x65, 0xD2, 0x0D, 0x19, 0x49, 0xB5, 0xF7, 0xAB ],
+        hex: "65D2 0D19 49B5 F7AB",
+        words: "CHEW GRIM WU HANG BUCK SAID",
+    },
+    TestVector {
+        alg: HashAlg::Md4,
+        passphrase: "AbCdEfGhIjK",
+        seed: "alpha1",
+        count: 99,
+        otp: [ 0xD1, 0x50, 0xC8, 0x2C, 0xCE, 0x6F, 0x62, 0xD1 ],
+        hex: "D150 C82C CE6F 62D1",
+        words: "ROIL FREE COG HUNK WAIT COCA",
+    },
+    TestVector {
+        alg: HashAlg::Md4,
+        passphrase: "OTP's are good",
+        seed: "correct",
+        count: 0,
+        otp: [ 0x84, 0x9C, 0x79, 0xD4, 0xF6, 0xF5, 0x53, 0x88 ],
+        hex: "849C 79D4 F6F5 5388",
+        words: "FOOL STEM DONE TOOL BECK NILE",
+    },
+    TestVector {
+        alg: HashAlg::Md4,
+        passphrase: "OTP's are good",
+        seed: "correct",
+        count: 1,
+        otp: [ 0x8C, 0x09, 0x92, 0xFB, 0x25, 0x08, 0x47, 0xB1 ],
+        hex: "8C09 92FB 2508 47B1",
+        words: "GIST AMOS MOOT AIDS FOOD SEEM",
+    },
+    TestVector {
+        alg: HashAlg::Md4,
+        passphrase: "OTP's are good",
+        seed: "correct",
+        count: 99,
+        otp: [ 0x3F, 0x3B, 0xF4, 0xB4, 0x14, 0x5F, 0xD7, 0x4B ],
+        hex: "3F3B F4B4 145F D74B",
+        words: "TAG SLOW NOV MIN WOOL KENO",
+    },
+];
+
+/// The MD5 test vectors.
+pub const MD5: [TestVector; 9] = [
+    TestVector {
+        alg: HashAlg::Md5,
+        passphrase: "This is a test.",
+        seed: "TeSt",
+        count: 0,
+        otp: [ 0x9E, 0x87, 0x61, 0x34, 0xD9, 0x04, 0x99, 0xDD ],
+        hex: "9E87 6134 D904 99DD",
+        words: "INCH SEA ANNE LONG AHEM TOUR",
+    },
+    TestVector {
+        alg: HashAlg::Md5,
+        passphrase: "This is a test.",
+        seed: "TeSt",
+        count: 1,
+        otp: [ 0x79, 0x65, 0xE0, 0x54, 0x36, 0xF5, 0x02, 0x9F ],
+        hex: "7965 E054 36F5 029F",
+        words: "EASE OIL FUM CURE AWRY AVIS",
+    },
+    TestVector {
+        alg: HashAlg::Md5,
+        passphrase: "This is a test.",
+        seed: "TeSt",
+        count: 99,
+        otp: [ 0x50, 0xFE, 0x19, 0x62, 0xC4, 0x96, 0x58, 0x80 ],
+        hex: "50FE 1962 C496 5880",
+        words: "BAIL TUFT BITS GANG CHEF THY",
+    },
+    TestVector {
+        alg: HashAlg::Md5,
+        passphrase: "AbCdEfGhIjK",
+        seed: "alpha1",
+        count: 0,
+        otp: [ 0x87, 0x06, 0x6D, 0xD9, 0x64, 0x4B, 0xF2, 0x06 ],
+        hex: "8706 6DD9 644B F206",
+        words: "FULL PEW DOWN ONCE MORT ARC",
+    },
+    TestVector {
+        alg: HashAlg::Md5,
+        passphrase: "AbCdEfGhIjK",
+        seed: "alpha1",
+        count: 1,
+        otp: [ 0x7C, 0xD3, 0x4C, 0x10, 0x40, 0xAD, 0xD1, 0x4B ],
+        hex: "7CD3 4C10 40AD D14B",
+        words: "FACT HOOF AT FIST SITE KENT",
+    },
+    TestVector {
+        alg: HashAlg::Md5,
+        passphrase: "AbCdEfGhIjK",
+        seed: "alpha1",
+        count: 99,
+        otp: [ 0x5A, 0xA3, 0x7A, 0x81, 0xF2, 0x12, 0x14, 0x6C ],
+        hex: "5AA3 7A81 F212 146C",
+        words: "BODE HOP JAKE STOW JUT RAP",
+    },
+    TestVector {
+        alg: HashAlg::Md5,
+        passphrase: "OTP's are good",
+        seed: "correct",
+        count: 0,
+        otp: [ 0xF2, 0x05, 0x75, 0x39, 0x43, 0xDE, 0x4C, 0xF9 ],
+        hex: "F205 7539 43DE 4CF9",
+        words: "ULAN NEW ARMY FUSE SUIT EYED",
+    },
+    TestVector {
+        alg: HashAlg::Md5,
+        passphrase: "OTP's are good",
+        seed: "correct",
+        count: 1,
+        otp: [ 0xDD, 0xCD, 0xAC, 0x95, 0x6F, 0x23, 0x49, 0x37 ],
+        hex: "DDCD AC95 6F23 4937",
+        words: "SKIM CULT LOB SLAM POE HOWL",
+    },
+    TestVector {
+        alg: HashAlg::Md5,
+        passphrase: "OTP's are good",
+        seed: "correct",
+        count: 99,
+        otp: [ 0xB2, 0x03, 0xE2, 0x8F, 0xA5, 0x25, 0xBE, 0x47 ],
+        hex: "B203 E28F A525 BE47",
+        words: "LONG IVY JULY AJAR BOND LEE",
+    },
+];
+
+/// The SHA1 test vectors.
+pub const SHA1: [TestVector; 9] = [
+    TestVector {
+        alg: HashAlg::Sha1,
+        passphrase: "This is a test.",
+        seed: "TeSt",
+        count: 0,
+        otp: [ 0xBB, 0x9E, 0x6A, 0xE1, 0x97, 0x9D, 0x8F, 0xF4 ],
+        hex: "BB9E 6AE1 979D 8FF4",
+        words: "MILT VARY MAST OK SEES WENT",
+    },
+    TestVector {
+        alg: HashAlg::Sha1,
+        passphrase: "This is a test.",
+        seed: "TeSt",
+        count: 1,
+        otp: [ 0x63, 0xD9, 0x36, 0x63, 0x97, 0x34, 0x38, 0x5B ],
+        hex: "63D9 3663 9734 385B",
+        words: "CART OTTO HIVE ODE VAT NUT",
+    },
+    TestVector {
+        alg: HashAlg::Sha1,
+        passphrase: "This is a test.",
+        seed: "TeSt",
+        count: 99,
+        otp: [ 0x87, 0xFE, 0xC7, 0x76, 0x8B, 0x73, 0xCC, 0xF9 ],
+        hex: "87FE C776 8B73 CCF9",
+        words: "GAFF WAIT SKID GIG SKY EYED",
+    },
+    TestVector {
+        alg: HashAlg::Sha1,
+        passphrase: "AbCdEfGhIjK",
+        seed: "alpha1",
+        count: 0,
+        otp: [ 0xAD, 0x85, 0xF6, 0x58, 0xEB, 0xE3, 0x83, 0xC9 ],
+        hex: "AD85 F658 EBE3 83C9",
+        words: "LEST OR HEEL SCOT ROB SUIT",
+    },
+    TestVector {
+        alg: HashAlg::Sha1,
+        passphrase: "AbCdEfGhIjK",
+        seed: "alpha1",
+        count: 1,
+        otp: [ 0xD0, 0x7C, 0xE2, 0x29, 0xB5, 0xCF, 0x11, 0x9B ],
+        hex: "D07C E229 B5CF 119B",
+        words: "RITE TAKE GELD COST TUNE RECK",
+    },
+    TestVector {
+        alg: HashAlg::Sha1,
+        passphrase: "AbCdEfGhIjK",
+        seed: "alpha1",
+        count: 99,
+        otp: [ 0x27, 0xBC, 0x71, 0x03, 0x5A, 0xAF, 0x3D, 0xC6 ],
+        hex: "27BC 7103 5AAF 3DC6",
+        words: "MAY STAR TIN LYON VEDA STAN",
+    },
+    TestVector {
+        alg: HashAlg::Sha1,
+        passphrase: "OTP's are good",
+        seed: "correct",
+        count: 0,
+        otp: [ 0xD5, 0x1F, 0x3E, 0x99, 0xBF, 0x8E, 0x6F, 0x0B ],
+        hex: "D51F 3E99 BF8E 6F0B",
+        words: "RUST WELT KICK FELL TAIL FRAU",
+    },
+    TestVector {
+        alg: HashAlg::Sha1,
+        passphrase: "OTP's are good",
+        seed: "correct",
+        count: 1,
+        otp: [ 0x82, 0xAE, 0xB5, 0x2D, 0x94, 0x37, 0x74, 0xE4 ],
+        hex: "82AE B52D 9437 74E4",
+        words: "FLIT DOSE ALSO MEW DRUM DEFY",
+    },
+    TestVector {
+        alg: HashAlg::Sha1,
+        passphrase: "OTP's are good",
+        seed: "correct",
+        count: 99,
+        otp: [ 0x4F, 0x29, 0x6A, 0x74, 0xFE, 0x15, 0x67, 0xEC ],
+        hex: "4F29 6A74 FE15 67EC",
+        words: "AURA ALOE HURL WING BERG WAIT",
+    },
+];
+
+/// Every test vector, for all algorithms.
+pub fn all () -> impl Iterator<Item = &'static TestVector> {
+    MD4.iter().chain(MD5.iter()).chain(SHA1.iter())
+}
@@ -0,0 +1,93 @@
+//! Encoding OTP challenges as URIs, so that provisioning tools can show them
+//! as QR codes for a companion app to scan. The URIs look like this:
+//!
+//! ```text
+//! otp-skey://md5/ke1234?count=499
+//! ```
+//!
+//! That is: the `otp-skey` scheme, then the hash algorithm as the authority,
+//! the seed as the path, and the hash count as the `count` query parameter.
+//! The passphrase is never included. Since algorithm names and seeds are
+//! restricted to characters that need no escaping, there is no percent-encoding.
+use core::fmt;
+#[cfg(feature = "alloc")]
+use alloc::string::String;
+use crate::{OTPChallenge, is_valid_seed};
+
+/// The URI scheme used by [write_otp_uri] and [parse_otp_uri].
+pub const OTP_URI_SCHEME: &str = "otp-skey";
+
+/// Whether `alg` is a plausible algorithm name: one or more alphanumerics and
+/// hyphens, which need no escaping in URIs or quoted strings.
+pub(crate) fn is_valid_alg (alg: &str) -> bool {
+    !alg.is_empty() && alg.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-')
+}
+
+/// Write an OTP challenge as an `otp-skey://` URI.
+///
+/// Returns an error if the algorithm name contains anything other than
+/// alphanumerics and hyphens, or if the seed is not one to sixteen
+/// alphanumeric characters, since these could not be decoded unambiguously.
+pub fn write_otp_uri <W: fmt::Write> (w: &mut W, challenge: &OTPChallenge<'_>) -> fmt::Result {
+    if !is_valid_alg(challenge.hash_alg) || !is_valid_seed(challenge.seed) {
+        return Err(fmt::Error);
+    }
+    write!(w, "{}://{}/{}?count={}", OTP_URI_SCHEME, challenge.hash_alg, challenge.seed, challenge.hash_count)
+}
+
+/// Encode an OTP challenge as an `otp-skey://` URI, returning `None` under the
+/// same conditions that [write_otp_uri] returns an error.
+#[cfg(feature = "alloc")]
+pub fn otp_uri_string (challenge: &OTPChallenge<'_>) -> Option<String> {
+    let mut s = String::new();
+    write_otp_uri(&mut s, challenge).ok()?;
+    Some(s)
+}
+
+/// Parse an `otp-skey://` URI into an OTP challenge. The scheme is matched
+/// case-insensitively, and query parameters other than `count` are ignored.
+pub fn parse_otp_uri <'a> (uri: &'a str) -> Option<OTPChallenge<'a>> {
+    let (scheme, rest) = uri.split_once("://")?;
+    if !scheme.eq_ignore_ascii_case(OTP_URI_SCHEME) {
+        return None;
+    }
+    let (path, query) = rest.split_once('?')?;
+    let (hash_alg, seed) = path.split_once('/')?;
+    if !is_valid_alg(hash_alg) || !is_valid_seed(seed) {
+        return None;
+    }
+    let hash_count = query
+        .split('&')
+        .find_map(|param| param.strip_prefix("count="))?
+        .parse::<usize>()
+        .ok()?;
+    Some(OTPChallenge { hash_alg, hash_count, seed, capabilities: Default::default() })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn encodes_uris () {
+        let challenge = OTPChallenge { hash_alg: "md5", hash_count: 499, seed: "ke1234", capabilities: Default::default() };
+        let uri = otp_uri_string(&challenge).unwrap();
+        assert_eq!(uri, "otp-skey://md5/ke1234?count=499");
+        assert_eq!(parse_otp_uri(&uri), Some(challenge));
+        assert_eq!(otp_uri_string(&OTPChallenge { hash_alg: "md5", hash_count: 1, seed: "ke 1234", capabilities: Default::default() }), None);
+    }
+
+    #[test]
+    fn decodes_uris () {
+        assert_eq!(
+            parse_otp_uri("OTP-SKEY://x-sha256/TeSt?label=jdoe&count=7"),
+            Some(OTPChallenge { hash_alg: "x-sha256", hash_count: 7, seed: "TeSt", capabilities: Default::default() }),
+        );
+        assert_eq!(parse_otp_uri("otpauth://md5/ke1234?count=499"), None);
+        assert_eq!(parse_otp_uri("otp-skey://md5/ke1234"), None);
+        assert_eq!(parse_otp_uri("otp-skey://md5/ke1234?count=x"), None);
+        assert_eq!(parse_otp_uri("otp-skey://md5/ke1234/x?count=1"), None);
+    }
+
+}
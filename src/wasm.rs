@@ -0,0 +1,44 @@
+//! JavaScript bindings, via `wasm-bindgen`, for computing OTP responses in the
+//! browser. Everything goes in and out as strings, and OTP values are passed
+//! around as contiguous hexadecimal.
+use alloc::string::{String, ToString};
+use wasm_bindgen::prelude::*;
+use crate::bindings;
+pub use crate::bindings::Challenge;
+
+fn js_error (e: bindings::BindingError) -> JsError {
+    JsError::new(&e.to_string())
+}
+
+/// Parse an OTP challenge string.
+#[wasm_bindgen(js_name = parseChallenge)]
+pub fn parse_challenge (challenge: &str) -> Result<Challenge, JsError> {
+    bindings::parse_challenge(challenge).map_err(js_error)
+}
+
+/// Calculate an OTP value, returned as contiguous lowercase hexadecimal.
+#[wasm_bindgen(js_name = calculateOtp)]
+pub fn calculate_otp (alg: &str, passphrase: &str, seed: &str, count: u32) -> Result<String, JsError> {
+    bindings::calculate_otp(alg, passphrase, seed, count).map_err(js_error)
+}
+
+/// Calculate the response to an OTP challenge string, such as
+/// `otp-md5 499 ke1234`, as a complete `word:` or `hex:` response.
+#[wasm_bindgen(js_name = respondToChallenge)]
+pub fn respond_to_challenge (challenge: &str, passphrase: &str, words: bool) -> Result<String, JsError> {
+    bindings::respond_to_challenge(challenge, passphrase, words).map_err(js_error)
+}
+
+/// Convert an OTP value in hexadecimal to six words from the standard
+/// dictionary.
+#[wasm_bindgen(js_name = hexToWords)]
+pub fn hex_to_words (hex: &str) -> Result<String, JsError> {
+    bindings::hex_to_words(hex).map_err(js_error)
+}
+
+/// Convert six words from the standard dictionary to an OTP value in
+/// contiguous lowercase hexadecimal.
+#[wasm_bindgen(js_name = wordsToHex)]
+pub fn words_to_hex (words: &str) -> Result<String, JsError> {
+    bindings::words_to_hex(words).map_err(js_error)
+}
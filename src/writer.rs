@@ -0,0 +1,196 @@
+//! Formatting challenges and responses directly into caller-provided byte
+//! buffers, such as network buffers, so that high-throughput servers and
+//! clients need not build an intermediate `String` for every connection.
+//!
+//! With the `bytes` feature, the same can be written into any
+//! `bytes::BufMut`. Challenges are only written with the `parsing-response`
+//! feature, which servers enable.
+use core::fmt;
+#[cfg(feature = "words")]
+use crate::write_words;
+#[cfg(all(feature = "bytes", feature = "words"))]
+use crate::MAX_WORD_STRING_LEN;
+#[cfg(feature = "bytes")]
+use crate::MAX_HEX_STRING_LEN;
+#[cfg(feature = "parsing-response")]
+use crate::HashAlg;
+use crate::{write_hex, HexStyle};
+
+/// The maximum length of a challenge that [crate::parse_otp_challenge] will
+/// accept, and so the maximum length of a challenge written by
+/// [OtpResponseWriter::challenge] that is of any use.
+pub const MAX_CHALLENGE_LEN: usize = 128;
+
+/// The buffer did not have enough space remaining for the output. Nothing was
+/// written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BufferTooSmall;
+
+impl fmt::Display for BufferTooSmall {
+
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("buffer too small")
+    }
+
+}
+
+/// Writes challenges and responses one after another into a byte slice.
+///
+/// ```rust
+/// use rfc2289_otp::{HashAlg, HexStyle, OtpResponseWriter};
+/// let mut buf = [0u8; 64];
+/// let mut w = OtpResponseWriter::new(&mut buf);
+/// let otp = [ 0x9E, 0x87, 0x61, 0x34, 0xD9, 0x04, 0x99, 0xDD ];
+/// assert_eq!(w.hex(&otp, HexStyle::CANONICAL), Ok(23));
+/// assert_eq!(w.written(), b"hex:9E87 6134 D904 99DD");
+/// ```
+pub struct OtpResponseWriter <'a> {
+    buf: &'a mut [u8],
+    len: usize,
+}
+
+impl <'a> OtpResponseWriter <'a> {
+
+    /// Create a writer that writes to the start of `buf`.
+    pub fn new (buf: &'a mut [u8]) -> Self {
+        OtpResponseWriter { buf, len: 0 }
+    }
+
+    /// The number of bytes written so far.
+    pub fn len (&self) -> usize {
+        self.len
+    }
+
+    /// Whether nothing has been written so far.
+    pub fn is_empty (&self) -> bool {
+        self.len == 0
+    }
+
+    /// The bytes written so far.
+    pub fn written (&self) -> &[u8] {
+        &self.buf[..self.len]
+    }
+
+    /// Run `f`, returning the number of bytes it wrote, or undoing its writes
+    /// if it ran out of space.
+    fn write_with (&mut self, f: impl FnOnce(&mut Self) -> fmt::Result) -> Result<usize, BufferTooSmall> {
+        let start = self.len;
+        if f(self).is_err() {
+            self.len = start;
+            return Err(BufferTooSmall);
+        }
+        Ok(self.len - start)
+    }
+
+    /// Write a challenge, such as `otp-md5 499 ke1234`, returning its length.
+    #[cfg(feature = "parsing-response")]
+    pub fn challenge (&mut self, alg: HashAlg, count: usize, seed: &str) -> Result<usize, BufferTooSmall> {
+        self.write_with(|w| fmt::Write::write_fmt(w, format_args!("otp-{} {} {}", alg, count, seed)))
+    }
+
+    /// Write a response of six words from the standard dictionary, prefixed
+    /// with `word:`, returning its length.
+    #[cfg(feature = "words")]
+    pub fn words (&mut self, otp: &[u8; 8]) -> Result<usize, BufferTooSmall> {
+        self.write_with(|w| write_words(w, otp, true))
+    }
+
+    /// Write a response of hexadecimal in the given style, prefixed with
+    /// `hex:`, returning its length.
+    pub fn hex (&mut self, otp: &[u8; 8], style: HexStyle) -> Result<usize, BufferTooSmall> {
+        self.write_with(|w| write_hex(w, otp, style.with_prefix()))
+    }
+
+}
+
+impl fmt::Write for OtpResponseWriter<'_> {
+
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let end = self.len + s.len();
+        if end > self.buf.len() {
+            return Err(fmt::Error);
+        }
+        self.buf[self.len..end].copy_from_slice(s.as_bytes());
+        self.len = end;
+        Ok(())
+    }
+
+}
+
+/// Format into a stack buffer of `N` bytes using `f`, then copy the result into
+/// `buf` if it has room.
+#[cfg(feature = "bytes")]
+fn put_with <B: bytes::BufMut, const N: usize> (
+    buf: &mut B,
+    f: impl FnOnce(&mut OtpResponseWriter<'_>) -> Result<usize, BufferTooSmall>,
+) -> Result<usize, BufferTooSmall> {
+    let mut tmp = [0u8; N];
+    let mut w = OtpResponseWriter::new(&mut tmp);
+    let len = f(&mut w)?;
+    if buf.remaining_mut() < len {
+        return Err(BufferTooSmall);
+    }
+    buf.put_slice(w.written());
+    Ok(len)
+}
+
+/// Write a challenge into a `bytes::BufMut`, returning its length. Challenges
+/// longer than [MAX_CHALLENGE_LEN] are rejected.
+#[cfg(all(feature = "bytes", feature = "parsing-response"))]
+pub fn put_challenge <B: bytes::BufMut> (buf: &mut B, alg: HashAlg, count: usize, seed: &str) -> Result<usize, BufferTooSmall> {
+    put_with::<B, MAX_CHALLENGE_LEN>(buf, |w| w.challenge(alg, count, seed))
+}
+
+/// Write a `word:` response into a `bytes::BufMut`, returning its length.
+#[cfg(all(feature = "bytes", feature = "words"))]
+pub fn put_words <B: bytes::BufMut> (buf: &mut B, otp: &[u8; 8]) -> Result<usize, BufferTooSmall> {
+    put_with::<B, MAX_WORD_STRING_LEN>(buf, |w| w.words(otp))
+}
+
+/// Write a `hex:` response into a `bytes::BufMut`, returning its length.
+#[cfg(feature = "bytes")]
+pub fn put_hex <B: bytes::BufMut> (buf: &mut B, otp: &[u8; 8], style: HexStyle) -> Result<usize, BufferTooSmall> {
+    put_with::<B, MAX_HEX_STRING_LEN>(buf, |w| w.hex(otp, style))
+}
+
+#[cfg(all(test, feature = "words"))]
+mod tests {
+
+    use super::*;
+
+    const OTP: [u8; 8] = [ 0x9E, 0x87, 0x61, 0x34, 0xD9, 0x04, 0x99, 0xDD ];
+
+    #[test]
+    fn writes_into_slices () {
+        let mut buf = [0u8; 64];
+        let mut w = OtpResponseWriter::new(&mut buf);
+        assert_eq!(w.words(&OTP), Ok(33));
+        assert_eq!(w.written(), b"word:INCH SEA ANNE LONG AHEM TOUR");
+        assert_eq!(w.hex(&OTP, HexStyle::CONTIGUOUS_LOWERCASE), Ok(20));
+        assert_eq!(&w.written()[33..], b"hex:9e876134d90499dd");
+        // Nothing is written if there is not enough room.
+        assert_eq!(w.hex(&OTP, HexStyle::CANONICAL), Err(BufferTooSmall));
+        assert_eq!(w.len(), 53);
+    }
+
+    #[test]
+    #[cfg(feature = "parsing-response")]
+    fn writes_challenges () {
+        let mut buf = [0u8; 19];
+        let mut w = OtpResponseWriter::new(&mut buf);
+        assert_eq!(w.challenge(HashAlg::Sha1, 499, "ke1234"), Ok(19));
+        assert_eq!(w.written(), b"otp-sha1 499 ke1234");
+    }
+
+    #[test]
+    #[cfg(all(feature = "bytes", feature = "parsing-response"))]
+    fn writes_into_buf_muts () {
+        let mut buf = [0u8; 41];
+        let mut slice = &mut buf[..];
+        assert_eq!(put_challenge(&mut slice, HashAlg::Md5, 499, "ke1234"), Ok(18));
+        assert_eq!(put_words(&mut slice, &OTP), Err(BufferTooSmall));
+        assert_eq!(put_hex(&mut slice, &OTP, HexStyle::CANONICAL), Ok(23));
+        assert_eq!(&buf, b"otp-md5 499 ke1234hex:9E87 6134 D904 99DD");
+    }
+
+}